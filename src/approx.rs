@@ -0,0 +1,189 @@
+/*
+Approximate floating point comparisons used by the matrix and automatic differentiation types.
+*/
+
+// Absolute/relative tolerance comparison. Two values are considered equal if they differ by at
+// most `abs_tol`, or by at most `rel_tol` times the larger of their magnitudes.
+pub fn approx_eq_f64(a: f64, b: f64, abs_tol: f64, rel_tol: f64) -> bool {
+    if a == b {
+        // Also covers infinities of the same sign
+        return true;
+    }
+    if !a.is_finite() || !b.is_finite() {
+        return false;
+    }
+    let diff: f64 = (a - b).abs();
+    let largest: f64 = a.abs().max(b.abs());
+    return diff <= abs_tol || diff <= rel_tol * largest;
+}
+
+// Number of representable f64 values between a and b. Returns None if either value is NaN.
+pub fn ulp_distance(a: f64, b: f64) -> Option<u64> {
+    if a.is_nan() || b.is_nan() {
+        return None;
+    }
+    if a == b {
+        // Treats 0.0 and -0.0 as identical
+        return Some(0);
+    }
+
+    // Map the sign-magnitude bit pattern onto a monotonic integer line
+    let to_ordered = |x: f64| -> i64 {
+        let bits: i64 = x.to_bits() as i64;
+        if bits < 0 {
+            i64::MIN - bits
+        } else {
+            bits
+        }
+    };
+    let ia: i64 = to_ordered(a);
+    let ib: i64 = to_ordered(b);
+    return Some((ia as i128 - ib as i128).unsigned_abs() as u64);
+}
+
+pub fn ulps_eq_f64(a: f64, b: f64, max_ulps: u64) -> bool {
+    return match ulp_distance(a, b) {
+        Some(d) => d <= max_ulps,
+        None => false,
+    };
+}
+
+// Formats the entries of two matrices that are not approximately equal, one per line.
+// Returns None if the matrices match. Used by the assertion macros.
+#[doc(hidden)]
+pub fn matrix_mismatch_report(
+    left: &crate::lin_alg::mat::Matrix<f64>,
+    right: &crate::lin_alg::mat::Matrix<f64>,
+    is_eq: impl Fn(f64, f64) -> bool,
+) -> Option<String> {
+    if left.get_dim() != right.get_dim() {
+        return Some(format!(
+            "  dimension mismatch: left is {:?}, right is {:?}",
+            left.get_dim(),
+            right.get_dim()
+        ));
+    }
+
+    let (rows, cols) = left.get_dim();
+    let mut report: String = String::new();
+    for r in 0..rows {
+        for c in 0..cols {
+            let (a, b) = (left.get(r, c), right.get(r, c));
+            if !is_eq(a, b) {
+                report.push_str(&format!(
+                    "  ({}, {}): left = {:e}, right = {:e}, diff = {:e}\n",
+                    r,
+                    c,
+                    a,
+                    b,
+                    (a - b).abs()
+                ));
+            }
+        }
+    }
+
+    if report.is_empty() {
+        return None;
+    }
+    return Some(report);
+}
+
+// Asserts two matrices are equal within an absolute and relative tolerance, printing every
+// offending entry on failure.
+#[macro_export]
+macro_rules! assert_matrix_approx_eq {
+    ($left:expr, $right:expr, $abs_tol:expr, $rel_tol:expr $(,)?) => {{
+        let (abs_tol, rel_tol): (f64, f64) = ($abs_tol, $rel_tol);
+        if let Some(report) = $crate::approx::matrix_mismatch_report(&$left, &$right, |a, b| {
+            $crate::approx::approx_eq_f64(a, b, abs_tol, rel_tol)
+        }) {
+            panic!(
+                "assertion `left ≈ right` failed (abs_tol = {:e}, rel_tol = {:e})\n{}",
+                abs_tol, rel_tol, report
+            );
+        }
+    }};
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::assert_matrix_approx_eq!($left, $right, 1e-10, 1e-10)
+    };
+}
+
+// Asserts two matrices are equal to within a number of units in the last place.
+#[macro_export]
+macro_rules! assert_matrix_ulps_eq {
+    ($left:expr, $right:expr, $max_ulps:expr $(,)?) => {{
+        let max_ulps: u64 = $max_ulps;
+        if let Some(report) = $crate::approx::matrix_mismatch_report(&$left, &$right, |a, b| {
+            $crate::approx::ulps_eq_f64(a, b, max_ulps)
+        }) {
+            panic!(
+                "assertion `left ≈ right` failed (max_ulps = {})\n{}",
+                max_ulps, report
+            );
+        }
+    }};
+}
+
+// Asserts two DiffNum values (value and derivative) are equal within a tolerance.
+#[macro_export]
+macro_rules! assert_diffnum_approx_eq {
+    ($left:expr, $right:expr, $abs_tol:expr, $rel_tol:expr $(,)?) => {{
+        let (left, right) = ($left, $right);
+        if !left.approx_eq(&right, $abs_tol, $rel_tol) {
+            panic!(
+                "assertion `left ≈ right` failed\n  left: {}\n right: {}",
+                left, right
+            );
+        }
+    }};
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::assert_diffnum_approx_eq!($left, $right, 1e-10, 1e-10)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approx_eq_symmetric_in_sign() {
+        // A negative difference must be caught just like a positive one
+        assert!(!approx_eq_f64(1.0, 2.0, 1e-10, 0.0));
+        assert!(!approx_eq_f64(2.0, 1.0, 1e-10, 0.0));
+        assert!(approx_eq_f64(1.0, 1.0 + 1e-12, 1e-10, 0.0));
+        assert!(approx_eq_f64(1.0 + 1e-12, 1.0, 1e-10, 0.0));
+    }
+
+    #[test]
+    fn test_approx_eq_relative() {
+        assert!(approx_eq_f64(1e10, 1e10 + 1.0, 0.0, 1e-9));
+        assert!(!approx_eq_f64(1e10, 1e10 + 100.0, 0.0, 1e-9));
+    }
+
+    #[test]
+    fn test_approx_eq_non_finite() {
+        assert!(approx_eq_f64(f64::INFINITY, f64::INFINITY, 1e-10, 1e-10));
+        assert!(!approx_eq_f64(
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            1e-10,
+            1e-10
+        ));
+        assert!(!approx_eq_f64(f64::NAN, f64::NAN, 1e-10, 1e-10));
+    }
+
+    #[test]
+    fn test_ulp_distance() {
+        assert_eq!(ulp_distance(1.0, 1.0), Some(0));
+        assert_eq!(ulp_distance(0.0, -0.0), Some(0));
+        let next: f64 = f64::from_bits(1.0f64.to_bits() + 1);
+        assert_eq!(ulp_distance(1.0, next), Some(1));
+        assert_eq!(ulp_distance(next, 1.0), Some(1));
+        // Crossing zero counts every subnormal in between
+        let tiny: f64 = f64::from_bits(1);
+        assert_eq!(ulp_distance(-tiny, tiny), Some(2));
+        assert_eq!(ulp_distance(f64::NAN, 1.0), None);
+        assert!(ulps_eq_f64(0.1 + 0.2, 0.3, 1));
+        assert!(!ulps_eq_f64(0.1 + 0.2, 0.3, 0));
+    }
+}
@@ -1,5 +1,7 @@
 /* Automatic differentiation type */
 
+use crate::float::Float;
+
 pub struct DiffNum<T> {
     pub f: T,
     pub df: T,
@@ -204,51 +206,80 @@ impl<T: std::ops::Div<Output = T> + Copy> std::ops::Div<T> for DiffNum<T> {
     }
 }
 
-// Operations with scalars on the left
-impl std::ops::Add<DiffNum<f64>> for f64 {
-    type Output = DiffNum<f64>;
+// Nested DiffNums take f64 scalars on the right as well, which the impls above only allow for
+// the inner type
+macro_rules! impl_nested_scalar_right_op {
+    ($trait:ident, $method:ident, $op:tt) => {
+        impl<T: Float> std::ops::$trait<f64> for DiffNum<DiffNum<T>> {
+            type Output = Self;
 
-    fn add(self, other: DiffNum<f64>) -> DiffNum<f64> {
-        return DiffNum {
-            f: self + other.f,
-            df: other.df,
-        };
-    }
+            fn $method(self, other: f64) -> Self {
+                return self $op DiffNum::<T>::from_f64(other);
+            }
+        }
+    };
 }
 
-impl std::ops::Sub<DiffNum<f64>> for f64 {
-    type Output = DiffNum<f64>;
+impl_nested_scalar_right_op!(Add, add, +);
+impl_nested_scalar_right_op!(Sub, sub, -);
+impl_nested_scalar_right_op!(Mul, mul, *);
+impl_nested_scalar_right_op!(Div, div, /);
+
+// Operations with scalars on the left. An f64 scalar works with every precision, since it is
+// converted with Float::from_f64, and f32 scalars additionally combine with DiffNum<f32>.
+macro_rules! impl_scalar_left_ops {
+    ($s:ty, $t:ty, [$($param:tt)*]) => {
+        impl<$($param)*> std::ops::Add<DiffNum<$t>> for $s {
+            type Output = DiffNum<$t>;
+
+            fn add(self, other: DiffNum<$t>) -> DiffNum<$t> {
+                return DiffNum {
+                    f: <$t>::from_f64(self as f64) + other.f,
+                    df: other.df,
+                };
+            }
+        }
 
-    fn sub(self, other: DiffNum<f64>) -> DiffNum<f64> {
-        return DiffNum {
-            f: self - other.f,
-            df: -other.df,
-        };
-    }
-}
+        impl<$($param)*> std::ops::Sub<DiffNum<$t>> for $s {
+            type Output = DiffNum<$t>;
 
-impl std::ops::Mul<DiffNum<f64>> for f64 {
-    type Output = DiffNum<f64>;
+            fn sub(self, other: DiffNum<$t>) -> DiffNum<$t> {
+                return DiffNum {
+                    f: <$t>::from_f64(self as f64) - other.f,
+                    df: -other.df,
+                };
+            }
+        }
 
-    fn mul(self, other: DiffNum<f64>) -> DiffNum<f64> {
-        return DiffNum {
-            f: self * other.f,
-            df: self * other.df,
-        };
-    }
-}
+        impl<$($param)*> std::ops::Mul<DiffNum<$t>> for $s {
+            type Output = DiffNum<$t>;
 
-impl std::ops::Div<DiffNum<f64>> for f64 {
-    type Output = DiffNum<f64>;
+            fn mul(self, other: DiffNum<$t>) -> DiffNum<$t> {
+                let scalar: $t = <$t>::from_f64(self as f64);
+                return DiffNum {
+                    f: scalar * other.f,
+                    df: scalar * other.df,
+                };
+            }
+        }
 
-    fn div(self, other: DiffNum<f64>) -> DiffNum<f64> {
-        return DiffNum {
-            f: self / other.f,
-            df: (-self * other.df) / (other.f * other.f),
-        };
-    }
+        impl<$($param)*> std::ops::Div<DiffNum<$t>> for $s {
+            type Output = DiffNum<$t>;
+
+            fn div(self, other: DiffNum<$t>) -> DiffNum<$t> {
+                let scalar: $t = <$t>::from_f64(self as f64);
+                return DiffNum {
+                    f: scalar / other.f,
+                    df: (-scalar * other.df) / (other.f * other.f),
+                };
+            }
+        }
+    };
 }
 
+impl_scalar_left_ops!(f64, T, [T: Float]);
+impl_scalar_left_ops!(f32, f32, []);
+
 // Comparison and Equality
 impl<T: Eq> Eq for DiffNum<T> {}
 
@@ -270,19 +301,32 @@ impl<T: PartialOrd> PartialOrd for DiffNum<T> {
     }
 }
 
-// Implementation of mathematical functions
+// Approximate comparisons, applied to both the value and the derivative
 impl DiffNum<f64> {
+    pub fn approx_eq(&self, other: &Self, abs_tol: f64, rel_tol: f64) -> bool {
+        return crate::approx::approx_eq_f64(self.f, other.f, abs_tol, rel_tol)
+            && crate::approx::approx_eq_f64(self.df, other.df, abs_tol, rel_tol);
+    }
+
+    pub fn ulps_eq(&self, other: &Self, max_ulps: u64) -> bool {
+        return crate::approx::ulps_eq_f64(self.f, other.f, max_ulps)
+            && crate::approx::ulps_eq_f64(self.df, other.df, max_ulps);
+    }
+}
+
+// Implementation of mathematical functions, for any Float precision including nested DiffNums
+impl<T: Float> DiffNum<T> {
     pub fn powi(self, n: i32) -> Self {
         return Self {
             f: self.f.powi(n),
-            df: (n as f64) * self.f.powi(n - 1) * self.df,
+            df: T::from_f64(n as f64) * self.f.powi(n - 1) * self.df,
         };
     }
 
-    pub fn powf(self, n: f64) -> Self {
+    pub fn powf(self, n: T) -> Self {
         return Self {
             f: self.f.powf(n),
-            df: n * self.f.powf(n - 1.0) * self.df,
+            df: n * self.f.powf(n - T::one()) * self.df,
         };
     }
 
@@ -303,28 +347,28 @@ impl DiffNum<f64> {
     pub fn tan(self) -> Self {
         return Self {
             f: self.f.tan(),
-            df: (1.0 / self.f.cos().powi(2)) * self.df,
+            df: (T::one() / self.f.cos().powi(2)) * self.df,
         };
     }
 
     pub fn asin(self) -> Self {
         return Self {
             f: self.f.asin(),
-            df: (1.0 / (1.0 - self.f.powi(2)).sqrt()) * self.df,
+            df: (T::one() / (T::one() - self.f.powi(2)).sqrt()) * self.df,
         };
     }
 
     pub fn acos(self) -> Self {
         return Self {
             f: self.f.acos(),
-            df: (-1.0 / (1.0 - self.f.powi(2)).sqrt()) * self.df,
+            df: (-T::one() / (T::one() - self.f.powi(2)).sqrt()) * self.df,
         };
     }
 
     pub fn atan(self) -> Self {
         return Self {
             f: self.f.atan(),
-            df: (1.0 / (1.0 + self.f.powi(2))) * self.df,
+            df: (T::one() / (T::one() + self.f.powi(2))) * self.df,
         };
     }
 
@@ -338,21 +382,219 @@ impl DiffNum<f64> {
     pub fn ln(self) -> Self {
         return Self {
             f: self.f.ln(),
-            df: (1.0 / self.f) * self.df,
+            df: (T::one() / self.f) * self.df,
         };
     }
 
     pub fn log10(self) -> Self {
         return Self {
             f: self.f.log10(),
-            df: (1.0 / (self.f * std::f64::consts::LN_10)) * self.df,
+            df: (T::one() / (self.f * T::from_f64(std::f64::consts::LN_10))) * self.df,
         };
     }
 
     pub fn sqrt(self) -> Self {
         return Self {
             f: self.f.sqrt(),
-            df: (0.5 / self.f.sqrt()) * self.df,
+            df: (T::from_f64(0.5) / self.f.sqrt()) * self.df,
+        };
+    }
+
+    pub fn cbrt(self) -> Self {
+        return Self {
+            f: self.f.cbrt(),
+            df: (T::one() / (T::from_f64(3.0) * self.f.cbrt().powi(2))) * self.df,
+        };
+    }
+
+    pub fn recip(self) -> Self {
+        return Self {
+            f: self.f.recip(),
+            df: -self.df / (self.f * self.f),
+        };
+    }
+
+    // x^y with both base and exponent differentiable. Terms with a zero tangent are skipped, as
+    // are exponent terms of a zero result, since ln(x) is infinite at a zero base.
+    pub fn pow(self, other: Self) -> Self {
+        let f: T = self.f.powf(other.f);
+        let mut df: T = T::zero();
+        if self.df != T::zero() {
+            df += other.f * self.f.powf(other.f - T::one()) * self.df;
+        }
+        if other.df != T::zero() && f != T::zero() {
+            df += f * self.f.ln() * other.df;
+        }
+        return Self { f, df };
+    }
+
+    pub fn sin_cos(self) -> (Self, Self) {
+        let (sin, cos) = self.f.sin_cos();
+        return (
+            Self {
+                f: sin,
+                df: cos * self.df,
+            },
+            Self {
+                f: cos,
+                df: -sin * self.df,
+            },
+        );
+    }
+
+    // Angle of the point (other, self), matching f64::atan2 with self as y
+    pub fn atan2(self, other: Self) -> Self {
+        let r2: T = self.f * self.f + other.f * other.f;
+        return Self {
+            f: self.f.atan2(other.f),
+            df: (other.f * self.df - self.f * other.df) / r2,
+        };
+    }
+
+    // The derivative at the origin, where hypot has a kink, is taken as zero
+    pub fn hypot(self, other: Self) -> Self {
+        let h: T = self.f.hypot(other.f);
+        let df: T = if h == T::zero() {
+            T::zero()
+        } else {
+            (self.f * self.df + other.f * other.df) / h
+        };
+        return Self { f: h, df };
+    }
+
+    pub fn sinh(self) -> Self {
+        return Self {
+            f: self.f.sinh(),
+            df: self.f.cosh() * self.df,
+        };
+    }
+
+    pub fn cosh(self) -> Self {
+        return Self {
+            f: self.f.cosh(),
+            df: self.f.sinh() * self.df,
+        };
+    }
+
+    pub fn tanh(self) -> Self {
+        let t: T = self.f.tanh();
+        return Self {
+            f: t,
+            df: (T::one() - t * t) * self.df,
+        };
+    }
+
+    pub fn asinh(self) -> Self {
+        return Self {
+            f: self.f.asinh(),
+            df: (T::one() / (self.f * self.f + T::one()).sqrt()) * self.df,
+        };
+    }
+
+    pub fn acosh(self) -> Self {
+        return Self {
+            f: self.f.acosh(),
+            df: (T::one() / (self.f * self.f - T::one()).sqrt()) * self.df,
+        };
+    }
+
+    pub fn atanh(self) -> Self {
+        return Self {
+            f: self.f.atanh(),
+            df: (T::one() / (T::one() - self.f * self.f)) * self.df,
+        };
+    }
+
+    pub fn exp2(self) -> Self {
+        let f: T = self.f.exp2();
+        return Self {
+            f,
+            df: f * T::from_f64(std::f64::consts::LN_2) * self.df,
+        };
+    }
+
+    pub fn exp_m1(self) -> Self {
+        return Self {
+            f: self.f.exp_m1(),
+            df: self.f.exp() * self.df,
+        };
+    }
+
+    pub fn ln_1p(self) -> Self {
+        return Self {
+            f: self.f.ln_1p(),
+            df: (T::one() / (T::one() + self.f)) * self.df,
+        };
+    }
+
+    pub fn log2(self) -> Self {
+        return Self {
+            f: self.f.log2(),
+            df: (T::one() / (self.f * T::from_f64(std::f64::consts::LN_2))) * self.df,
+        };
+    }
+
+    pub fn log(self, base: T) -> Self {
+        return Self {
+            f: self.f.log(base),
+            df: (T::one() / (self.f * base.ln())) * self.df,
+        };
+    }
+
+    pub fn mul_add(self, a: Self, b: Self) -> Self {
+        return Self {
+            f: self.f.mul_add(a.f, b.f),
+            df: self.df * a.f + self.f * a.df + b.df,
+        };
+    }
+
+    // Non-smooth functions use the subgradient at their kinks that is symmetric between the
+    // two sides: abs and hypot have derivative 0 at 0, and min and max average the derivatives
+    // of their arguments at a tie. signum is piecewise constant, so its derivative is always 0.
+    pub fn abs(self) -> Self {
+        let slope: T = if self.f > T::zero() {
+            T::one()
+        } else if self.f < T::zero() {
+            -T::one()
+        } else {
+            T::zero()
+        };
+        return Self {
+            f: self.f.abs(),
+            df: slope * self.df,
+        };
+    }
+
+    pub fn signum(self) -> Self {
+        return Self {
+            f: self.f.signum(),
+            df: T::zero(),
+        };
+    }
+
+    pub fn min(self, other: Self) -> Self {
+        if self.f < other.f {
+            return self;
+        }
+        if other.f < self.f {
+            return other;
+        }
+        return Self {
+            f: self.f.min(other.f),
+            df: T::from_f64(0.5) * (self.df + other.df),
+        };
+    }
+
+    pub fn max(self, other: Self) -> Self {
+        if self.f > other.f {
+            return self;
+        }
+        if other.f > self.f {
+            return other;
+        }
+        return Self {
+            f: self.f.max(other.f),
+            df: T::from_f64(0.5) * (self.df + other.df),
         };
     }
 }
@@ -432,6 +674,7 @@ mod tests {
         assert_eq!(a.df, 1.0);
     }
 
+    #[test]
     fn test_sub_assign_f32() {
         let mut a: DiffNum<f32> = DiffNum { f: 5.0, df: 4.0 };
         let b: DiffNum<f32> = DiffNum { f: 3.0, df: 1.0 };
@@ -687,4 +930,172 @@ mod tests {
         assert!((f_dy.f - (1.0f64 * 2.0f64).exp()).abs() < 1e-10);
         assert!((f_dy.df - (1.0f64 * (1.0f64 * 2.0f64).exp())).abs() < 1e-10);
     }
+
+    type Unary = fn(DiffNum<f64>) -> DiffNum<f64>;
+    type Binary = fn(DiffNum<f64>, DiffNum<f64>) -> DiffNum<f64>;
+
+    // Checks the derivative of a unary function against a central finite difference
+    fn check_unary(g: Unary, x: f64) {
+        let h: f64 = 1e-6;
+        let exact: DiffNum<f64> = g(DiffNum { f: x, df: 1.0 });
+        let fd: f64 = (g(DiffNum::from(x + h)).f - g(DiffNum::from(x - h)).f) / (2.0 * h);
+        assert_eq!(exact.f, g(DiffNum::from(x)).f);
+        assert!(
+            crate::approx::approx_eq_f64(exact.df, fd, 1e-7, 1e-7),
+            "derivative {} does not match finite difference {} at x = {}",
+            exact.df,
+            fd,
+            x
+        );
+    }
+
+    // Checks both partial derivatives of a binary function at (x, y)
+    fn check_binary(g: Binary, x: f64, y: f64) {
+        let h: f64 = 1e-6;
+        let c = |v: f64| DiffNum::from(v);
+        let d_x: f64 = g(DiffNum { f: x, df: 1.0 }, c(y)).df;
+        let d_y: f64 = g(c(x), DiffNum { f: y, df: 1.0 }).df;
+        let fd_x: f64 = (g(c(x + h), c(y)).f - g(c(x - h), c(y)).f) / (2.0 * h);
+        let fd_y: f64 = (g(c(x), c(y + h)).f - g(c(x), c(y - h)).f) / (2.0 * h);
+        assert!(crate::approx::approx_eq_f64(d_x, fd_x, 1e-7, 1e-7));
+        assert!(crate::approx::approx_eq_f64(d_y, fd_y, 1e-7, 1e-7));
+    }
+
+    #[test]
+    fn test_elementary_funcs_against_finite_differences() {
+        let unary: [Unary; 17] = [
+            |x| x.sinh(),
+            |x| x.cosh(),
+            |x| x.tanh(),
+            |x| x.asinh(),
+            |x| x.acosh(),
+            |x| x.atanh(),
+            |x| x.log2(),
+            |x| x.log(3.0),
+            |x| x.exp2(),
+            |x| x.exp_m1(),
+            |x| x.ln_1p(),
+            |x| x.cbrt(),
+            |x| x.abs(),
+            |x| x.recip(),
+            |x| x.sin_cos().0,
+            |x| x.sin_cos().1,
+            |x| x.powf(2.5),
+        ];
+        for g in unary {
+            // Points outside the domain, such as acosh below 1 or atanh above 1, are skipped
+            for x in [0.3, 0.7, 1.5, 2.7] {
+                if g(DiffNum::from(x)).f.is_finite() {
+                    check_unary(g, x);
+                }
+            }
+        }
+        check_unary(|x| x.abs(), -1.2);
+        check_unary(|x| x.cbrt(), -8.0);
+    }
+
+    #[test]
+    fn test_binary_funcs_against_finite_differences() {
+        let binary: [Binary; 5] = [
+            |x, y| x.atan2(y),
+            |x, y| x.hypot(y),
+            |x, y| x.pow(y),
+            |x, y| x.min(y),
+            |x, y| x.max(y),
+        ];
+        for g in binary {
+            check_binary(g, 1.3, 0.6);
+            check_binary(g, 0.4, 2.1);
+        }
+        check_binary(|x, y| x.atan2(y), 0.5, -1.5);
+        check_binary(|x, y| x.mul_add(y, DiffNum::from(0.25)), 1.3, -0.6);
+
+        // The addend enters mul_add with derivative 1
+        let c: DiffNum<f64> = DiffNum::from(2.0);
+        assert_eq!(
+            c.mul_add(c, DiffNum { f: 1.0, df: 1.0 }),
+            DiffNum { f: 5.0, df: 1.0 }
+        );
+    }
+
+    #[test]
+    fn test_pow_of_diffnums() {
+        // d/dx x^x = x^x (ln x + 1)
+        let x: DiffNum<f64> = DiffNum { f: 2.0, df: 1.0 };
+        let y: DiffNum<f64> = x.pow(x);
+        assert!((y.f - 4.0).abs() < 1e-12);
+        assert!((y.df - 4.0 * (2.0_f64.ln() + 1.0)).abs() < 1e-12);
+
+        // A constant exponent at a zero base does not produce 0 * ln(0) = NaN
+        let zero: DiffNum<f64> = DiffNum { f: 0.0, df: 1.0 };
+        assert_eq!(zero.pow(DiffNum::from(2.0)), DiffNum { f: 0.0, df: 0.0 });
+
+        // Nor does a constant zero base with an exponent below 1 produce 0 * inf = NaN, in either
+        // the base or the exponent term
+        let base: DiffNum<f64> = DiffNum::from(0.0);
+        assert_eq!(base.pow(DiffNum::from(0.5)), DiffNum { f: 0.0, df: 0.0 });
+        assert_eq!(
+            base.pow(DiffNum { f: 0.5, df: 1.0 }),
+            DiffNum { f: 0.0, df: 0.0 }
+        );
+        // The derivative of sqrt at zero remains infinite
+        assert_eq!(zero.pow(DiffNum::from(0.5)).df, f64::INFINITY);
+    }
+
+    #[test]
+    fn test_subgradients_at_kinks() {
+        let zero: DiffNum<f64> = DiffNum { f: 0.0, df: 1.0 };
+        assert_eq!(zero.abs(), DiffNum { f: 0.0, df: 0.0 });
+        assert_eq!(zero.hypot(zero), DiffNum { f: 0.0, df: 0.0 });
+        assert_eq!(
+            DiffNum { f: -3.0, df: 1.0 }.signum(),
+            DiffNum { f: -1.0, df: 0.0 }
+        );
+
+        let a: DiffNum<f64> = DiffNum { f: 1.0, df: 1.0 };
+        let b: DiffNum<f64> = DiffNum { f: 1.0, df: -3.0 };
+        assert_eq!(a.max(b), DiffNum { f: 1.0, df: -1.0 });
+        assert_eq!(a.min(b), DiffNum { f: 1.0, df: -1.0 });
+        assert_eq!(a.max(DiffNum::from(0.0)), a);
+        assert_eq!(a.min(DiffNum::from(0.0)), DiffNum::from(0.0));
+    }
+
+    #[test]
+    fn test_scalar_left_ops_other_precisions() {
+        let x: DiffNum<f32> = DiffNum { f: 2.0, df: 1.0 };
+        assert_eq!(1.0_f32 - x, DiffNum { f: -1.0, df: -1.0 });
+        let scaled: DiffNum<f32> = 3.0 * x;
+        assert_eq!(scaled, DiffNum { f: 6.0, df: 3.0 });
+        assert_eq!(1.0_f32 / x, DiffNum { f: 0.5, df: -0.25 });
+
+        // d2/dx2 of 1 / x is 2 / x^3
+        let y: DiffNum<DiffNum<f64>> = DiffNum {
+            f: DiffNum { f: 2.0, df: 1.0 },
+            df: DiffNum { f: 1.0, df: 0.0 },
+        };
+        let z: DiffNum<DiffNum<f64>> = 1.0 / y + 5.0;
+        assert_eq!(z.f.f, 5.5);
+        assert_eq!(z.df.f, -0.25);
+        assert_eq!(z.df.df, 0.25);
+    }
+
+    #[test]
+    fn test_approx_eq() {
+        let a: DiffNum<f64> = DiffNum { f: 2.0, df: -1.0 };
+        let b: DiffNum<f64> = DiffNum {
+            f: 2.0 + 1e-12,
+            df: -1.0 - 1e-12,
+        };
+        assert!(a.approx_eq(&b, 1e-10, 0.0));
+        assert!(!a.approx_eq(&DiffNum { f: 2.0, df: -2.0 }, 1e-10, 1e-10));
+        assert!(!a.approx_eq(&DiffNum { f: 1.0, df: -1.0 }, 1e-10, 1e-10));
+        crate::assert_diffnum_approx_eq!(a, b);
+
+        let c: DiffNum<f64> = DiffNum {
+            f: 0.1 + 0.2,
+            df: 1.0,
+        };
+        assert!(c.ulps_eq(&DiffNum { f: 0.3, df: 1.0 }, 1));
+        assert!(!c.ulps_eq(&DiffNum { f: 0.3, df: 1.0 }, 0));
+    }
 }
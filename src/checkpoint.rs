@@ -0,0 +1,295 @@
+/*
+Memory-bounded reverse mode for iterative computations by binomial checkpointing.
+
+A loop x_(k+1) = step(k, x_k) for k = 0, ..., n - 1 followed by a scalar loss l(x_n) would put
+every operation of every iteration on the tape. Here each call to TimeStep::step marks one
+iteration: only states at a limited number of checkpoints are kept, and the backward pass
+recomputes the states in between from the nearest checkpoint, recording a single step at a time.
+Memory is therefore bounded by the checkpoints plus the tape of one step.
+
+The schedule is the binomial one of Griewank's Revolve: with s stored states, counting the
+initial one, and r repetitions, C(s + r, s) steps can be reversed while every step is evaluated
+at most r times besides its recorded evaluation. For each segment the number of repetitions r
+is the smallest one that covers it, and the first checkpoint is placed so that both halves need
+at most r repetitions.
+Quantities that are not part of the state, e.g. model parameters, can be differentiated by
+appending them to the state and copying them unchanged in every step.
+*/
+
+use crate::float::Float;
+use crate::gradient_check::Objective;
+use crate::reverse::{Adjoints, Tape, Var};
+
+// One iteration of the loop, evaluated in f64 when advancing and with Var when recorded
+pub trait TimeStep {
+    fn step<F: Float>(&self, k: usize, state: &[F]) -> Vec<F>;
+}
+
+// Cost of a checkpointed run
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CheckpointStats {
+    // Step evaluations without recording, including the recomputations
+    pub forward_steps: usize,
+    // Recorded step evaluations, one per step
+    pub recorded_steps: usize,
+    // Largest number of checkpoints held at once, not counting the initial state
+    pub max_checkpoints: usize,
+    // Largest tape length of a single recorded step
+    pub max_tape_len: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct CheckpointedGradient {
+    pub loss: f64,
+    pub final_state: Vec<f64>,
+    // Gradient of the loss with respect to the initial state
+    pub gradient: Vec<f64>,
+    pub stats: CheckpointStats,
+}
+
+// C(s + r, s), the number of steps that s checkpoints and r repetitions can reverse, saturating
+fn binomial_steps(s: usize, r: usize) -> usize {
+    let mut result: u128 = 1;
+    for i in 1..=s.min(r) as u128 {
+        result = result * ((s + r) as u128 + 1 - i) / i;
+        if result >= usize::MAX as u128 {
+            return usize::MAX;
+        }
+    }
+    return result as usize;
+}
+
+// Smallest number of repetitions with which n steps are reversed using the given number of
+// checkpoints besides the initial state
+pub fn repetitions(n: usize, checkpoints: usize) -> usize {
+    let mut r: usize = 0;
+    while binomial_steps(checkpoints + 1, r) < n {
+        r += 1;
+    }
+    return r;
+}
+
+struct Run<'a, S: TimeStep, O: Objective> {
+    stepper: &'a S,
+    loss: &'a O,
+    checkpoints: usize,
+    loss_value: f64,
+    final_state: Vec<f64>,
+    stats: CheckpointStats,
+}
+
+impl<S: TimeStep, O: Objective> Run<'_, S, O> {
+    // State at step `to` computed from the state at step `from`
+    fn advance(&mut self, from: usize, to: usize, state: &[f64]) -> Vec<f64> {
+        let mut x: Vec<f64> = state.to_vec();
+        for k in from..to {
+            x = self.stepper.step(k, &x);
+            self.stats.forward_steps += 1;
+        }
+        return x;
+    }
+
+    // Records step k and returns the cotangent of its input. The cotangent of the last output is
+    // not known beforehand, so the last step records the loss as well.
+    fn adjoint_step(&mut self, k: usize, state: &[f64], bar: Option<Vec<f64>>) -> Vec<f64> {
+        let tape: Tape = Tape::new();
+        let inputs: Vec<Var> = state.iter().map(|&xi| tape.var(xi)).collect();
+        let outputs: Vec<Var> = self.stepper.step(k, &inputs);
+        self.stats.recorded_steps += 1;
+        let adjoints: Adjoints = match bar {
+            Some(bar) => tape.backward(&outputs, &bar),
+            None => {
+                let loss: Var = self.loss.eval(&outputs);
+                self.loss_value = loss.value();
+                self.final_state = outputs.iter().map(|v| v.value()).collect();
+                tape.gradient(loss)
+            }
+        };
+        self.stats.max_tape_len = self.stats.max_tape_len.max(tape.len());
+        return inputs.iter().map(|&v| adjoints.wrt(v)).collect();
+    }
+
+    // Reverses steps a..b given the state at a, with `free` checkpoints available. `bar` is the
+    // cotangent of the state at b, None if it is the final state.
+    fn reverse(
+        &mut self,
+        a: usize,
+        b: usize,
+        state: &[f64],
+        free: usize,
+        bar: Option<Vec<f64>>,
+    ) -> Vec<f64> {
+        let n: usize = b - a;
+        if n == 1 {
+            return self.adjoint_step(a, state, bar);
+        }
+        if free == 0 {
+            let mut bar: Option<Vec<f64>> = bar;
+            for k in (a..b).rev() {
+                let x: Vec<f64> = self.advance(a, k, state);
+                bar = Some(self.adjoint_step(k, &x, bar));
+            }
+            return bar.unwrap();
+        }
+
+        let r: usize = repetitions(n, free);
+        let left: usize = binomial_steps(free + 1, r - 1).min(n - 1);
+        let m: usize = a + left;
+        let checkpoint: Vec<f64> = self.advance(a, m, state);
+
+        let held: usize = self.checkpoints - free + 1;
+        self.stats.max_checkpoints = self.stats.max_checkpoints.max(held);
+        let bar_m: Vec<f64> = self.reverse(m, b, &checkpoint, free - 1, bar);
+        drop(checkpoint);
+        return self.reverse(a, m, state, free, Some(bar_m));
+    }
+}
+
+// Loss l(x_n) after n_steps iterations from x0 and its gradient with respect to x0, holding at
+// most `checkpoints` intermediate states
+pub fn checkpointed_gradient<S: TimeStep, O: Objective>(
+    stepper: &S,
+    loss: &O,
+    x0: &[f64],
+    n_steps: usize,
+    checkpoints: usize,
+) -> CheckpointedGradient {
+    if n_steps == 0 {
+        let tape: Tape = Tape::new();
+        let inputs: Vec<Var> = x0.iter().map(|&xi| tape.var(xi)).collect();
+        let value: Var = loss.eval(&inputs);
+        let adjoints: Adjoints = tape.gradient(value);
+        return CheckpointedGradient {
+            loss: value.value(),
+            final_state: x0.to_vec(),
+            gradient: inputs.iter().map(|&v| adjoints.wrt(v)).collect(),
+            stats: CheckpointStats {
+                max_tape_len: tape.len(),
+                ..CheckpointStats::default()
+            },
+        };
+    }
+    let mut run: Run<S, O> = Run {
+        stepper,
+        loss,
+        checkpoints,
+        loss_value: 0.0,
+        final_state: Vec::new(),
+        stats: CheckpointStats::default(),
+    };
+    let gradient: Vec<f64> = run.reverse(0, n_steps, x0, checkpoints, None);
+    return CheckpointedGradient {
+        loss: run.loss_value,
+        final_state: run.final_state,
+        gradient,
+        stats: run.stats,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reverse;
+
+    // Explicit Euler for a damped pendulum, with the damping c carried as the third component
+    struct Pendulum {
+        dt: f64,
+    }
+
+    impl TimeStep for Pendulum {
+        fn step<F: Float>(&self, _k: usize, state: &[F]) -> Vec<F> {
+            let dt: F = F::from_f64(self.dt);
+            let (theta, omega, c) = (state[0], state[1], state[2]);
+            return vec![
+                theta + dt * omega,
+                omega - dt * (theta.sin() + c * omega),
+                c,
+            ];
+        }
+    }
+
+    // Energy of the final state
+    struct Energy;
+
+    impl Objective for Energy {
+        fn eval<F: Float>(&self, x: &[F]) -> F {
+            return F::from_f64(0.5) * x[1] * x[1] + F::one() - x[0].cos();
+        }
+    }
+
+    // The whole loop on one tape, for reference
+    struct Unrolled {
+        n_steps: usize,
+    }
+
+    impl Objective for Unrolled {
+        fn eval<F: Float>(&self, x: &[F]) -> F {
+            let mut state: Vec<F> = x.to_vec();
+            for k in 0..self.n_steps {
+                state = Pendulum { dt: 0.01 }.step(k, &state);
+            }
+            return Energy.eval(&state);
+        }
+    }
+
+    #[test]
+    fn test_binomial_steps() {
+        assert_eq!(binomial_steps(3, 2), 10);
+        assert_eq!(binomial_steps(0, 7), 1);
+        assert_eq!(binomial_steps(5, 0), 1);
+        assert_eq!(binomial_steps(200, 200), usize::MAX);
+        assert_eq!(repetitions(15, 3), 2);
+        assert_eq!(repetitions(16, 3), 3);
+        assert_eq!(repetitions(1, 0), 0);
+        assert_eq!(repetitions(7, 0), 6);
+    }
+
+    #[test]
+    fn test_matches_full_tape() {
+        let x0: [f64; 3] = [1.0, 0.0, 0.3];
+        let n: usize = 100;
+        let expected: Vec<f64> = reverse::gradient(&Unrolled { n_steps: n }, &x0);
+        let pendulum: Pendulum = Pendulum { dt: 0.01 };
+
+        for checkpoints in [0, 1, 3, 10, 200] {
+            let result: CheckpointedGradient =
+                checkpointed_gradient(&pendulum, &Energy, &x0, n, checkpoints);
+            for (g, e) in result.gradient.iter().zip(&expected) {
+                assert!((g - e).abs() < 1e-12);
+            }
+            assert!((result.loss - Unrolled { n_steps: n }.eval(&x0)).abs() < 1e-15);
+            assert_eq!(result.final_state.len(), 3);
+
+            let stats: CheckpointStats = result.stats;
+            assert_eq!(stats.recorded_steps, n);
+            assert!(stats.max_checkpoints <= checkpoints);
+            assert!(stats.forward_steps <= repetitions(n, checkpoints) * n);
+        }
+    }
+
+    #[test]
+    fn test_memory_and_recomputation() {
+        let x0: [f64; 3] = [0.5, 0.2, 0.1];
+        let pendulum: Pendulum = Pendulum { dt: 0.01 };
+        let n: usize = 1000;
+
+        // Enough checkpoints: every state is computed once before it is recorded
+        let ample: CheckpointStats = checkpointed_gradient(&pendulum, &Energy, &x0, 20, 19).stats;
+        assert_eq!(ample.forward_steps, 19);
+
+        // Ten checkpoints reverse 1000 steps with four repetitions, and the tape never holds
+        // more than a single step
+        let result: CheckpointedGradient = checkpointed_gradient(&pendulum, &Energy, &x0, n, 10);
+        assert_eq!(repetitions(n, 10), 4);
+        assert!(result.stats.forward_steps <= 4 * n);
+        assert!(result.stats.max_tape_len < 30);
+        let full: Tape = Tape::new();
+        let vars: Vec<Var> = x0.iter().map(|&xi| full.var(xi)).collect();
+        Unrolled { n_steps: n }.eval(&vars);
+        assert!(full.len() > 5 * n);
+
+        // Without steps the loss is differentiated directly
+        let none: CheckpointedGradient = checkpointed_gradient(&pendulum, &Energy, &x0, 0, 5);
+        assert_eq!(none.gradient, vec![0.5_f64.sin(), 0.2, 0.0]);
+    }
+}
@@ -0,0 +1,557 @@
+/*
+Complex numbers, mainly for complex-step differentiation: for a real-analytic f,
+f'(x) = Im(f(x + ih)) / h with an error of order h^2 and no subtractive cancellation, so h can
+be as small as 1e-200 and the derivative is exact to machine precision.
+
+Complex implements Float so generic code can be evaluated at x + ih. Following the complex-step
+conventions of Martins, Sturdza and Alonso (2003), comparisons, min, max, abs and signum look at
+the real part only, which keeps them consistent with the real function they extend. The
+elementary functions are the principal branches, except that cbrt stays real on the negative
+real axis like f64::cbrt. Use `norm` for the modulus.
+*/
+
+use crate::custom_rule::CustomRule;
+use crate::float::Float;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Complex<T> {
+    pub re: T,
+    pub im: T,
+}
+
+impl<T: Float> Complex<T> {
+    pub fn new(re: T, im: T) -> Self {
+        return Self { re, im };
+    }
+
+    pub fn i() -> Self {
+        return Self::new(T::zero(), T::one());
+    }
+
+    pub fn conj(self) -> Self {
+        return Self::new(self.re, -self.im);
+    }
+
+    // Modulus |z|
+    pub fn norm(self) -> T {
+        return self.re.hypot(self.im);
+    }
+
+    // Argument in (-pi, pi]
+    pub fn arg(self) -> T {
+        return self.im.atan2(self.re);
+    }
+
+    fn real(re: T) -> Self {
+        return Self::new(re, T::zero());
+    }
+
+    fn scale(self, s: T) -> Self {
+        return Self::new(self.re * s, self.im * s);
+    }
+
+    // Multiplication by i
+    fn mul_i(self) -> Self {
+        return Self::new(-self.im, self.re);
+    }
+
+    pub fn powi(self, n: i32) -> Self {
+        let mut base: Self = self;
+        let mut exp: u32 = n.unsigned_abs();
+        let mut result: Self = Self::real(T::one());
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result *= base;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base *= base;
+            }
+        }
+        return if n < 0 { result.recip() } else { result };
+    }
+
+    // A zero base follows f64::powf on the real part of the exponent: 0^0 = 1 and 0^n = inf for
+    // n < 0
+    pub fn powf(self, n: Self) -> Self {
+        if self.re == T::zero() && self.im == T::zero() {
+            if n.re == T::zero() {
+                return Self::real(T::one());
+            }
+            if n.re < T::zero() {
+                return Self::real(T::one() / T::zero());
+            }
+            return Self::real(T::zero());
+        }
+        return (n * self.ln()).exp();
+    }
+
+    // Written so that neither part cancels when the imaginary part is tiny
+    pub fn sqrt(self) -> Self {
+        let r: T = self.norm();
+        if r == T::zero() {
+            return Self::real(T::zero());
+        }
+        let two: T = T::from_f64(2.0);
+        if self.re >= T::zero() {
+            let t: T = ((r + self.re) / two).sqrt();
+            return Self::new(t, self.im / (two * t));
+        }
+        let t: T = ((r - self.re) / two).sqrt();
+        let sign: T = if self.im < T::zero() {
+            -T::one()
+        } else {
+            T::one()
+        };
+        return Self::new(self.im.abs() / (two * t), sign * t);
+    }
+
+    // Odd extension of the real cube root, analytic across the negative real axis
+    pub fn cbrt(self) -> Self {
+        if self.re < T::zero() {
+            return -(-self).cbrt();
+        }
+        if self.re == T::zero() && self.im == T::zero() {
+            return self;
+        }
+        return (self.ln().scale(T::from_f64(1.0 / 3.0))).exp();
+    }
+
+    pub fn recip(self) -> Self {
+        return Self::real(T::one()) / self;
+    }
+
+    pub fn exp(self) -> Self {
+        let (s, c) = self.im.sin_cos();
+        return Self::new(c, s).scale(self.re.exp());
+    }
+
+    pub fn exp2(self) -> Self {
+        return self.scale(T::from_f64(std::f64::consts::LN_2)).exp();
+    }
+
+    // cos(b) - 1 is written as -2 sin^2(b / 2) to keep accuracy for small arguments
+    pub fn exp_m1(self) -> Self {
+        let half_sin: T = (self.im / T::from_f64(2.0)).sin();
+        let cos_m1: T = -T::from_f64(2.0) * half_sin * half_sin;
+        return Self::new(
+            self.re.exp_m1() * self.im.cos() + cos_m1,
+            self.re.exp() * self.im.sin(),
+        );
+    }
+
+    pub fn ln(self) -> Self {
+        return Self::new(self.norm().ln(), self.arg());
+    }
+
+    // ln|1 + z| = ln_1p(2a + a^2 + b^2) / 2, accurate for small z
+    pub fn ln_1p(self) -> Self {
+        let (a, b) = (self.re, self.im);
+        let re: T = (T::from_f64(2.0) * a + a * a + b * b).ln_1p() / T::from_f64(2.0);
+        return Self::new(re, b.atan2(T::one() + a));
+    }
+
+    pub fn log2(self) -> Self {
+        return self.ln().scale(T::from_f64(std::f64::consts::LOG2_E));
+    }
+
+    pub fn log10(self) -> Self {
+        return self.ln().scale(T::from_f64(std::f64::consts::LOG10_E));
+    }
+
+    pub fn log(self, base: Self) -> Self {
+        return self.ln() / base.ln();
+    }
+
+    pub fn sin(self) -> Self {
+        let (s, c) = self.re.sin_cos();
+        return Self::new(s * self.im.cosh(), c * self.im.sinh());
+    }
+
+    pub fn cos(self) -> Self {
+        let (s, c) = self.re.sin_cos();
+        return Self::new(c * self.im.cosh(), -s * self.im.sinh());
+    }
+
+    pub fn sin_cos(self) -> (Self, Self) {
+        return (self.sin(), self.cos());
+    }
+
+    // tan(a + ib) = (sin 2a + i sinh 2b) / (cos 2a + cosh 2b)
+    pub fn tan(self) -> Self {
+        let two: T = T::from_f64(2.0);
+        let den: T = (two * self.re).cos() + (two * self.im).cosh();
+        return Self::new((two * self.re).sin() / den, (two * self.im).sinh() / den);
+    }
+
+    // atan(z / sqrt(1 - z^2)) inside the unit strip, -i ln(iz + sqrt(1 - z^2)) outside. The
+    // former keeps a tiny imaginary part accurate, which the modulus in the logarithm would lose.
+    pub fn asin(self) -> Self {
+        let root: Self = (Self::real(T::one()) - self * self).sqrt();
+        if self.re.abs() < T::one() {
+            return (self / root).atan();
+        }
+        let w: Self = (self.mul_i() + root).ln();
+        return Self::new(w.im, -w.re);
+    }
+
+    pub fn acos(self) -> Self {
+        return Self::real(T::from_f64(std::f64::consts::FRAC_PI_2)) - self.asin();
+    }
+
+    // Re = atan2(2a, 1 - a^2 - b^2) / 2 and Im = ln_1p(4b / (a^2 + (1 - b)^2)) / 4
+    pub fn atan(self) -> Self {
+        let (a, b) = (self.re, self.im);
+        let two: T = T::from_f64(2.0);
+        let re: T = (two * a).atan2(T::one() - a * a - b * b) / two;
+        let one_m_b: T = T::one() - b;
+        let im: T = (T::from_f64(4.0) * b / (a * a + one_m_b * one_m_b)).ln_1p() / T::from_f64(4.0);
+        return Self::new(re, im);
+    }
+
+    // Analytic continuation of atan2 in both arguments, with the branch taken from the real parts
+    pub fn atan2(self, other: Self) -> Self {
+        let pi: T = T::pi();
+        if other.re > T::zero() {
+            return (self / other).atan();
+        }
+        if other.re < T::zero() {
+            let shift: T = if self.re >= T::zero() { pi } else { -pi };
+            return (self / other).atan() + Self::real(shift);
+        }
+        if self.re == T::zero() {
+            return Self::real(T::zero());
+        }
+        let half_pi: T = pi / T::from_f64(2.0) * self.re.signum();
+        return Self::real(half_pi) - (other / self).atan();
+    }
+
+    pub fn sinh(self) -> Self {
+        let (s, c) = self.im.sin_cos();
+        return Self::new(self.re.sinh() * c, self.re.cosh() * s);
+    }
+
+    pub fn cosh(self) -> Self {
+        let (s, c) = self.im.sin_cos();
+        return Self::new(self.re.cosh() * c, self.re.sinh() * s);
+    }
+
+    // tanh(a + ib) = (sinh 2a + i sin 2b) / (cosh 2a + cos 2b)
+    pub fn tanh(self) -> Self {
+        let two: T = T::from_f64(2.0);
+        let den: T = (two * self.re).cosh() + (two * self.im).cos();
+        return Self::new((two * self.re).sinh() / den, (two * self.im).sin() / den);
+    }
+
+    // Evaluated on the right half plane, where z + sqrt(z^2 + 1) does not cancel
+    pub fn asinh(self) -> Self {
+        if self.re < T::zero() {
+            return -(-self).asinh();
+        }
+        return (self + (self * self + Self::real(T::one())).sqrt()).ln();
+    }
+
+    pub fn acosh(self) -> Self {
+        let one: Self = Self::real(T::one());
+        return (self + (self + one).sqrt() * (self - one).sqrt()).ln();
+    }
+
+    pub fn atanh(self) -> Self {
+        return (self.ln_1p() - (-self).ln_1p()).scale(T::from_f64(0.5));
+    }
+
+    // Complex-step extension: negates when the real part is negative
+    pub fn abs(self) -> Self {
+        return if self.re < T::zero() { -self } else { self };
+    }
+
+    pub fn signum(self) -> Self {
+        return Self::real(self.re.signum());
+    }
+
+    pub fn hypot(self, other: Self) -> Self {
+        if self.re == T::zero() && self.im == T::zero() {
+            return other.abs();
+        }
+        if other.re == T::zero() && other.im == T::zero() {
+            return self.abs();
+        }
+        return (self * self + other * other).sqrt();
+    }
+
+    pub fn mul_add(self, a: Self, b: Self) -> Self {
+        return self * a + b;
+    }
+
+    pub fn min(self, other: Self) -> Self {
+        if self.re < other.re {
+            return self;
+        }
+        if other.re < self.re {
+            return other;
+        }
+        return (self + other).scale(T::from_f64(0.5));
+    }
+
+    pub fn max(self, other: Self) -> Self {
+        if self.re > other.re {
+            return self;
+        }
+        if other.re > self.re {
+            return other;
+        }
+        return (self + other).scale(T::from_f64(0.5));
+    }
+}
+
+// Arithmetic
+impl<T: Float> std::ops::Add for Complex<T> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        return Self::new(self.re + other.re, self.im + other.im);
+    }
+}
+
+impl<T: Float> std::ops::Sub for Complex<T> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        return Self::new(self.re - other.re, self.im - other.im);
+    }
+}
+
+impl<T: Float> std::ops::Mul for Complex<T> {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        return Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        );
+    }
+}
+
+// Smith's algorithm, which avoids overflow in c^2 + d^2
+impl<T: Float> std::ops::Div for Complex<T> {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        let (a, b, c, d) = (self.re, self.im, other.re, other.im);
+        if c.abs() >= d.abs() {
+            let ratio: T = d / c;
+            let den: T = c + d * ratio;
+            return Self::new((a + b * ratio) / den, (b - a * ratio) / den);
+        }
+        let ratio: T = c / d;
+        let den: T = c * ratio + d;
+        return Self::new((a * ratio + b) / den, (b * ratio - a) / den);
+    }
+}
+
+impl<T: Float> std::ops::Neg for Complex<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        return Self::new(-self.re, -self.im);
+    }
+}
+
+impl<T: Float> std::ops::AddAssign for Complex<T> {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl<T: Float> std::ops::SubAssign for Complex<T> {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+impl<T: Float> std::ops::MulAssign for Complex<T> {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+impl<T: Float> std::ops::DivAssign for Complex<T> {
+    fn div_assign(&mut self, other: Self) {
+        *self = *self / other;
+    }
+}
+
+// Operations with real scalars on the right
+impl<T: Float> std::ops::Add<T> for Complex<T> {
+    type Output = Self;
+
+    fn add(self, other: T) -> Self {
+        return Self::new(self.re + other, self.im);
+    }
+}
+
+impl<T: Float> std::ops::Sub<T> for Complex<T> {
+    type Output = Self;
+
+    fn sub(self, other: T) -> Self {
+        return Self::new(self.re - other, self.im);
+    }
+}
+
+impl<T: Float> std::ops::Mul<T> for Complex<T> {
+    type Output = Self;
+
+    fn mul(self, other: T) -> Self {
+        return self.scale(other);
+    }
+}
+
+impl<T: Float> std::ops::Div<T> for Complex<T> {
+    type Output = Self;
+
+    fn div(self, other: T) -> Self {
+        return Self::new(self.re / other, self.im / other);
+    }
+}
+
+// Ordering compares real parts only, see the module comment
+impl<T: Float> PartialOrd for Complex<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        return self.re.partial_cmp(&other.re);
+    }
+}
+
+impl<T: Float> Float for Complex<T> {
+    fn from_f64(value: f64) -> Self {
+        return Self::real(T::from_f64(value));
+    }
+
+    fn to_f64(self) -> f64 {
+        return self.re.to_f64();
+    }
+
+    // First order in the imaginary parts, which is all the complex step reads
+    fn apply_rule<R: CustomRule>(rule: &R, x: &[Self]) -> Vec<Self> {
+        let re: Vec<T> = x.iter().map(|xi| xi.re).collect();
+        let im: Vec<T> = x.iter().map(|xi| xi.im).collect();
+        let y_re: Vec<T> = T::apply_rule(rule, &re);
+        let y_im: Vec<T> = rule.jvp(&re, &y_re, &im);
+        return y_re
+            .into_iter()
+            .zip(y_im)
+            .map(|(re, im)| Complex::new(re, im))
+            .collect();
+    }
+
+    crate::float::forward_methods!(
+        Complex<T>;
+        unary: sqrt, cbrt, recip, exp, exp2, exp_m1, ln, ln_1p, log2, log10, sin, cos, tan,
+            asin, acos, atan, sinh, cosh, tanh, asinh, acosh, atanh, abs, signum;
+        binary: powf, log, atan2, hypot, min, max
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type C = Complex<f64>;
+
+    // Complex extension, real derivative and evaluation point
+    type Check = (fn(C) -> C, fn(f64) -> f64, f64);
+
+    fn assert_close(z: C, re: f64, im: f64) {
+        assert!(
+            (z.re - re).abs() < 1e-13 && (z.im - im).abs() < 1e-13,
+            "{:?} != ({}, {})",
+            z,
+            re,
+            im
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_and_principal_values() {
+        let z: C = Complex::new(3.0, 4.0);
+        assert_eq!(z.norm(), 5.0);
+        assert_close(z * z.conj(), 25.0, 0.0);
+        assert_close(z / Complex::new(1.0, 2.0), 2.2, -0.4);
+        assert_close(
+            Complex::new(1.0, 2.0) / Complex::new(0.5, 3.0),
+            0.7027027027027027,
+            -0.21621621621621623,
+        );
+        assert_close(z.sqrt(), 2.0, 1.0);
+        assert_close(Complex::new(-4.0, 0.0).sqrt(), 0.0, 2.0);
+        assert_close(Complex::new(-3.0, -4.0).sqrt(), 1.0, -2.0);
+        assert_close(C::i().powi(2), -1.0, 0.0);
+        assert_close(z.powi(-1), 0.12, -0.16);
+        assert_close(Complex::new(0.0, std::f64::consts::PI).exp(), -1.0, 0.0);
+        assert_close(Complex::new(-1.0, 0.0).ln(), 0.0, std::f64::consts::PI);
+        assert_close(Complex::new(-8.0, 0.0).cbrt(), -2.0, 0.0);
+
+        // Zero bases agree with f64
+        let zero: C = C::real(0.0);
+        assert_eq!(zero.powf(C::real(0.0)), C::real(0.0_f64.powf(0.0)));
+        assert_eq!(zero.powf(C::real(-1.0)), C::real(0.0_f64.powf(-1.0)));
+        assert_eq!(zero.powf(C::real(2.5)), C::real(0.0_f64.powf(2.5)));
+        assert_eq!(zero.powf(C::new(-1.0, 1e-20)).re, f64::INFINITY);
+    }
+
+    #[test]
+    fn test_functions_satisfy_identities() {
+        let z: C = Complex::new(0.3, -0.7);
+        let (s, c) = z.sin_cos();
+        assert_close(s * s + c * c, 1.0, 0.0);
+        assert_close(z.exp().ln() - z, 0.0, 0.0);
+        assert_close(z.sin().asin() - z, 0.0, 0.0);
+        assert_close(z.cos().acos() - z, 0.0, 0.0);
+        assert_close(z.tan().atan() - z, 0.0, 0.0);
+        assert_close(z.sinh().asinh() - z, 0.0, 0.0);
+        assert_close(z.tanh().atanh() - z, 0.0, 0.0);
+        assert_close(z.tan() * z.cos() - z.sin(), 0.0, 0.0);
+        assert_close(z.tanh() * z.cosh() - z.sinh(), 0.0, 0.0);
+        assert_close(z.exp_m1() - (z.exp() - 1.0), 0.0, 0.0);
+        assert_close(z.ln_1p() - (z + 1.0).ln(), 0.0, 0.0);
+        assert_close(z.cbrt().powi(3) - z, 0.0, 0.0);
+        assert_close(z.exp2() - z.scale(2.0_f64.ln()).exp(), 0.0, 0.0);
+        let w: C = Complex::new(1.7, 0.2);
+        assert_close(w.cosh().acosh() - w, 0.0, 0.0);
+        assert_close(w.powf(z) - (z * w.ln()).exp(), 0.0, 0.0);
+    }
+
+    #[test]
+    fn test_complex_step_derivatives() {
+        // Im(f(x + ih)) / h recovers f'(x) to machine precision with a tiny step
+        let h: f64 = 1e-30;
+        let step = |x: f64| Complex::new(x, h);
+        let checks: [Check; 10] = [
+            (|z| z.asin(), |x| 1.0 / (1.0 - x * x).sqrt(), 0.6),
+            (|z| z.acos(), |x| -1.0 / (1.0 - x * x).sqrt(), -0.2),
+            (
+                |z| z.atan2(Complex::new(-2.0, 0.0)),
+                |x| -2.0 / (4.0 + x * x),
+                0.5,
+            ),
+            (
+                |z| Complex::new(-1.0, 0.0).atan2(z),
+                |x| 1.0 / (1.0 + x * x),
+                -0.3,
+            ),
+            (|z| z.abs(), |x| x.signum(), -2.0),
+            (|z| z.asinh(), |x| 1.0 / (x * x + 1.0).sqrt(), -1.5),
+            (|z| z.cbrt(), |x| 1.0 / (3.0 * x.cbrt().powi(2)), -8.0),
+            (
+                |z| z.hypot(Complex::new(3.0, 0.0)),
+                |x| x / x.hypot(3.0),
+                4.0,
+            ),
+            (|z| z.log10(), |x| 1.0 / (x * 10.0_f64.ln()), 2.5),
+            (|z| z.exp_m1(), |x| x.exp(), 1e-8),
+        ];
+        for (f, df, x) in checks {
+            let derivative: f64 = f(step(x)).im / h;
+            assert!((derivative - df(x)).abs() < 1e-14 * df(x).abs().max(1.0));
+        }
+    }
+}
@@ -0,0 +1,295 @@
+/*
+Custom derivative rules for operations that should not be differentiated through their
+implementation, e.g. table lookups, iterative solvers or calls into external code.
+
+A CustomRule supplies the primal map y = f(x) on plain f64 values together with its
+Jacobian-vector product J(x) v and optionally its vector-Jacobian product w^T J(x). Inside
+generic code the rule is called with `apply`, and every Float type handles it:
+
+- f64 and f32 evaluate the primal.
+- DiffNum and Complex pair the primal of the values with the JVP of the tangents, the latter
+  being the complex-step limit for Complex.
+- Taylor solves y'(t) = J(x(t)) x'(t) for the series of y by Picard iteration, gaining one
+  coefficient per JVP evaluation, so derivatives of any order follow as far as the JVP itself
+  is differentiable.
+- Var evaluates the primal and records the rule on the tape, and the backward pass calls the
+  VJP once with the accumulated cotangents of all outputs. The tape keeps a clone of the rule
+  until it is cleared, so a rule that holds large data should share it through an Rc.
+
+The derivative rules receive the outputs y along with x, so a solver's JVP can be written by
+implicit differentiation without solving again. The JVP is generic over Float so that nested
+types see the derivative of the JVP, including its dependence on y. A JVP that reads values with
+to_f64 is treated as piecewise constant in x, which is exact for piecewise linear maps.
+*/
+
+use crate::float::Float;
+
+pub trait CustomRule: Clone + 'static {
+    // y = f(x)
+    fn primal(&self, x: &[f64]) -> Vec<f64>;
+
+    // J(x) v, with y = f(x)
+    fn jvp<F: Float>(&self, x: &[F], y: &[F], v: &[F]) -> Vec<F>;
+
+    // w^T J(x), with y = f(x). The default assembles it from one JVP per input; override it
+    // when the transposed product is cheaper, as for adjoint solves.
+    fn vjp(&self, x: &[f64], y: &[f64], w: &[f64]) -> Vec<f64> {
+        let mut unit: Vec<f64> = vec![0.0; x.len()];
+        let mut result: Vec<f64> = Vec::with_capacity(x.len());
+        for j in 0..x.len() {
+            unit[j] = 1.0;
+            let column: Vec<f64> = self.jvp(x, y, &unit);
+            result.push(column.iter().zip(w).map(|(c, wi)| c * wi).sum());
+            unit[j] = 0.0;
+        }
+        return result;
+    }
+}
+
+// Applies the rule to x in any Float type
+pub fn apply<F: Float, R: CustomRule>(rule: &R, x: &[F]) -> Vec<F> {
+    return F::apply_rule(rule, x);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto_diff::DiffNum;
+    use crate::complex::Complex;
+    use crate::gradient_check::{
+        autodiff_gradient, check_gradient, Difference, GradientCheck, Objective,
+    };
+    use crate::reverse::{self, Adjoints, Tape, Var};
+    use crate::taylor::Taylor;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    // Piecewise linear interpolation in a table with unit spacing
+    #[derive(Clone)]
+    struct Lookup {
+        table: Vec<f64>,
+    }
+
+    impl Lookup {
+        fn interval(&self, x: f64) -> usize {
+            return (x.floor().max(0.0) as usize).min(self.table.len() - 2);
+        }
+    }
+
+    impl CustomRule for Lookup {
+        fn primal(&self, x: &[f64]) -> Vec<f64> {
+            let i: usize = self.interval(x[0]);
+            let t: f64 = x[0] - i as f64;
+            return vec![(1.0 - t) * self.table[i] + t * self.table[i + 1]];
+        }
+
+        fn jvp<F: Float>(&self, x: &[F], _y: &[F], v: &[F]) -> Vec<F> {
+            let i: usize = self.interval(x[0].to_f64());
+            return vec![F::from_f64(self.table[i + 1] - self.table[i]) * v[0]];
+        }
+    }
+
+    #[test]
+    fn test_lookup_in_every_mode() {
+        let lookup: Lookup = Lookup {
+            table: vec![0.0, 1.0, 4.0, 9.0, 16.0],
+        };
+        let x: f64 = 2.25;
+
+        assert_eq!(apply(&lookup, &[x]), vec![5.25]);
+        assert_eq!(apply(&lookup, &[x as f32]), vec![5.25_f32]);
+
+        let dual: DiffNum<f64> = apply(&lookup, &[DiffNum { f: x, df: 2.0 }])[0];
+        assert_eq!((dual.f, dual.df), (5.25, 10.0));
+
+        let complex: Complex<f64> = apply(&lookup, &[Complex::new(x, 1e-20)])[0];
+        assert_eq!(complex.im / 1e-20, 5.0);
+
+        let series: Taylor<f64, 2> = apply(&lookup, &[Taylor::variable(x)])[0];
+        assert_eq!(series.derivative(1), 5.0);
+        assert_eq!(series.derivative(2), 0.0);
+
+        let tape: Tape = Tape::new();
+        let v: Var = tape.var(x);
+        let y: Var = apply(&lookup, &[v * v])[0];
+        // x^2 = 5.0625 lies in the interval with slope 16 - 9
+        assert_eq!(tape.gradient(y).wrt(v), 7.0 * 2.0 * x);
+    }
+
+    // Positive root of y^3 + p y - 1 by Newton's method, a black box to autodiff. By implicit
+    // differentiation dy/dp = -y / (3 y^2 + p).
+    #[derive(Clone)]
+    struct CubicRoot;
+
+    impl CustomRule for CubicRoot {
+        fn primal(&self, x: &[f64]) -> Vec<f64> {
+            let p: f64 = x[0];
+            let mut y: f64 = 1.0;
+            for _ in 0..100 {
+                let step: f64 = (y * y * y + p * y - 1.0) / (3.0 * y * y + p);
+                y -= step;
+                if step.abs() < 1e-16 {
+                    break;
+                }
+            }
+            return vec![y];
+        }
+
+        fn jvp<F: Float>(&self, x: &[F], y: &[F], v: &[F]) -> Vec<F> {
+            return vec![-y[0] / (F::from_f64(3.0) * y[0] * y[0] + x[0]) * v[0]];
+        }
+    }
+
+    // The same Newton iteration evaluated directly in F
+    fn unrolled_root<F: Float>(p: F) -> F {
+        let mut y: F = F::one();
+        for _ in 0..60 {
+            y -= (y * y * y + p * y - F::one()) / (F::from_f64(3.0) * y * y + p);
+        }
+        return y;
+    }
+
+    #[test]
+    fn test_solver_higher_derivatives() {
+        let p: f64 = 0.8;
+        let custom: Taylor<f64, 3> = apply(&CubicRoot, &[Taylor::variable(p)])[0];
+        let unrolled: Taylor<f64, 3> = unrolled_root(Taylor::variable(p));
+        for k in 0..=3 {
+            assert!((custom.coeff(k) - unrolled.coeff(k)).abs() < 1e-13);
+        }
+
+        let nested: DiffNum<DiffNum<f64>> = apply(
+            &CubicRoot,
+            &[DiffNum {
+                f: DiffNum { f: p, df: 1.0 },
+                df: DiffNum { f: 1.0, df: 0.0 },
+            }],
+        )[0];
+        assert!((nested.df.df - unrolled.derivative(2)).abs() < 1e-12);
+    }
+
+    // Polar to Cartesian coordinates with an explicit VJP
+    #[derive(Clone)]
+    struct Polar;
+
+    impl CustomRule for Polar {
+        fn primal(&self, x: &[f64]) -> Vec<f64> {
+            return vec![x[0] * x[1].cos(), x[0] * x[1].sin()];
+        }
+
+        fn jvp<F: Float>(&self, x: &[F], _y: &[F], v: &[F]) -> Vec<F> {
+            let (s, c) = x[1].sin_cos();
+            return vec![c * v[0] - x[0] * s * v[1], s * v[0] + x[0] * c * v[1]];
+        }
+
+        fn vjp(&self, x: &[f64], _y: &[f64], w: &[f64]) -> Vec<f64> {
+            let (s, c) = x[1].sin_cos();
+            return vec![c * w[0] + s * w[1], x[0] * (c * w[1] - s * w[0])];
+        }
+    }
+
+    // Uses both outputs of the rule together with ordinary operations
+    struct PolarObjective;
+
+    impl Objective for PolarObjective {
+        fn eval<F: Float>(&self, x: &[F]) -> F {
+            let xy: Vec<F> = apply(&Polar, &[x[0] * x[2], x[1]]);
+            return xy[0] * xy[1].exp() + x[2].sin() * xy[1];
+        }
+    }
+
+    #[test]
+    fn test_reverse_mode_uses_vjp() {
+        // The explicit VJP agrees with the default one built from the JVP
+        #[derive(Clone)]
+        struct DefaultPolar;
+
+        impl CustomRule for DefaultPolar {
+            fn primal(&self, x: &[f64]) -> Vec<f64> {
+                return Polar.primal(x);
+            }
+
+            fn jvp<F: Float>(&self, x: &[F], y: &[F], v: &[F]) -> Vec<F> {
+                return Polar.jvp(x, y, v);
+            }
+        }
+
+        let x: [f64; 3] = [1.3, 0.4, -0.7];
+        let w: [f64; 2] = [0.5, -2.0];
+        let y: Vec<f64> = Polar.primal(&x[..2]);
+        let explicit: Vec<f64> = Polar.vjp(&x[..2], &y, &w);
+        for (a, b) in explicit.iter().zip(DefaultPolar.vjp(&x[..2], &y, &w)) {
+            assert!((a - b).abs() < 1e-15);
+        }
+
+        let forward: Vec<f64> = autodiff_gradient(&PolarObjective, &x);
+        let backward: Vec<f64> = reverse::gradient(&PolarObjective, &x);
+        for (f, b) in forward.iter().zip(&backward) {
+            assert!((f - b).abs() < 1e-14);
+        }
+        let check: GradientCheck = check_gradient(
+            &PolarObjective,
+            &x,
+            &backward,
+            Difference::ComplexStep,
+            1e-20,
+        );
+        assert!(check.max_relative_error() < 1e-14);
+
+        // Constant inputs are not recorded
+        let tape: Tape = Tape::new();
+        let r: Var = tape.var(2.0);
+        let len: usize = tape.len();
+        let xy: Vec<Var> = apply(&Polar, &[r, Var::constant(0.0)]);
+        assert_eq!(tape.len(), len + 2);
+        let adjoints: Adjoints = tape.backward(&xy, &[1.0, 1.0]);
+        assert_eq!(adjoints.wrt(r), 1.0);
+        assert!(apply(&Polar, &[Var::constant(1.0), Var::constant(0.5)])[0].is_constant());
+    }
+
+    #[test]
+    fn test_reverse_mode_calls_vjp_once() {
+        // Polar that counts its JVP and VJP calls
+        #[derive(Clone, Default)]
+        struct Counted {
+            jvps: Rc<Cell<usize>>,
+            vjps: Rc<Cell<usize>>,
+        }
+
+        impl CustomRule for Counted {
+            fn primal(&self, x: &[f64]) -> Vec<f64> {
+                return Polar.primal(x);
+            }
+
+            fn jvp<F: Float>(&self, x: &[F], y: &[F], v: &[F]) -> Vec<F> {
+                self.jvps.set(self.jvps.get() + 1);
+                return Polar.jvp(x, y, v);
+            }
+
+            fn vjp(&self, x: &[f64], y: &[f64], w: &[f64]) -> Vec<f64> {
+                self.vjps.set(self.vjps.get() + 1);
+                return Polar.vjp(x, y, w);
+            }
+        }
+
+        let rule: Counted = Counted::default();
+        let tape: Tape = Tape::new();
+        let r: Var = tape.var(1.5);
+        let theta: Var = tape.var(0.3);
+        let xy: Vec<Var> = apply(&rule, &[r, theta]);
+        assert_eq!(rule.vjps.get(), 0);
+        // Both outputs feed the loss, so the rule sees the sum of their cotangents
+        let loss: Var = xy[0] * xy[1] + xy[0];
+        let adjoints: Adjoints = tape.backward(&[loss], &[1.0]);
+        assert_eq!((rule.jvps.get(), rule.vjps.get()), (0, 1));
+
+        let w: [f64; 2] = [xy[1].value() + 1.0, xy[0].value()];
+        let expected: Vec<f64> = Polar.vjp(&[1.5, 0.3], &Polar.primal(&[1.5, 0.3]), &w);
+        assert!((adjoints.wrt(r) - expected[0]).abs() < 1e-15);
+        assert!((adjoints.wrt(theta) - expected[1]).abs() < 1e-15);
+
+        // Outputs without cotangent skip the VJP
+        tape.backward(&[tape.var(0.0)], &[1.0]);
+        assert_eq!(rule.vjps.get(), 1);
+    }
+}
@@ -0,0 +1,249 @@
+/*
+Floating point abstraction over the primitive floats and DiffNum.
+
+Code written against `Float` runs unchanged on f32, f64 and dual numbers, including nested ones
+such as DiffNum<DiffNum<f64>> for second derivatives. The elementary functions keep the names
+and meaning of their f64 counterparts. Matrices and the linear solvers accept any Float through
+lin_alg::Scalar, and operations with hand-written derivatives enter through apply_rule, see
+custom_rule.
+*/
+
+use crate::auto_diff::DiffNum;
+use crate::custom_rule::CustomRule;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+pub trait Float:
+    Copy
+    + PartialOrd
+    + std::fmt::Debug
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+{
+    // Constant with every derivative part zero
+    fn from_f64(value: f64) -> Self;
+    // Value with every derivative part dropped
+    fn to_f64(self) -> f64;
+
+    fn zero() -> Self {
+        return Self::from_f64(0.0);
+    }
+
+    fn one() -> Self {
+        return Self::from_f64(1.0);
+    }
+
+    fn pi() -> Self {
+        return Self::from_f64(std::f64::consts::PI);
+    }
+
+    fn powi(self, n: i32) -> Self;
+    fn powf(self, n: Self) -> Self;
+    fn sqrt(self) -> Self;
+    fn cbrt(self) -> Self;
+    fn recip(self) -> Self;
+
+    fn exp(self) -> Self;
+    fn exp2(self) -> Self;
+    fn exp_m1(self) -> Self;
+    fn ln(self) -> Self;
+    fn ln_1p(self) -> Self;
+    fn log2(self) -> Self;
+    fn log10(self) -> Self;
+    fn log(self, base: Self) -> Self;
+
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn tan(self) -> Self;
+    fn sin_cos(self) -> (Self, Self);
+    fn asin(self) -> Self;
+    fn acos(self) -> Self;
+    fn atan(self) -> Self;
+    fn atan2(self, other: Self) -> Self;
+
+    fn sinh(self) -> Self;
+    fn cosh(self) -> Self;
+    fn tanh(self) -> Self;
+    fn asinh(self) -> Self;
+    fn acosh(self) -> Self;
+    fn atanh(self) -> Self;
+
+    fn abs(self) -> Self;
+    fn signum(self) -> Self;
+    fn hypot(self, other: Self) -> Self;
+    fn min(self, other: Self) -> Self;
+    fn max(self, other: Self) -> Self;
+    fn mul_add(self, a: Self, b: Self) -> Self;
+
+    // Evaluates a custom rule on x, see custom_rule::apply
+    fn apply_rule<R: CustomRule>(rule: &R, x: &[Self]) -> Vec<Self>;
+}
+
+// Forwards trait methods to the inherent methods of the same name on $t
+macro_rules! forward_methods {
+    ($t:ty; unary: $($unary:ident),*; binary: $($binary:ident),*) => {
+        $(
+            fn $unary(self) -> Self {
+                return <$t>::$unary(self);
+            }
+        )*
+        $(
+            fn $binary(self, other: Self) -> Self {
+                return <$t>::$binary(self, other);
+            }
+        )*
+        fn powi(self, n: i32) -> Self {
+            return <$t>::powi(self, n);
+        }
+        fn sin_cos(self) -> (Self, Self) {
+            return <$t>::sin_cos(self);
+        }
+        fn mul_add(self, a: Self, b: Self) -> Self {
+            return <$t>::mul_add(self, a, b);
+        }
+    };
+}
+
+pub(crate) use forward_methods;
+
+macro_rules! impl_float_primitive {
+    ($t:ty) => {
+        impl Float for $t {
+            fn from_f64(value: f64) -> Self {
+                return value as $t;
+            }
+
+            fn to_f64(self) -> f64 {
+                return self as f64;
+            }
+
+            fn log(self, base: Self) -> Self {
+                return <$t>::log(self, base);
+            }
+
+            fn apply_rule<R: CustomRule>(rule: &R, x: &[Self]) -> Vec<Self> {
+                let values: Vec<f64> = x.iter().map(|&xi| xi as f64).collect();
+                return rule.primal(&values).into_iter().map(|yi| yi as $t).collect();
+            }
+
+            forward_methods!(
+                $t;
+                unary: sqrt, cbrt, recip, exp, exp2, exp_m1, ln, ln_1p, log2, log10, sin, cos,
+                    tan, asin, acos, atan, sinh, cosh, tanh, asinh, acosh, atanh, abs, signum;
+                binary: powf, atan2, hypot, min, max
+            );
+        }
+    };
+}
+
+impl_float_primitive!(f32);
+impl_float_primitive!(f64);
+
+impl<T: Float> Float for DiffNum<T> {
+    fn from_f64(value: f64) -> Self {
+        return DiffNum {
+            f: T::from_f64(value),
+            df: T::zero(),
+        };
+    }
+
+    fn to_f64(self) -> f64 {
+        return self.f.to_f64();
+    }
+
+    // Both the argument and the base may carry derivatives
+    fn log(self, base: Self) -> Self {
+        return self.ln() / base.ln();
+    }
+
+    // The inherent powf takes a constant exponent, the trait version a differentiable one
+    fn powf(self, n: Self) -> Self {
+        return self.pow(n);
+    }
+
+    // Primal of the values and JVP of the tangents
+    fn apply_rule<R: CustomRule>(rule: &R, x: &[Self]) -> Vec<Self> {
+        let values: Vec<T> = x.iter().map(|xi| xi.f).collect();
+        let tangents: Vec<T> = x.iter().map(|xi| xi.df).collect();
+        let f: Vec<T> = T::apply_rule(rule, &values);
+        let df: Vec<T> = rule.jvp(&values, &f, &tangents);
+        return f
+            .into_iter()
+            .zip(df)
+            .map(|(f, df)| DiffNum { f, df })
+            .collect();
+    }
+
+    forward_methods!(
+        DiffNum<T>;
+        unary: sqrt, cbrt, recip, exp, exp2, exp_m1, ln, ln_1p, log2, log10, sin, cos, tan,
+            asin, acos, atan, sinh, cosh, tanh, asinh, acosh, atanh, abs, signum;
+        binary: atan2, hypot, min, max
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Written once against the trait, evaluated for every implementation below
+    fn logistic<F: Float>(x: F) -> F {
+        return F::one() / (F::one() + (-x).exp());
+    }
+
+    #[test]
+    fn test_primitives() {
+        assert_eq!(logistic(0.0_f64), 0.5);
+        assert_eq!(logistic(0.0_f32), 0.5);
+        assert_eq!(<f32 as Float>::from_f64(0.1), 0.1_f32);
+        assert_eq!(Float::powf(2.0_f64, 3.0), 8.0);
+        assert_eq!(f32::pi(), std::f32::consts::PI);
+        assert_eq!(<DiffNum<f64> as Float>::pi().f, std::f64::consts::PI);
+    }
+
+    #[test]
+    fn test_diffnum_f32() {
+        // logistic'(x) = s (1 - s)
+        let y: DiffNum<f32> = logistic(DiffNum {
+            f: 0.0_f32,
+            df: 1.0,
+        });
+        assert_eq!(y.f, 0.5);
+        assert!((y.df - 0.25).abs() < 1e-7);
+
+        let x: DiffNum<f32> = DiffNum { f: 2.0, df: 1.0 };
+        let z: DiffNum<f32> = x.sin() * x.exp();
+        let expected: f32 = 2.0_f32.exp() * (2.0_f32.sin() + 2.0_f32.cos());
+        assert!((z.df - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_nested_diffnum_second_derivative() {
+        // Seeding both levels with 1 puts f'' in df.df for f(x) = x^3 sin(x)
+        let x0: f64 = 0.7;
+        let x: DiffNum<DiffNum<f64>> = DiffNum {
+            f: DiffNum { f: x0, df: 1.0 },
+            df: DiffNum { f: 1.0, df: 0.0 },
+        };
+        let y: DiffNum<DiffNum<f64>> = x.powi(3) * x.sin();
+
+        let f: f64 = x0.powi(3) * x0.sin();
+        let d1: f64 = 3.0 * x0.powi(2) * x0.sin() + x0.powi(3) * x0.cos();
+        let d2: f64 = 6.0 * x0 * x0.sin() + 6.0 * x0.powi(2) * x0.cos() - x0.powi(3) * x0.sin();
+        assert!((y.f.f - f).abs() < 1e-14);
+        assert!((y.f.df - d1).abs() < 1e-13);
+        assert!((y.df.f - d1).abs() < 1e-13);
+        assert!((y.df.df - d2).abs() < 1e-13);
+
+        // Generic code sees the nested type as just another Float
+        let s: DiffNum<DiffNum<f64>> = logistic(x);
+        let sig: f64 = 1.0 / (1.0 + (-x0).exp());
+        assert!((s.df.df - sig * (1.0 - sig) * (1.0 - 2.0 * sig)).abs() < 1e-14);
+    }
+}
@@ -0,0 +1,220 @@
+/*
+Gradient checking: compares a gradient, typically from DiffNum or a hand-written derivative,
+against reference values from finite differences or the complex step.
+
+The objective is written once against Float so it can be evaluated in f64, DiffNum<f64> and
+Complex<f64>. Forward differences have an error of order h and central differences of order
+h^2, both limited by cancellation for small h, so a sweep over step sizes shows the usual
+V-shaped error curve. The complex step has no cancellation and is accurate to machine precision
+for any small h, but only for objectives that are real-analytic in the complex-step sense.
+*/
+
+use crate::auto_diff::DiffNum;
+use crate::complex::Complex;
+use crate::float::Float;
+
+// Scalar objective f: R^n -> R that can be evaluated in any Float type
+pub trait Objective {
+    fn eval<F: Float>(&self, x: &[F]) -> F;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Difference {
+    Forward,
+    Central,
+    ComplexStep,
+}
+
+#[derive(Clone, Debug)]
+pub struct GradientCheck {
+    pub step: f64,
+    pub reference: Vec<f64>,
+    // |g_i - r_i| / max(|g_i|, |r_i|), and 0 where both vanish
+    pub relative_error: Vec<f64>,
+}
+
+impl GradientCheck {
+    pub fn max_relative_error(&self) -> f64 {
+        return self.relative_error.iter().fold(0.0, |acc, &e| acc.max(e));
+    }
+}
+
+// Gradient by forward mode, one DiffNum pass per component
+pub fn autodiff_gradient<O: Objective>(objective: &O, x: &[f64]) -> Vec<f64> {
+    let mut point: Vec<DiffNum<f64>> = x.iter().map(|&xi| DiffNum { f: xi, df: 0.0 }).collect();
+    let mut gradient: Vec<f64> = Vec::with_capacity(x.len());
+    for i in 0..x.len() {
+        point[i].df = 1.0;
+        gradient.push(objective.eval(&point).df);
+        point[i].df = 0.0;
+    }
+    return gradient;
+}
+
+// Reference gradient with step h
+pub fn reference_gradient<O: Objective>(
+    objective: &O,
+    x: &[f64],
+    method: Difference,
+    h: f64,
+) -> Vec<f64> {
+    let mut gradient: Vec<f64> = Vec::with_capacity(x.len());
+    match method {
+        Difference::Forward => {
+            let f0: f64 = objective.eval(x);
+            let mut point: Vec<f64> = x.to_vec();
+            for i in 0..x.len() {
+                point[i] = x[i] + h;
+                gradient.push((objective.eval(&point) - f0) / h);
+                point[i] = x[i];
+            }
+        }
+        Difference::Central => {
+            let mut point: Vec<f64> = x.to_vec();
+            for i in 0..x.len() {
+                point[i] = x[i] + h;
+                let f_plus: f64 = objective.eval(&point);
+                point[i] = x[i] - h;
+                let f_minus: f64 = objective.eval(&point);
+                point[i] = x[i];
+                gradient.push((f_plus - f_minus) / (2.0 * h));
+            }
+        }
+        Difference::ComplexStep => {
+            let mut point: Vec<Complex<f64>> = x.iter().map(|&xi| Complex::new(xi, 0.0)).collect();
+            for i in 0..x.len() {
+                point[i].im = h;
+                gradient.push(objective.eval(&point).im / h);
+                point[i].im = 0.0;
+            }
+        }
+    }
+    return gradient;
+}
+
+// Compares gradient against the reference computed with step h
+pub fn check_gradient<O: Objective>(
+    objective: &O,
+    x: &[f64],
+    gradient: &[f64],
+    method: Difference,
+    h: f64,
+) -> GradientCheck {
+    assert_eq!(
+        gradient.len(),
+        x.len(),
+        "Gradient has {} components for {} variables",
+        gradient.len(),
+        x.len()
+    );
+    let reference: Vec<f64> = reference_gradient(objective, x, method, h);
+    let mut relative_error: Vec<f64> = Vec::with_capacity(x.len());
+    for i in 0..x.len() {
+        let scale: f64 = gradient[i].abs().max(reference[i].abs());
+        relative_error.push(if scale == 0.0 {
+            0.0
+        } else {
+            (gradient[i] - reference[i]).abs() / scale
+        });
+    }
+    return GradientCheck {
+        step: h,
+        reference,
+        relative_error,
+    };
+}
+
+// One check per step size, e.g. for steps 1e-1, 1e-2, ... to locate the best finite difference
+pub fn step_sweep<O: Objective>(
+    objective: &O,
+    x: &[f64],
+    gradient: &[f64],
+    method: Difference,
+    steps: &[f64],
+) -> Vec<GradientCheck> {
+    return steps
+        .iter()
+        .map(|&h| check_gradient(objective, x, gradient, method, h))
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // f(x, y, z) = exp(x) sin(y) / (1 + z^2) + ln(x y) + atan2(y, z)
+    struct Example;
+
+    impl Objective for Example {
+        fn eval<F: Float>(&self, x: &[F]) -> F {
+            return x[0].exp() * x[1].sin() / (F::one() + x[2] * x[2])
+                + (x[0] * x[1]).ln()
+                + x[1].atan2(x[2]);
+        }
+    }
+
+    fn analytic_gradient(x: &[f64]) -> Vec<f64> {
+        let den: f64 = 1.0 + x[2] * x[2];
+        let r2: f64 = x[1] * x[1] + x[2] * x[2];
+        return vec![
+            x[0].exp() * x[1].sin() / den + 1.0 / x[0],
+            x[0].exp() * x[1].cos() / den + 1.0 / x[1] + x[2] / r2,
+            -2.0 * x[2] * x[0].exp() * x[1].sin() / (den * den) - x[1] / r2,
+        ];
+    }
+
+    #[test]
+    fn test_autodiff_matches_analytic() {
+        let x: [f64; 3] = [0.7, 1.3, -0.4];
+        let gradient: Vec<f64> = autodiff_gradient(&Example, &x);
+        for (g, a) in gradient.iter().zip(analytic_gradient(&x)) {
+            assert!((g - a).abs() < 1e-14);
+        }
+    }
+
+    #[test]
+    fn test_check_gradient_methods() {
+        let x: [f64; 3] = [0.7, 1.3, -0.4];
+        let gradient: Vec<f64> = autodiff_gradient(&Example, &x);
+
+        let complex: GradientCheck =
+            check_gradient(&Example, &x, &gradient, Difference::ComplexStep, 1e-20);
+        assert!(complex.max_relative_error() < 1e-14);
+        let central: GradientCheck =
+            check_gradient(&Example, &x, &gradient, Difference::Central, 1e-5);
+        assert!(central.max_relative_error() < 1e-8);
+        let forward: GradientCheck =
+            check_gradient(&Example, &x, &gradient, Difference::Forward, 1e-7);
+        assert!(forward.max_relative_error() < 1e-5);
+        assert_eq!(forward.relative_error.len(), 3);
+
+        // A wrong component is flagged and the others are not
+        let mut wrong: Vec<f64> = gradient.clone();
+        wrong[1] *= 1.01;
+        let check: GradientCheck =
+            check_gradient(&Example, &x, &wrong, Difference::ComplexStep, 1e-20);
+        assert!(check.relative_error[0] < 1e-14 && check.relative_error[2] < 1e-14);
+        assert!((check.relative_error[1] - 0.01 / 1.01).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_step_sweep() {
+        let x: [f64; 3] = [0.7, 1.3, -0.4];
+        let gradient: Vec<f64> = analytic_gradient(&x);
+        let steps: Vec<f64> = (1..=12).map(|k| 10.0_f64.powi(-k)).collect();
+
+        // Truncation error shrinks first, then cancellation takes over
+        let sweep: Vec<GradientCheck> =
+            step_sweep(&Example, &x, &gradient, Difference::Central, &steps);
+        let errors: Vec<f64> = sweep.iter().map(|c| c.max_relative_error()).collect();
+        assert_eq!(sweep[3].step, 1e-4);
+        assert!(errors[1] < errors[0] && errors[2] < errors[1]);
+        assert!(errors[11] > errors[4]);
+
+        // The complex step stays accurate down to tiny steps
+        let steps: Vec<f64> = vec![1e-8, 1e-50, 1e-150];
+        for check in step_sweep(&Example, &x, &gradient, Difference::ComplexStep, &steps) {
+            assert!(check.max_relative_error() < 1e-14);
+        }
+    }
+}
@@ -1,102 +1,216 @@
+pub mod adjoint;
+pub mod banded;
+pub mod block;
+pub mod factorization;
+mod gemm;
+pub mod inverse;
+mod kernels;
 pub mod mat;
+pub mod matrix_equations;
+pub mod matrix_functions;
+pub mod norm;
+pub mod permutation;
+mod scalar;
+pub mod schur;
+pub mod shape;
+pub mod smat;
+pub mod sparse;
+pub mod triangular;
 
-pub fn lin_solve(A: &mat::Matrix<f64>, b: &mat::Matrix<f64>) -> mat::Matrix<f64> {
-    assert_eq!(
-        A.get_dim().0,
-        b.get_dim().0,
-        "Incompatible matrix dimensions"
-    );
-    assert_eq!(b.get_dim().1, 1, "b must be a column vector");
+pub use factorization::Factorization;
+pub use gemm::gemm;
+pub use scalar::Scalar;
+
+// Errors reported by fallible linear algebra routines
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinAlgError {
+    DimensionMismatch {
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+    Singular,
+    NotConverged,
+    NotPositiveDefinite,
+    NotFinite,
+}
+
+impl std::fmt::Display for LinAlgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            LinAlgError::DimensionMismatch { expected, found } => write!(
+                f,
+                "dimension mismatch: expected {:?}, found {:?}",
+                expected, found
+            ),
+            LinAlgError::Singular => write!(f, "matrix is singular"),
+            LinAlgError::NotConverged => write!(f, "iteration did not converge"),
+            LinAlgError::NotPositiveDefinite => write!(f, "matrix is not positive definite"),
+            LinAlgError::NotFinite => write!(f, "matrix has infinite or NaN entries"),
+        };
+    }
+}
+
+impl std::error::Error for LinAlgError {}
 
-    // TODO: Add more safety checks
+// Solves A * X = B for every column of B, in the least squares sense if A has more rows than
+// columns. Fails if A is wide or numerically rank deficient, see Factorization::solve. To reuse
+// the factorisation of A across calls, build a Factorization directly.
+pub fn lin_solve<F: Scalar>(
+    A: &mat::Matrix<F>,
+    b: &mat::Matrix<F>,
+) -> Result<mat::Matrix<F>, LinAlgError> {
+    return F::lin_solve(A, b);
+}
 
+// Estimates the 1-norm condition number ||A||_1 * ||A^-1||_1 of a square matrix without forming
+// the inverse, using Hager's method with Higham's extra test vector. The estimate is a lower
+// bound that is usually within a small factor of the true value. For dual or Taylor entries it
+// is the condition number of the values, which governs the accuracy of the derivatives as well.
+pub fn cond_estimate<F: Scalar>(A: &mat::Matrix<F>) -> f64 {
     let (r, c) = A.get_dim();
+    assert_eq!(r, c, "Condition number requires a square matrix.");
+    if r == 0 {
+        return 0.0;
+    }
+
+    let values: Vec<f64> = A.clone().into_vec().into_iter().map(F::to_f64).collect();
+    let A: mat::Matrix<f64> = mat::Matrix::from_vec((r, c), values, A.get_layout());
+    return A.norm_1() * inverse_norm_1_estimate(&A);
+}
 
-    let (Q, R) = qr_decomposition(A);
-    let y: mat::Matrix<f64> = &Q.transpose() * b;
-    let mut x: mat::Matrix<f64> = mat::Matrix::new((A.get_dim().1, 1));
-    for i in (0..r).rev() {
-        let mut sum: f64 = 0.0;
-        for j in (i + 1)..c {
-            sum += R.get(i, j) * x.get(j, 0);
+fn inverse_norm_1_estimate(A: &mat::Matrix<f64>) -> f64 {
+    let n: usize = A.get_dim().0;
+
+    let factorization: Factorization = Factorization::qr(A).expect("A is square");
+    let solve = |x: &mat::Matrix<f64>| factorization.solve(x);
+    let solve_transposed = |x: &mat::Matrix<f64>| factorization.solve_transposed(x);
+
+    let mut x: mat::Matrix<f64> = &mat::Matrix::ones((n, 1)) / (n as f64);
+    let mut estimate: f64 = 0.0;
+    let mut last_index: Option<usize> = None;
+
+    const MAX_ITER: usize = 5;
+    for _ in 0..MAX_ITER {
+        let y: mat::Matrix<f64> = match solve(&x) {
+            Ok(y) => y,
+            Err(_) => return f64::INFINITY,
+        };
+        estimate = y.norm_p(1.0);
+        if !estimate.is_finite() {
+            return f64::INFINITY;
+        }
+
+        let mut xi: mat::Matrix<f64> = mat::Matrix::new((n, 1));
+        for i in 0..n {
+            xi.set(i, 0, if y.get(i, 0) >= 0.0 { 1.0 } else { -1.0 });
         }
-        x.set(i, 0, (y.get(i, 0) - sum) / R.get(i, i));
+        let z: mat::Matrix<f64> = match solve_transposed(&xi) {
+            Ok(z) => z,
+            Err(_) => return f64::INFINITY,
+        };
+
+        let mut j_max: usize = 0;
+        for i in 1..n {
+            if z.get(i, 0).abs() > z.get(j_max, 0).abs() {
+                j_max = i;
+            }
+        }
+        if z.get(j_max, 0).abs() <= z.dot_product(&x) || last_index == Some(j_max) {
+            break;
+        }
+
+        x = mat::Matrix::new((n, 1));
+        x.set(j_max, 0, 1.0);
+        last_index = Some(j_max);
     }
 
-    return x;
+    // Higham's alternating test vector guards against cases where the iteration stalls
+    let mut b: mat::Matrix<f64> = mat::Matrix::new((n, 1));
+    for i in 0..n {
+        let sign: f64 = if i % 2 == 0 { 1.0 } else { -1.0 };
+        let scale: f64 = if n > 1 {
+            1.0 + (i as f64) / ((n - 1) as f64)
+        } else {
+            1.0
+        };
+        b.set(i, 0, sign * scale);
+    }
+    let alt_estimate: f64 = match solve(&b) {
+        Ok(x) => 2.0 * x.norm_p(1.0) / (3.0 * n as f64),
+        Err(_) => return f64::INFINITY,
+    };
+
+    return estimate.max(alt_estimate);
 }
 
-fn qr_decomposition(A: &mat::Matrix<f64>) -> (mat::Matrix<f64>, mat::Matrix<f64>) {
+fn qr_decomposition<F: Scalar>(A: &mat::Matrix<F>) -> (mat::Matrix<F>, mat::Matrix<F>) {
     assert!(
         A.get_dim().0 >= A.get_dim().1,
         "Matrix A of dimension m x n must have m >= n."
     );
 
-    // TODO: Add check to make sure A is not singular
-
     let (r, c) = A.get_dim();
 
-    let mut Q: mat::Matrix<f64> = mat::Matrix::identity(r);
-    let mut R: mat::Matrix<f64> = A.clone();
+    let mut Q: mat::Matrix<F> = mat::Matrix::identity(r);
+    let mut R: mat::Matrix<F> = A.clone();
 
-    // TODO: This can be optimised to avoid creating so many temporary matrices
     for i in 0..c {
-        // Get the column vector from R for th k-th column from row k to r
-        let rv: mat::Matrix<f64> = {
-            let mut rv_temp = mat::Matrix::new((r - i, 1));
-            for j in i..r {
-                rv_temp.set(j - i, 0, R.get(j, i));
-            }
-            rv_temp
+        // Householder vector for the i-th column of R from row i to r
+        let mut v: mat::Matrix<F> = mat::Matrix::new((r - i, 1));
+        for j in i..r {
+            v.set(j - i, 0, R.get(j, i));
+        }
+        let sign_v1: F = if v.get(0, 0) >= F::zero() {
+            F::one()
+        } else {
+            -F::one()
         };
+        v.set(0, 0, v.get(0, 0) + sign_v1 * v.norm());
 
-        let e: mat::Matrix<f64> = {
-            let mut e_temp: mat::Matrix<f64> = mat::Matrix::new((r - i, 1));
-            e_temp.set(0, 0, rv.norm());
-            e_temp
-        };
+        let v_dot: F = v.dot_product(&v);
+        if v_dot == F::zero() {
+            // Column is already zero below the diagonal
+            continue;
+        }
+        let beta: F = F::from_f64(2.0) / v_dot;
 
-        let sign_rv1 = if rv.get(0, 0) >= 0.0 { 1.0 } else { -1.0 };
-        let v: mat::Matrix<f64> = {
-            let mut v_temp: mat::Matrix<f64> = mat::Matrix::new((r - i, 1));
+        // Apply H = I - beta * v * v^T in place as R = H * R and Q = Q * H^T. H only touches
+        // rows i..r of R and columns i..r of Q, so no full size temporaries are needed.
+        for k in 0..c {
+            let mut s: F = F::zero();
             for j in 0..(r - i) {
-                v_temp.set(j, 0, rv.get(j, 0) + sign_rv1 * e.get(j, 0));
+                s += v.get(j, 0) * R.get(i + j, k);
             }
-            v_temp
-        };
-        let v_dot: f64 = v.dot_product(&v);
-        let sub_h: mat::Matrix<f64> =
-            mat::Matrix::identity(r - i) - 2.0 * (1.0 / v_dot) * &(&v * &v.transpose());
-        let mut h: mat::Matrix<f64> = mat::Matrix::identity(r);
-        for j in i..r {
-            for k in i..r {
-                h.set(j, k, sub_h.get(j - i, k - i));
+            for j in 0..(r - i) {
+                R.set(i + j, k, R.get(i + j, k) - beta * s * v.get(j, 0));
+            }
+        }
+        for row in 0..r {
+            let mut s: F = F::zero();
+            for j in 0..(r - i) {
+                s += Q.get(row, i + j) * v.get(j, 0);
+            }
+            for j in 0..(r - i) {
+                Q.set(row, i + j, Q.get(row, i + j) - beta * s * v.get(j, 0));
             }
         }
-
-        Q = &Q * &h.transpose();
-        R = &h * &R;
     }
 
     return (Q, R);
 }
 
+// Regular 3x3 matrix whose leading entry is zero, so LU needs pivoting. Shared by the tests of the
+// factorisations and the routines built on them.
 #[cfg(test)]
+pub(crate) fn example_matrix() -> mat::Matrix<f64> {
+    return mat::Matrix::from_rows(&[[0.0, 2.0, 1.0], [1.0, 1.0, 0.0], [3.0, -1.0, 2.0]]);
+}
+
+#[cfg(test)]
+#[allow(clippy::excessive_precision)]
 mod tests {
     use super::*;
-
-    // Helper function to check if all elements of a matrix are below a tolerance
-    fn matrix_elements_below_tolerance(matrix: &mat::Matrix<f64>, tolerance: f64) -> bool {
-        let (rows, cols) = matrix.get_dim();
-        for r in 0..rows {
-            for c in 0..cols {
-                if matrix.get(r, c) >= tolerance {
-                    return false;
-                }
-            }
-        }
-        true
-    }
+    use crate::auto_diff::DiffNum;
 
     #[test]
     fn test_qr_decomposition() {
@@ -139,18 +253,29 @@ mod tests {
 
         // Check that Q*R reconstructs the original matrix A
         // For floating point results, we need tolerance-based comparison
-        let reconstruction_error = (&a - &a_reconstructed).abs();
-        assert!(matrix_elements_below_tolerance(
-            &reconstruction_error,
-            1e-10
-        ));
+        crate::assert_matrix_approx_eq!(a, a_reconstructed, 1e-10, 0.0);
 
         // Note: Due to floating point precision, we can't use exact equality for Q and R
-        // so we use matrix operations with tolerance checking
-        let q_error = (&q - &q_expected).abs();
-        let r_error = (&r - &r_expected).abs();
-        assert!(matrix_elements_below_tolerance(&q_error, 1e-10));
-        assert!(matrix_elements_below_tolerance(&r_error, 1e-10));
+        crate::assert_matrix_approx_eq!(q, q_expected, 1e-10, 0.0);
+        crate::assert_matrix_approx_eq!(r, r_expected, 1e-10, 0.0);
+    }
+
+    #[test]
+    fn test_qr_decomposition_zero_column() {
+        // The first column is zero, so its Householder vector vanishes and the reflection is
+        // skipped instead of dividing by zero
+        let a: mat::Matrix<f64> = mat::Matrix::from_vec(
+            (3, 2),
+            vec![0.0, 1.0, 0.0, 2.0, 0.0, 2.0],
+            mat::Layout::RowMajor,
+        );
+        let (q, r) = qr_decomposition(&a);
+        assert_eq!(r.get(0, 0), 0.0);
+        crate::assert_matrix_approx_eq!(&q * &r, a, 1e-14, 0.0);
+        crate::assert_matrix_approx_eq!(&q.transpose() * &q, mat::Matrix::identity(3), 1e-14, 0.0);
+        // The first row of R is left as it was
+        assert_eq!(r.get(0, 1), 1.0);
+        assert!((r.get(1, 1).abs() - 8.0_f64.sqrt()).abs() < 1e-14);
     }
 
     #[test]
@@ -171,7 +296,7 @@ mod tests {
         b.set(1, 0, 4.0);
         b.set(2, 0, 2.0);
 
-        let x = lin_solve(&a, &b);
+        let x = lin_solve(&a, &b).unwrap();
 
         let mut x_expected = mat::Matrix::new((3, 1));
         x_expected.set(0, 0, 1.153846153846154);
@@ -179,8 +304,128 @@ mod tests {
         x_expected.set(2, 0, 0.230769230769231);
 
         // Note: Due to floating point precision, we can't use exact equality
-        // so we use matrix operations with tolerance checking
-        let solution_error = (&x - &x_expected).abs();
-        assert!(matrix_elements_below_tolerance(&solution_error, 1e-10));
+        crate::assert_matrix_approx_eq!(x, x_expected, 1e-10, 0.0);
+    }
+
+    #[test]
+    fn test_solve_multiple_rhs() {
+        let mut a = mat::Matrix::identity(3);
+        a.set(0, 2, 2.0);
+        a.set(2, 1, -1.0);
+        let mut b = mat::Matrix::new((3, 50));
+        for c in 0..50 {
+            b.set(c % 3, c, c as f64);
+        }
+        let x = lin_solve(&a, &b).unwrap();
+        assert_eq!(x.get_dim(), (3, 50));
+        crate::assert_matrix_approx_eq!(&a * &x, b);
+    }
+
+    #[test]
+    fn test_solve_least_squares() {
+        // Fit y = c0 + c1 * t through (0, 1), (1, 3), (2, 4): c0 = 7/6, c1 = 3/2
+        let mut a = mat::Matrix::new((3, 2));
+        let mut b = mat::Matrix::new((3, 1));
+        for (i, y) in [1.0, 3.0, 4.0].iter().enumerate() {
+            a.set(i, 0, 1.0);
+            a.set(i, 1, i as f64);
+            b.set(i, 0, *y);
+        }
+        let x = lin_solve(&a, &b).unwrap();
+        assert!((x.get(0, 0) - 7.0 / 6.0).abs() < 1e-12);
+        assert!((x.get(1, 0) - 1.5).abs() < 1e-12);
+    }
+
+    // Residual of the least squares fit above, written once for every Scalar
+    fn fit_residual<F: Scalar>() -> F {
+        let mut a: mat::Matrix<F> = mat::Matrix::ones((3, 2));
+        let mut b: mat::Matrix<F> = mat::Matrix::new((3, 1));
+        for (i, y) in [1.0, 3.0, 4.0].iter().enumerate() {
+            a.set(i, 1, F::from_f64(i as f64));
+            b.set(i, 0, F::from_f64(*y));
+        }
+        let x: mat::Matrix<F> = lin_solve(&a, &b).unwrap();
+        return (&(&a * &x) - &b).norm();
+    }
+
+    #[test]
+    fn test_solve_generic_scalars() {
+        let expected: f64 = 1.0 / 6.0_f64.sqrt();
+        assert!((fit_residual::<f64>() - expected).abs() < 1e-12);
+        assert!((fit_residual::<f32>() - expected as f32).abs() < 1e-5);
+        let r: crate::auto_diff::DiffNum<f64> = fit_residual();
+        assert!((r.f - expected).abs() < 1e-12);
+        assert_eq!(r.df, 0.0);
+    }
+
+    #[test]
+    fn test_solve_singular() {
+        let mut a = mat::Matrix::new((2, 2));
+        a.set(0, 0, 1.0);
+        a.set(1, 0, 2.0);
+        assert_eq!(
+            lin_solve(&a, &mat::Matrix::ones((2, 1))).unwrap_err(),
+            LinAlgError::Singular
+        );
+        assert_eq!(
+            lin_solve(&a, &mat::Matrix::ones((3, 1))).unwrap_err(),
+            LinAlgError::DimensionMismatch {
+                expected: (2, 1),
+                found: (3, 1)
+            }
+        );
+    }
+
+    #[test]
+    fn test_cond_estimate() {
+        // ||A||_1 = 4 and ||A^-1||_1 = 11/13 for this matrix
+        let mut a = mat::Matrix::new((3, 3));
+        a.set(0, 0, 2.0);
+        a.set(0, 1, -1.0);
+        a.set(0, 2, 0.0);
+        a.set(1, 0, 1.0);
+        a.set(1, 1, 2.0);
+        a.set(1, 2, 1.0);
+        a.set(2, 0, 0.0);
+        a.set(2, 1, 1.0);
+        a.set(2, 2, 3.0);
+        assert!((cond_estimate(&a) - 44.0 / 13.0).abs() < 1e-10);
+
+        assert!((cond_estimate(&mat::Matrix::<f64>::identity(4)) - 1.0).abs() < 1e-12);
+
+        let mut singular = mat::Matrix::ones((2, 2));
+        singular.set(0, 0, 0.0);
+        singular.set(0, 1, 0.0);
+        assert_eq!(cond_estimate(&singular), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_cond_estimate_ill_conditioned() {
+        // Hilbert matrices are notoriously ill-conditioned: cond_1(H_5) is about 9.4e5
+        let n: usize = 5;
+        let mut h = mat::Matrix::new((n, n));
+        for i in 0..n {
+            for j in 0..n {
+                h.set(i, j, 1.0 / ((i + j + 1) as f64));
+            }
+        }
+        let estimate: f64 = cond_estimate(&h);
+        assert!(estimate > 9.0e5 && estimate < 1.0e6);
+
+        // Dual entries are estimated through their values
+        let mut dual: mat::Matrix<DiffNum<f64>> = mat::Matrix::new((n, n));
+        for i in 0..n {
+            for j in 0..n {
+                dual.set(
+                    i,
+                    j,
+                    DiffNum {
+                        f: h.get(i, j),
+                        df: 1.0,
+                    },
+                );
+            }
+        }
+        assert_eq!(cond_estimate(&dual), estimate);
     }
 }
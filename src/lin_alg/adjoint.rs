@@ -0,0 +1,479 @@
+/*
+Adjoint (vector-Jacobian) rules for reverse mode differentiation through the linear solvers and
+factorisations. Each rule takes the primal inputs and outputs together with the cotangent of
+the output, i.e. the gradient of a scalar loss with respect to it, and returns the cotangents of
+the inputs. They cost one extra solve or triangular solve with the existing factors instead of
+differentiating through every elementary operation of the factorisation.
+
+Forward mode needs no special rules: the solvers are generic over Scalar and run directly on
+Matrix<DiffNum<f64>>. Reverse mode uses the rules through Scalar: lin_solve with square A,
+Matrix::inverse and Matrix::log_det on a Matrix<Var> apply a CustomRule whose VJP is the rule
+below, so the tape holds one node per output instead of the whole factorisation.
+*/
+
+use super::factorization::Factorization;
+use super::mat::{Layout, Matrix};
+use super::triangular::{solve_triangular, Diagonal, Triangle};
+use super::LinAlgError;
+use super::Scalar;
+use crate::custom_rule::{apply, CustomRule};
+use crate::float::Float;
+use crate::reverse::Var;
+use std::rc::Rc;
+
+// For X = A^-1 B with square A, returns (A_bar, B_bar) with B_bar = A^-T X_bar and
+// A_bar = -B_bar X^T
+pub fn lin_solve_adjoint<F: Scalar>(
+    A: &Matrix<F>,
+    X: &Matrix<F>,
+    X_bar: &Matrix<F>,
+) -> Result<(Matrix<F>, Matrix<F>), LinAlgError> {
+    let B_bar: Matrix<F> = Factorization::lu(A)?.solve_transposed(X_bar)?;
+    let A_bar: Matrix<F> = -(&B_bar * &X.transpose());
+    return Ok((A_bar, B_bar));
+}
+
+// For A = Q * R as computed by Factorization::qr, with A of size m x n and m >= n. Only the
+// first n columns of Q are determined by A, so the remaining columns of Q_bar are ignored, as
+// are the rows of R_bar below row n. Uses A_bar = (Q_bar + Q copyltu(M)) R^-T with
+// M = R R_bar^T - Q_bar^T Q on the thin factors, where copyltu mirrors the lower triangle of M
+// onto the upper one. Fails with LinAlgError::Singular if A is rank deficient.
+pub fn qr_adjoint<F: Scalar>(
+    Q: &Matrix<F>,
+    R: &Matrix<F>,
+    Q_bar: &Matrix<F>,
+    R_bar: &Matrix<F>,
+) -> Result<Matrix<F>, LinAlgError> {
+    let m: usize = Q.get_dim().0;
+    let n: usize = R.get_dim().1;
+    if Q_bar.get_dim() != Q.get_dim() {
+        return Err(LinAlgError::DimensionMismatch {
+            expected: Q.get_dim(),
+            found: Q_bar.get_dim(),
+        });
+    }
+    if R_bar.get_dim() != R.get_dim() {
+        return Err(LinAlgError::DimensionMismatch {
+            expected: R.get_dim(),
+            found: R_bar.get_dim(),
+        });
+    }
+
+    let Q1: Matrix<F> = Q.get_block(0, 0, (m, n));
+    let Q1_bar: Matrix<F> = Q_bar.get_block(0, 0, (m, n));
+    let R1: Matrix<F> = R.get_block(0, 0, (n, n));
+    let R1_bar: Matrix<F> = R_bar.get_block(0, 0, (n, n));
+
+    let M: Matrix<F> = &(&R1 * &R1_bar.transpose()) - &(&Q1_bar.transpose() * &Q1);
+    let mut C: Matrix<F> = Matrix::<F>::new((n, n));
+    for i in 0..n {
+        for j in 0..n {
+            C.set(i, j, M.get(i.max(j), i.min(j)));
+        }
+    }
+    let G: Matrix<F> = Q1_bar + &(&Q1 * &C);
+
+    // A_bar = G R^-T, i.e. R A_bar^T = G^T
+    let A_bar_t: Matrix<F> = solve_triangular(
+        &R1,
+        &G.transpose(),
+        Triangle::Upper,
+        false,
+        Diagonal::NonUnit,
+    )?;
+    return Ok(A_bar_t.into_transpose());
+}
+
+// For Y = A^-1, returns A_bar = -Y^T Y_bar Y^T
+pub fn inverse_adjoint<F: Scalar>(Y: &Matrix<F>, Y_bar: &Matrix<F>) -> Matrix<F> {
+    let Yt: Matrix<F> = Y.transpose();
+    return -(&(&Yt * Y_bar) * &Yt);
+}
+
+// For y = log|det(A)|, returns A_bar = y_bar A^-T. The same cotangent scaled by det(A) gives the
+// rule for det itself.
+pub fn log_det_adjoint<F: Scalar>(A: &Matrix<F>, y_bar: F) -> Result<Matrix<F>, LinAlgError> {
+    let n: usize = A.get_dim().0;
+    let A_inv_t: Matrix<F> = Factorization::lu(A)?.solve_transposed(&Matrix::<F>::identity(n))?;
+    return Ok(A_inv_t * y_bar);
+}
+
+// The tape rules below flatten every matrix in row-major order
+fn row_major<F: Scalar>(M: &Matrix<F>) -> Vec<F> {
+    return M.to_layout(Layout::RowMajor).into_vec();
+}
+
+fn from_row_major(dim: (usize, usize), data: &[f64]) -> Matrix<f64> {
+    return Matrix::from_vec(dim, data.to_vec(), Layout::RowMajor);
+}
+
+fn values(M: &Matrix<Var>) -> Matrix<f64> {
+    let entries: Vec<f64> = row_major(M).into_iter().map(Var::value).collect();
+    return from_row_major(M.get_dim(), &entries);
+}
+
+// Values of square A factorised once for a rule, which therefore never meets a singular matrix
+fn regular_lu(A: &Matrix<Var>) -> Result<Rc<Factorization<f64>>, LinAlgError> {
+    let lu: Factorization<f64> = Factorization::lu(&values(A))?;
+    lu.check_singular()?;
+    return Ok(Rc::new(lu));
+}
+
+// X = A^-1 B with inputs (A, B). The JVPs of these rules run on the f64 factors and only
+// complete the CustomRule, since Var calls the VJP.
+#[derive(Clone)]
+struct LinSolveRule {
+    lu: Rc<Factorization<f64>>,
+    n: usize,
+    k: usize,
+}
+
+impl CustomRule for LinSolveRule {
+    fn primal(&self, x: &[f64]) -> Vec<f64> {
+        let B: Matrix<f64> = from_row_major((self.n, self.k), &x[self.n * self.n..]);
+        return row_major(&self.lu.solve(&B).expect("A is regular"));
+    }
+
+    // dX = A^-1 (dB - dA X)
+    fn jvp<F: Float>(&self, _x: &[F], y: &[F], v: &[F]) -> Vec<F> {
+        let (n, k) = (self.n, self.k);
+        let y: Vec<f64> = y.iter().map(|yi| yi.to_f64()).collect();
+        let v: Vec<f64> = v.iter().map(|vi| vi.to_f64()).collect();
+        let dA: Matrix<f64> = from_row_major((n, n), &v[..n * n]);
+        let dB: Matrix<f64> = from_row_major((n, k), &v[n * n..]);
+        let R: Matrix<f64> = &dB - &(&dA * &from_row_major((n, k), &y));
+        let dX: Matrix<f64> = self.lu.solve(&R).expect("A is regular");
+        return row_major(&dX).into_iter().map(F::from_f64).collect();
+    }
+
+    fn vjp(&self, x: &[f64], y: &[f64], w: &[f64]) -> Vec<f64> {
+        let (n, k) = (self.n, self.k);
+        let A: Matrix<f64> = from_row_major((n, n), &x[..n * n]);
+        let X: Matrix<f64> = from_row_major((n, k), y);
+        let (A_bar, B_bar) =
+            lin_solve_adjoint(&A, &X, &from_row_major((n, k), w)).expect("A is regular");
+        let mut result: Vec<f64> = row_major(&A_bar);
+        result.extend(row_major(&B_bar));
+        return result;
+    }
+}
+
+// Y = A^-1
+#[derive(Clone)]
+struct InverseRule {
+    lu: Rc<Factorization<f64>>,
+    n: usize,
+}
+
+impl CustomRule for InverseRule {
+    fn primal(&self, _x: &[f64]) -> Vec<f64> {
+        let I: Matrix<f64> = Matrix::<f64>::identity(self.n);
+        return row_major(&self.lu.solve(&I).expect("A is regular"));
+    }
+
+    // dY = -Y dA Y
+    fn jvp<F: Float>(&self, _x: &[F], y: &[F], v: &[F]) -> Vec<F> {
+        let n: usize = self.n;
+        let y: Vec<f64> = y.iter().map(|yi| yi.to_f64()).collect();
+        let v: Vec<f64> = v.iter().map(|vi| vi.to_f64()).collect();
+        let Y: Matrix<f64> = from_row_major((n, n), &y);
+        let dY: Matrix<f64> = -(&(&Y * &from_row_major((n, n), &v)) * &Y);
+        return row_major(&dY).into_iter().map(F::from_f64).collect();
+    }
+
+    fn vjp(&self, _x: &[f64], y: &[f64], w: &[f64]) -> Vec<f64> {
+        let n: usize = self.n;
+        let Y: Matrix<f64> = from_row_major((n, n), y);
+        return row_major(&inverse_adjoint(&Y, &from_row_major((n, n), w)));
+    }
+}
+
+// y = log|det(A)|
+#[derive(Clone)]
+struct LogDetRule {
+    lu: Rc<Factorization<f64>>,
+    n: usize,
+}
+
+impl CustomRule for LogDetRule {
+    fn primal(&self, _x: &[f64]) -> Vec<f64> {
+        let LU: &Matrix<f64> = match &*self.lu {
+            Factorization::Lu { LU, .. } => LU,
+            Factorization::Qr { .. } => unreachable!(),
+        };
+        return vec![LU.diagonal().iter().map(|d| d.abs().ln()).sum()];
+    }
+
+    // dy = tr(A^-1 dA)
+    fn jvp<F: Float>(&self, _x: &[F], _y: &[F], v: &[F]) -> Vec<F> {
+        let v: Vec<f64> = v.iter().map(|vi| vi.to_f64()).collect();
+        let dA: Matrix<f64> = from_row_major((self.n, self.n), &v);
+        let trace: f64 = self.lu.solve(&dA).expect("A is regular").trace();
+        return vec![F::from_f64(trace)];
+    }
+
+    fn vjp(&self, x: &[f64], _y: &[f64], w: &[f64]) -> Vec<f64> {
+        let A: Matrix<f64> = from_row_major((self.n, self.n), x);
+        return row_major(&log_det_adjoint(&A, w[0]).expect("A is regular"));
+    }
+}
+
+// Scalar::lin_solve for Var. Least squares solves with tall A are taped element by element.
+pub(crate) fn record_lin_solve<'t>(
+    A: &Matrix<Var<'t>>,
+    B: &Matrix<Var<'t>>,
+) -> Result<Matrix<Var<'t>>, LinAlgError> {
+    let (n, cols) = A.get_dim();
+    if n != cols {
+        return Factorization::qr(A)?.solve(B);
+    }
+    let k: usize = B.get_dim().1;
+    if B.get_dim().0 != n {
+        return Err(LinAlgError::DimensionMismatch {
+            expected: (n, k),
+            found: B.get_dim(),
+        });
+    }
+    let rule: LinSolveRule = LinSolveRule {
+        lu: regular_lu(A)?,
+        n,
+        k,
+    };
+    let mut x: Vec<Var<'t>> = row_major(A);
+    x.extend(row_major(B));
+    return Ok(Matrix::from_vec((n, k), apply(&rule, &x), Layout::RowMajor));
+}
+
+// Scalar::inverse for Var
+pub(crate) fn record_inverse<'t>(A: &Matrix<Var<'t>>) -> Result<Matrix<Var<'t>>, LinAlgError> {
+    let n: usize = A.get_dim().0;
+    let rule: InverseRule = InverseRule {
+        lu: regular_lu(A)?,
+        n,
+    };
+    return Ok(Matrix::from_vec(
+        (n, n),
+        apply(&rule, &row_major(A)),
+        Layout::RowMajor,
+    ));
+}
+
+// Scalar::log_det for Var. A singular A has log|det(A)| = -inf and no useful gradient, so it
+// takes the generic path.
+pub(crate) fn record_log_det<'t>(A: &Matrix<Var<'t>>) -> Result<Var<'t>, LinAlgError> {
+    let lu: Rc<Factorization<f64>> = match regular_lu(A) {
+        Err(LinAlgError::Singular) => return super::inverse::lu_log_det(A),
+        result => result?,
+    };
+    let rule: LogDetRule = LogDetRule {
+        lu,
+        n: A.get_dim().0,
+    };
+    return Ok(apply(&rule, &row_major(A))[0]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto_diff::DiffNum;
+    use crate::lin_alg::example_matrix;
+    use crate::lin_alg::lin_solve;
+    use crate::reverse::{Adjoints, Tape};
+
+    type Dual = DiffNum<f64>;
+
+    // Matrix of dual numbers with values A and tangents dA
+    fn dual_matrix(A: &Matrix<f64>, dA: &Matrix<f64>) -> Matrix<Dual> {
+        let mut result: Matrix<Dual> = Matrix::<Dual>::new(A.get_dim());
+        for r in 0..A.get_dim().0 {
+            for c in 0..A.get_dim().1 {
+                result.set(
+                    r,
+                    c,
+                    DiffNum {
+                        f: A.get(r, c),
+                        df: dA.get(r, c),
+                    },
+                );
+            }
+        }
+        return result;
+    }
+
+    fn tangents(M: &Matrix<Dual>) -> Matrix<f64> {
+        let mut result: Matrix<f64> = Matrix::<f64>::new(M.get_dim());
+        for r in 0..M.get_dim().0 {
+            for c in 0..M.get_dim().1 {
+                result.set(r, c, M.get(r, c).df);
+            }
+        }
+        return result;
+    }
+
+    fn var_matrix<'t>(tape: &'t Tape, M: &Matrix<f64>) -> Matrix<Var<'t>> {
+        let entries: Vec<Var> = row_major(M).into_iter().map(|m| tape.var(m)).collect();
+        return Matrix::from_vec(M.get_dim(), entries, Layout::RowMajor);
+    }
+
+    fn adjoint_matrix(adjoints: &Adjoints, M: &Matrix<Var>) -> Matrix<f64> {
+        let entries: Vec<f64> = row_major(M).into_iter().map(|m| adjoints.wrt(m)).collect();
+        return from_row_major(M.get_dim(), &entries);
+    }
+
+    // <W, M> for a constant W
+    fn weighted_sum<'t>(W: &Matrix<f64>, M: &Matrix<Var<'t>>) -> Var<'t> {
+        let mut sum: Var = Var::constant(0.0);
+        for (w, m) in row_major(W).into_iter().zip(row_major(M)) {
+            sum += m * w;
+        }
+        return sum;
+    }
+
+    fn example_direction() -> Matrix<f64> {
+        return Matrix::from_rows(&[[0.3, -0.1, 0.2], [0.5, 0.0, -0.4], [0.1, 0.7, -0.2]]);
+    }
+
+    #[test]
+    fn test_forward_mode_solve() {
+        // dX = A^-1 (dB - dA X)
+        let A: Matrix<f64> = example_matrix();
+        let dA: Matrix<f64> = example_direction();
+        let B: Matrix<f64> = Matrix::from_rows(&[[1.0, 0.0], [2.0, -1.0], [0.5, 3.0]]);
+        let dB: Matrix<f64> = Matrix::from_rows(&[[0.0, 1.0], [-1.0, 0.0], [0.2, 0.1]]);
+
+        let X: Matrix<Dual> = lin_solve(&dual_matrix(&A, &dA), &dual_matrix(&B, &dB)).unwrap();
+        let X0: Matrix<f64> = lin_solve(&A, &B).unwrap();
+        let expected: Matrix<f64> = lin_solve(&A, &(&dB - &(&dA * &X0))).unwrap();
+        crate::assert_matrix_approx_eq!(tangents(&X), expected);
+
+        let f: Factorization<Dual> = Factorization::lu(&dual_matrix(&A, &dA)).unwrap();
+        let X_lu: Matrix<Dual> = f.solve(&dual_matrix(&B, &dB)).unwrap();
+        crate::assert_matrix_approx_eq!(tangents(&X_lu), expected);
+
+        // d det(A) = det(A) tr(A^-1 dA)
+        let det: Dual = dual_matrix(&A, &dA).det().unwrap();
+        let trace: f64 = (&A.inverse().unwrap() * &dA).trace();
+        assert!((det.df - A.det().unwrap() * trace).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_lin_solve_adjoint() {
+        // Reverse and forward mode agree: <X_bar, dX> = <A_bar, dA> + <B_bar, dB>
+        let A: Matrix<f64> = example_matrix();
+        let dA: Matrix<f64> = example_direction();
+        let B: Matrix<f64> = Matrix::from_rows(&[[1.0, 0.0], [2.0, -1.0], [0.5, 3.0]]);
+        let dB: Matrix<f64> = Matrix::from_rows(&[[0.0, 1.0], [-1.0, 0.0], [0.2, 0.1]]);
+        let X_bar: Matrix<f64> = Matrix::from_rows(&[[1.0, -2.0], [0.5, 0.0], [1.5, 1.0]]);
+
+        let X: Matrix<Dual> = lin_solve(&dual_matrix(&A, &dA), &dual_matrix(&B, &dB)).unwrap();
+        let X0: Matrix<f64> = lin_solve(&A, &B).unwrap();
+        let (A_bar, B_bar) = lin_solve_adjoint(&A, &X0, &X_bar).unwrap();
+        let forward: f64 = X_bar.dot_product(&tangents(&X));
+        let reverse: f64 = A_bar.dot_product(&dA) + B_bar.dot_product(&dB);
+        assert!((forward - reverse).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_qr_adjoint() {
+        let dA: Matrix<f64> = Matrix::from_rows(&[
+            [0.3, -0.1, 0.2],
+            [0.5, 0.0, -0.4],
+            [0.1, 0.7, -0.2],
+            [-0.3, 0.2, 0.6],
+        ]);
+        let A: Matrix<f64> = Matrix::from_rows(&[
+            [2.0, 1.0, 0.0],
+            [-1.0, 3.0, 1.0],
+            [0.5, 0.0, 2.0],
+            [1.0, 1.0, -1.0],
+        ]);
+        let (Q, R) = match Factorization::qr(&A).unwrap() {
+            Factorization::Qr { Q, R } => (Q, R),
+            _ => unreachable!(),
+        };
+        let (dQ, dR) = match Factorization::qr(&dual_matrix(&A, &dA)).unwrap() {
+            Factorization::Qr { Q, R } => (tangents(&Q), tangents(&R)),
+            _ => unreachable!(),
+        };
+
+        // Cotangents that only touch the thin factors
+        let mut Q_bar: Matrix<f64> = Matrix::<f64>::new((4, 4));
+        let mut R_bar: Matrix<f64> = Matrix::<f64>::new((4, 3));
+        for r in 0..4 {
+            for c in 0..3 {
+                Q_bar.set(r, c, ((r * 3 + c) as f64 * 0.37).sin());
+                if r <= c {
+                    R_bar.set(r, c, ((r + 2 * c) as f64 * 0.51).cos());
+                }
+            }
+        }
+
+        let A_bar: Matrix<f64> = qr_adjoint(&Q, &R, &Q_bar, &R_bar).unwrap();
+        let forward: f64 = Q_bar.dot_product(&dQ) + R_bar.dot_product(&dR);
+        assert!((forward - A_bar.dot_product(&dA)).abs() < 1e-12);
+
+        assert!(qr_adjoint(&Q, &R, &R_bar, &R_bar).is_err());
+    }
+
+    #[test]
+    fn test_inverse_and_log_det_adjoints() {
+        let A: Matrix<f64> = example_matrix();
+        let dA: Matrix<f64> = example_direction();
+        let A_dual: Matrix<Dual> = dual_matrix(&A, &dA);
+
+        let Y_bar: Matrix<f64> =
+            Matrix::from_rows(&[[1.0, 0.0, -1.0], [2.0, 0.5, 0.0], [0.0, 1.0, 3.0]]);
+        let dY: Matrix<f64> = tangents(&A_dual.inverse().unwrap());
+        let A_bar: Matrix<f64> = inverse_adjoint(&A.inverse().unwrap(), &Y_bar);
+        assert!((Y_bar.dot_product(&dY) - A_bar.dot_product(&dA)).abs() < 1e-12);
+
+        let d_log_det: f64 = A_dual.log_det().unwrap().df;
+        let A_bar: Matrix<f64> = log_det_adjoint(&A, 1.0).unwrap();
+        assert!((d_log_det - A_bar.dot_product(&dA)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_reverse_mode_through_solvers() {
+        // Reverse mode on the tape agrees with forward mode through the same calls
+        let A: Matrix<f64> = example_matrix();
+        let dA: Matrix<f64> = example_direction();
+        let B: Matrix<f64> = Matrix::from_rows(&[[1.0, 0.0], [2.0, -1.0], [0.5, 3.0]]);
+        let dB: Matrix<f64> = Matrix::from_rows(&[[0.0, 1.0], [-1.0, 0.0], [0.2, 0.1]]);
+        let X_bar: Matrix<f64> = Matrix::from_rows(&[[1.0, -2.0], [0.5, 0.0], [1.5, 1.0]]);
+
+        let tape: Tape = Tape::new();
+        let A_var: Matrix<Var> = var_matrix(&tape, &A);
+        let B_var: Matrix<Var> = var_matrix(&tape, &B);
+        let len: usize = tape.len();
+        let X: Matrix<Var> = lin_solve(&A_var, &B_var).unwrap();
+        // One node per entry of X rather than the whole factorisation
+        assert_eq!(tape.len(), len + 6);
+        crate::assert_matrix_approx_eq!(values(&X), lin_solve(&A, &B).unwrap());
+
+        let adjoints: Adjoints = tape.gradient(weighted_sum(&X_bar, &X));
+        let A_bar: Matrix<f64> = adjoint_matrix(&adjoints, &A_var);
+        let B_bar: Matrix<f64> = adjoint_matrix(&adjoints, &B_var);
+        let X_dual: Matrix<Dual> = lin_solve(&dual_matrix(&A, &dA), &dual_matrix(&B, &dB)).unwrap();
+        let forward: f64 = X_bar.dot_product(&tangents(&X_dual));
+        let reverse: f64 = A_bar.dot_product(&dA) + B_bar.dot_product(&dB);
+        assert!((forward - reverse).abs() < 1e-12);
+
+        // The loss <Y_bar, A^-1> + log|det(A)|
+        let Y_bar: Matrix<f64> =
+            Matrix::from_rows(&[[1.0, 0.0, -1.0], [2.0, 0.5, 0.0], [0.0, 1.0, 3.0]]);
+        let loss: Var = weighted_sum(&Y_bar, &A_var.inverse().unwrap()) + A_var.log_det().unwrap();
+        let expected: f64 = Y_bar.dot_product(&A.inverse().unwrap()) + A.log_det().unwrap();
+        assert!((loss.value() - expected).abs() < 1e-12);
+        let A_bar: Matrix<f64> = adjoint_matrix(&tape.gradient(loss), &A_var);
+        let A_dual: Matrix<Dual> = dual_matrix(&A, &dA);
+        let forward: f64 =
+            Y_bar.dot_product(&tangents(&A_dual.inverse().unwrap())) + A_dual.log_det().unwrap().df;
+        assert!((forward - A_bar.dot_product(&dA)).abs() < 1e-12);
+
+        // Singular and non-square inputs fail as for f64
+        let singular: Matrix<Var> = var_matrix(&tape, &Matrix::<f64>::ones((2, 2)));
+        assert_eq!(singular.inverse().unwrap_err(), LinAlgError::Singular);
+        assert_eq!(singular.log_det().unwrap().value(), f64::NEG_INFINITY);
+        assert!(lin_solve(&A_var, &singular).is_err());
+    }
+}
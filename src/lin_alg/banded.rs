@@ -0,0 +1,577 @@
+/*
+Tridiagonal and banded matrices, which store only the entries near the diagonal and solve
+linear systems in O(n) work per right-hand side instead of the O(n^3) of a dense factorisation.
+
+A band matrix with kl sub-diagonals and ku super-diagonals keeps entry (i, j) for
+-kl <= j - i <= ku, row by row in a buffer of n * (kl + ku + 1) values. Entries outside the band
+read as zero.
+*/
+
+use super::mat::Matrix;
+use super::sparse::{CsrMatrix, SparsityPattern};
+use super::LinAlgError;
+use std::ops::Mul;
+
+fn check_rhs(n: usize, B: &Matrix<f64>) -> Result<(), LinAlgError> {
+    if B.get_dim().0 != n {
+        return Err(LinAlgError::DimensionMismatch {
+            expected: (n, B.get_dim().1),
+            found: B.get_dim(),
+        });
+    }
+    return Ok(());
+}
+
+fn check_square(A: &Matrix<f64>) -> Result<usize, LinAlgError> {
+    let (rows, cols) = A.get_dim();
+    if rows != cols {
+        return Err(LinAlgError::DimensionMismatch {
+            expected: (rows, rows),
+            found: (rows, cols),
+        });
+    }
+    return Ok(rows);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tridiagonal {
+    lower: Vec<f64>,
+    diag: Vec<f64>,
+    upper: Vec<f64>,
+}
+
+impl Tridiagonal {
+    // `lower` and `upper` hold the sub- and super-diagonal, one entry shorter than `diag`
+    pub fn new(lower: Vec<f64>, diag: Vec<f64>, upper: Vec<f64>) -> Self {
+        let off: usize = diag.len().saturating_sub(1);
+        assert!(
+            lower.len() == off && upper.len() == off,
+            "Off-diagonals must have one entry less than the diagonal."
+        );
+        return Self { lower, diag, upper };
+    }
+
+    // Reads the three central diagonals of a square matrix, ignoring all other entries
+    pub fn from_matrix(A: &Matrix<f64>) -> Result<Self, LinAlgError> {
+        let n: usize = check_square(A)?;
+        let off: usize = n.saturating_sub(1);
+        return Ok(Self {
+            lower: (0..off).map(|i| A.get(i + 1, i)).collect(),
+            diag: (0..n).map(|i| A.get(i, i)).collect(),
+            upper: (0..off).map(|i| A.get(i, i + 1)).collect(),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        return self.diag.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.diag.is_empty();
+    }
+
+    pub fn lower(&self) -> &[f64] {
+        return &self.lower;
+    }
+
+    pub fn diag(&self) -> &[f64] {
+        return &self.diag;
+    }
+
+    pub fn upper(&self) -> &[f64] {
+        return &self.upper;
+    }
+
+    // Solves T * X = B with the Thomas algorithm. There is no pivoting, so this is meant for
+    // diagonally dominant or symmetric positive definite systems, which covers most
+    // discretisations. A zero pivot gives LinAlgError::Singular.
+    pub fn solve(&self, B: &Matrix<f64>) -> Result<Matrix<f64>, LinAlgError> {
+        let n: usize = self.len();
+        check_rhs(n, B)?;
+        if n == 0 {
+            return Ok(B.clone());
+        }
+
+        // Forward sweep: upper_mod[i] is the super-diagonal after eliminating the sub-diagonal
+        let mut upper_mod: Vec<f64> = vec![0.0; n];
+        let mut pivots: Vec<f64> = vec![0.0; n];
+        pivots[0] = self.diag[0];
+        for i in 0..n {
+            if i > 0 {
+                pivots[i] = self.diag[i] - self.lower[i - 1] * upper_mod[i - 1];
+            }
+            if pivots[i] == 0.0 {
+                return Err(LinAlgError::Singular);
+            }
+            if i + 1 < n {
+                upper_mod[i] = self.upper[i] / pivots[i];
+            }
+        }
+
+        let mut X: Matrix<f64> = B.clone();
+        for col in 0..B.get_dim().1 {
+            X.set(0, col, X.get(0, col) / pivots[0]);
+            for i in 1..n {
+                let val: f64 = (X.get(i, col) - self.lower[i - 1] * X.get(i - 1, col)) / pivots[i];
+                X.set(i, col, val);
+            }
+            for i in (0..(n - 1)).rev() {
+                X.set(i, col, X.get(i, col) - upper_mod[i] * X.get(i + 1, col));
+            }
+        }
+        return Ok(X);
+    }
+}
+
+impl From<&Tridiagonal> for Matrix<f64> {
+    fn from(value: &Tridiagonal) -> Self {
+        return Matrix::<f64>::from(&BandMatrix::from(value));
+    }
+}
+
+impl From<&Tridiagonal> for BandMatrix {
+    fn from(value: &Tridiagonal) -> Self {
+        let n: usize = value.len();
+        let mut result: BandMatrix = BandMatrix::new(n, 1, 1);
+        for i in 0..n {
+            result.set(i, i, value.diag[i]);
+            if i + 1 < n {
+                result.set(i + 1, i, value.lower[i]);
+                result.set(i, i + 1, value.upper[i]);
+            }
+        }
+        return result;
+    }
+}
+
+impl From<&Tridiagonal> for CsrMatrix {
+    fn from(value: &Tridiagonal) -> Self {
+        return CsrMatrix::from(&BandMatrix::from(value));
+    }
+}
+
+impl Mul<&Matrix<f64>> for &Tridiagonal {
+    type Output = Matrix<f64>;
+
+    fn mul(self, rhs: &Matrix<f64>) -> Matrix<f64> {
+        return &BandMatrix::from(self) * rhs;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BandMatrix {
+    n: usize,
+    kl: usize,
+    ku: usize,
+    data: Vec<f64>,
+}
+
+impl BandMatrix {
+    // Zero n x n matrix with kl sub-diagonals and ku super-diagonals
+    pub fn new(n: usize, kl: usize, ku: usize) -> Self {
+        return Self {
+            n,
+            kl,
+            ku,
+            data: vec![0.0; n * (kl + ku + 1)],
+        };
+    }
+
+    // Copies the band of a square matrix, ignoring all entries outside it
+    pub fn from_matrix(A: &Matrix<f64>, kl: usize, ku: usize) -> Result<Self, LinAlgError> {
+        let n: usize = check_square(A)?;
+        let mut result: BandMatrix = BandMatrix::new(n, kl, ku);
+        for i in 0..n {
+            for j in result.row_range(i) {
+                result.set(i, j, A.get(i, j));
+            }
+        }
+        return Ok(result);
+    }
+
+    // Copies the stored entries of a square sparse matrix that lie in the band, ignoring all
+    // others
+    pub fn from_csr(A: &CsrMatrix, kl: usize, ku: usize) -> Result<Self, LinAlgError> {
+        let (rows, cols) = A.get_dim();
+        if rows != cols {
+            return Err(LinAlgError::DimensionMismatch {
+                expected: (rows, rows),
+                found: (rows, cols),
+            });
+        }
+        let mut result: BandMatrix = BandMatrix::new(rows, kl, ku);
+        for i in 0..rows {
+            for (j, val) in A.row(i) {
+                if result.index(i, j).is_some() {
+                    result.set(i, j, val);
+                }
+            }
+        }
+        return Ok(result);
+    }
+
+    pub fn get_dim(&self) -> (usize, usize) {
+        return (self.n, self.n);
+    }
+
+    // (kl, ku)
+    pub fn bandwidths(&self) -> (usize, usize) {
+        return (self.kl, self.ku);
+    }
+
+    // Columns of row i that lie inside the band
+    fn row_range(&self, i: usize) -> std::ops::Range<usize> {
+        return i.saturating_sub(self.kl)..(i + self.ku + 1).min(self.n);
+    }
+
+    fn index(&self, i: usize, j: usize) -> Option<usize> {
+        if i >= self.n || j >= self.n {
+            panic!("Index out of bounds.");
+        }
+        if j + self.kl < i || j > i + self.ku {
+            return None;
+        }
+        return Some(i * (self.kl + self.ku + 1) + j + self.kl - i);
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> f64 {
+        return match self.index(i, j) {
+            Some(idx) => self.data[idx],
+            None => 0.0,
+        };
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, val: f64) {
+        let idx: usize = self
+            .index(i, j)
+            .expect("Entry lies outside the band of the matrix.");
+        self.data[idx] = val;
+    }
+
+    // LU factorisation with partial pivoting. Row interchanges widen the upper band of U to
+    // kl + ku.
+    pub fn lu(&self) -> BandLu {
+        let (n, kl) = (self.n, self.kl);
+        let mut LU: BandMatrix = BandMatrix::new(n, kl, kl + self.ku);
+        for i in 0..n {
+            for j in self.row_range(i) {
+                LU.set(i, j, self.get(i, j));
+            }
+        }
+
+        let mut pivots: Vec<usize> = vec![0; n];
+        for k in 0..n {
+            let last_row: usize = (k + kl).min(n - 1);
+            let mut p: usize = k;
+            for i in (k + 1)..=last_row {
+                if LU.get(i, k).abs() > LU.get(p, k).abs() {
+                    p = i;
+                }
+            }
+            pivots[k] = p;
+            let cols: std::ops::Range<usize> = LU.row_range(k);
+            if p != k {
+                for j in k..cols.end {
+                    let tmp: f64 = LU.get(k, j);
+                    LU.set(k, j, LU.get(p, j));
+                    LU.set(p, j, tmp);
+                }
+            }
+
+            let diag: f64 = LU.get(k, k);
+            if diag == 0.0 {
+                // Nothing to eliminate, solving reports the singularity
+                continue;
+            }
+            for i in (k + 1)..=last_row {
+                let l: f64 = LU.get(i, k) / diag;
+                LU.set(i, k, l);
+                for j in (k + 1)..cols.end {
+                    LU.set(i, j, LU.get(i, j) - l * LU.get(k, j));
+                }
+            }
+        }
+        return BandLu { LU, pivots };
+    }
+
+    // Cholesky factorisation A = L * L^T of a symmetric positive definite band matrix. Only the
+    // lower band is read, so the matrix may be stored with ku = 0.
+    pub fn cholesky(&self) -> Result<BandCholesky, LinAlgError> {
+        let (n, kl) = (self.n, self.kl);
+        let mut L: BandMatrix = BandMatrix::new(n, kl, 0);
+        for j in 0..n {
+            let first: usize = j.saturating_sub(kl);
+            let mut d: f64 = self.get(j, j);
+            for k in first..j {
+                d -= L.get(j, k) * L.get(j, k);
+            }
+            if d <= 0.0 || !d.is_finite() {
+                return Err(LinAlgError::NotPositiveDefinite);
+            }
+            let d: f64 = d.sqrt();
+            L.set(j, j, d);
+
+            for i in (j + 1)..(j + kl + 1).min(n) {
+                let mut s: f64 = self.get(i, j);
+                for k in i.saturating_sub(kl)..j {
+                    s -= L.get(i, k) * L.get(j, k);
+                }
+                L.set(i, j, s / d);
+            }
+        }
+        return Ok(BandCholesky { L });
+    }
+
+    pub fn solve(&self, B: &Matrix<f64>) -> Result<Matrix<f64>, LinAlgError> {
+        return self.lu().solve(B);
+    }
+}
+
+impl From<&BandMatrix> for Matrix<f64> {
+    fn from(value: &BandMatrix) -> Self {
+        let mut result: Matrix<f64> = Matrix::<f64>::new(value.get_dim());
+        for i in 0..value.n {
+            for j in value.row_range(i) {
+                result.set(i, j, value.get(i, j));
+            }
+        }
+        return result;
+    }
+}
+
+// Stores every entry of the band, including those that are zero
+impl From<&BandMatrix> for CsrMatrix {
+    fn from(value: &BandMatrix) -> Self {
+        let pattern: SparsityPattern = SparsityPattern::banded(value.get_dim(), value.kl, value.ku);
+        let mut values: Vec<f64> = Vec::with_capacity(pattern.nnz());
+        for i in 0..value.n {
+            for j in value.row_range(i) {
+                values.push(value.get(i, j));
+            }
+        }
+        return CsrMatrix::from_pattern(pattern, values);
+    }
+}
+
+impl Mul<&Matrix<f64>> for &BandMatrix {
+    type Output = Matrix<f64>;
+
+    fn mul(self, rhs: &Matrix<f64>) -> Matrix<f64> {
+        assert_eq!(self.n, rhs.get_dim().0, "Incompatible matrix dimensions");
+        let cols: usize = rhs.get_dim().1;
+        let mut result: Matrix<f64> = Matrix::<f64>::new((self.n, cols));
+        for i in 0..self.n {
+            for c in 0..cols {
+                let mut sum: f64 = 0.0;
+                for j in self.row_range(i) {
+                    sum += self.get(i, j) * rhs.get(j, c);
+                }
+                result.set(i, c, sum);
+            }
+        }
+        return result;
+    }
+}
+
+// Packed band LU factors: the multipliers of L below the diagonal and U on and above it, with
+// pivots[k] the row swapped with row k at step k
+#[derive(Debug, Clone)]
+pub struct BandLu {
+    LU: BandMatrix,
+    pivots: Vec<usize>,
+}
+
+impl BandLu {
+    pub fn solve(&self, B: &Matrix<f64>) -> Result<Matrix<f64>, LinAlgError> {
+        let (n, kl) = (self.LU.n, self.LU.kl);
+        check_rhs(n, B)?;
+        if (0..n).any(|i| self.LU.get(i, i) == 0.0) {
+            return Err(LinAlgError::Singular);
+        }
+
+        let mut X: Matrix<f64> = B.clone();
+        for col in 0..B.get_dim().1 {
+            for k in 0..n {
+                let p: usize = self.pivots[k];
+                if p != k {
+                    let tmp: f64 = X.get(k, col);
+                    X.set(k, col, X.get(p, col));
+                    X.set(p, col, tmp);
+                }
+                for i in (k + 1)..(k + kl + 1).min(n) {
+                    X.set(i, col, X.get(i, col) - self.LU.get(i, k) * X.get(k, col));
+                }
+            }
+            for i in (0..n).rev() {
+                let mut sum: f64 = X.get(i, col);
+                for j in (i + 1)..self.LU.row_range(i).end {
+                    sum -= self.LU.get(i, j) * X.get(j, col);
+                }
+                X.set(i, col, sum / self.LU.get(i, i));
+            }
+        }
+        return Ok(X);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BandCholesky {
+    L: BandMatrix,
+}
+
+impl BandCholesky {
+    // Lower triangular band factor
+    pub fn factor(&self) -> &BandMatrix {
+        return &self.L;
+    }
+
+    pub fn solve(&self, B: &Matrix<f64>) -> Result<Matrix<f64>, LinAlgError> {
+        let (n, kl) = (self.L.n, self.L.kl);
+        check_rhs(n, B)?;
+
+        let mut X: Matrix<f64> = B.clone();
+        for col in 0..B.get_dim().1 {
+            // L * y = b, then L^T * x = y
+            for i in 0..n {
+                let mut sum: f64 = X.get(i, col);
+                for k in i.saturating_sub(kl)..i {
+                    sum -= self.L.get(i, k) * X.get(k, col);
+                }
+                X.set(i, col, sum / self.L.get(i, i));
+            }
+            for i in (0..n).rev() {
+                let mut sum: f64 = X.get(i, col);
+                for k in (i + 1)..(i + kl + 1).min(n) {
+                    sum -= self.L.get(k, i) * X.get(k, col);
+                }
+                X.set(i, col, sum / self.L.get(i, i));
+            }
+        }
+        return Ok(X);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lin_alg::mat::Layout;
+
+    // Second difference operator of a 1D Poisson problem
+    fn poisson(n: usize) -> Tridiagonal {
+        return Tridiagonal::new(vec![-1.0; n - 1], vec![2.0; n], vec![-1.0; n - 1]);
+    }
+
+    fn rhs(n: usize, cols: usize) -> Matrix<f64> {
+        let mut B: Matrix<f64> = Matrix::<f64>::new((n, cols));
+        for i in 0..n {
+            for c in 0..cols {
+                B.set(i, c, ((i * 7 + c * 3) % 5) as f64 - 2.0);
+            }
+        }
+        return B;
+    }
+
+    #[test]
+    fn test_tridiagonal_solve() {
+        let T: Tridiagonal = poisson(8);
+        let B: Matrix<f64> = rhs(8, 3);
+        let X: Matrix<f64> = T.solve(&B).unwrap();
+        crate::assert_matrix_approx_eq!(&T * &X, B);
+        crate::assert_matrix_approx_eq!(&Matrix::<f64>::from(&T) * &X, B);
+
+        let singular: Tridiagonal = Tridiagonal::new(vec![1.0], vec![1.0, 1.0], vec![1.0]);
+        assert_eq!(singular.solve(&rhs(2, 1)), Err(LinAlgError::Singular));
+    }
+
+    #[test]
+    fn test_conversions() {
+        let T: Tridiagonal = Tridiagonal::new(vec![1.0, 2.0], vec![3.0, 4.0, 5.0], vec![6.0, 7.0]);
+        let dense: Matrix<f64> = Matrix::<f64>::from(&T);
+        assert_eq!(
+            dense,
+            Matrix::<f64>::from_vec(
+                (3, 3),
+                vec![3.0, 6.0, 0.0, 1.0, 4.0, 7.0, 0.0, 2.0, 5.0],
+                Layout::RowMajor
+            )
+        );
+        assert_eq!(Tridiagonal::from_matrix(&dense).unwrap(), T);
+
+        let band: BandMatrix = BandMatrix::from_matrix(&dense, 1, 0).unwrap();
+        assert_eq!(band.get(0, 1), 0.0);
+        assert_eq!(band.get(2, 1), 2.0);
+        assert_eq!(Matrix::<f64>::from(&band), dense.lower_triangular());
+
+        // Sparse round trips
+        let sparse: CsrMatrix = CsrMatrix::from(&T);
+        assert_eq!(sparse.nnz(), 7);
+        assert_eq!(sparse.to_dense(), dense);
+        assert_eq!(CsrMatrix::from(&band).to_dense(), dense.lower_triangular());
+        let wide: BandMatrix = BandMatrix::from_csr(&sparse, 2, 1).unwrap();
+        assert_eq!(wide.bandwidths(), (2, 1));
+        assert_eq!(Matrix::<f64>::from(&wide), dense);
+        assert_eq!(BandMatrix::from_csr(&sparse, 0, 1).unwrap().get(1, 0), 0.0);
+        let rect: CsrMatrix = CsrMatrix::new(SparsityPattern::banded((2, 3), 0, 0));
+        assert!(BandMatrix::from_csr(&rect, 0, 0).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Entry lies outside the band of the matrix.")]
+    fn test_set_outside_band() {
+        BandMatrix::new(4, 1, 2).set(3, 0, 1.0);
+    }
+
+    #[test]
+    fn test_band_lu_with_pivoting() {
+        // Zero leading entry, so the factorisation has to swap rows
+        let n: usize = 7;
+        let mut A: BandMatrix = BandMatrix::new(n, 2, 1);
+        for i in 0..n {
+            for j in A.row_range(i) {
+                A.set(i, j, ((i * 3 + j * 5) % 7) as f64 - 3.0);
+            }
+        }
+        A.set(0, 0, 0.0);
+        let B: Matrix<f64> = rhs(n, 2);
+        let X: Matrix<f64> = A.solve(&B).unwrap();
+        crate::assert_matrix_approx_eq!(&A * &X, B);
+        crate::assert_matrix_approx_eq!(
+            X,
+            crate::lin_alg::lin_solve(&Matrix::from(&A), &B).unwrap()
+        );
+
+        let zero: BandMatrix = BandMatrix::new(3, 1, 1);
+        assert_eq!(zero.solve(&rhs(3, 1)), Err(LinAlgError::Singular));
+    }
+
+    #[test]
+    fn test_band_cholesky() {
+        // Symmetric positive definite pentadiagonal matrix
+        let n: usize = 9;
+        let mut A: BandMatrix = BandMatrix::new(n, 2, 2);
+        for i in 0..n {
+            A.set(i, i, 6.0);
+            if i + 1 < n {
+                A.set(i, i + 1, -2.0);
+                A.set(i + 1, i, -2.0);
+            }
+            if i + 2 < n {
+                A.set(i, i + 2, 1.0);
+                A.set(i + 2, i, 1.0);
+            }
+        }
+        let chol: BandCholesky = A.cholesky().unwrap();
+        let L: Matrix<f64> = Matrix::<f64>::from(chol.factor());
+        crate::assert_matrix_approx_eq!(&L * &L.transpose(), Matrix::<f64>::from(&A));
+
+        let B: Matrix<f64> = rhs(n, 2);
+        crate::assert_matrix_approx_eq!(&A * &chol.solve(&B).unwrap(), B);
+
+        let mut indefinite: BandMatrix = BandMatrix::from(&poisson(4));
+        indefinite.set(3, 3, -1.0);
+        assert_eq!(
+            indefinite.cholesky().unwrap_err(),
+            LinAlgError::NotPositiveDefinite
+        );
+    }
+}
@@ -0,0 +1,222 @@
+/*
+Assembly of larger matrices from smaller pieces: Kronecker products, concatenation, block
+diagonals, block grids and tiling.
+*/
+
+use super::mat::Matrix;
+use super::Scalar;
+
+impl<T: Scalar> Matrix<T> {
+    // Copies `block` into this matrix with its top-left corner at (r0, c0)
+    pub fn set_block(&mut self, r0: usize, c0: usize, block: &Matrix<T>) {
+        let (br, bc) = block.get_dim();
+        assert!(
+            r0 + br <= self.get_dim().0 && c0 + bc <= self.get_dim().1,
+            "Block does not fit inside the matrix."
+        );
+        for r in 0..br {
+            for c in 0..bc {
+                self.set(r0 + r, c0 + c, block.get(r, c));
+            }
+        }
+    }
+
+    // Copy of the dim.0 x dim.1 block with its top-left corner at (r0, c0)
+    pub fn get_block(&self, r0: usize, c0: usize, dim: (usize, usize)) -> Matrix<T> {
+        assert!(
+            r0 + dim.0 <= self.get_dim().0 && c0 + dim.1 <= self.get_dim().1,
+            "Block does not fit inside the matrix."
+        );
+        let mut result: Matrix<T> = Matrix::<T>::new(dim);
+        for r in 0..dim.0 {
+            for c in 0..dim.1 {
+                result.set(r, c, self.get(r0 + r, c0 + c));
+            }
+        }
+        return result;
+    }
+
+    // Kronecker product: block (i, j) of the result is self[i, j] * rhs
+    pub fn kron(&self, rhs: &Matrix<T>) -> Matrix<T> {
+        let (r1, c1) = self.get_dim();
+        let (r2, c2) = rhs.get_dim();
+        let mut result: Matrix<T> = Matrix::<T>::new((r1 * r2, c1 * c2));
+        for i in 0..r1 {
+            for j in 0..c1 {
+                let a: T = self.get(i, j);
+                for k in 0..r2 {
+                    for l in 0..c2 {
+                        result.set(i * r2 + k, j * c2 + l, a * rhs.get(k, l));
+                    }
+                }
+            }
+        }
+        return result;
+    }
+
+    // Concatenates matrices with equal row counts side by side
+    pub fn hstack(blocks: &[&Matrix<T>]) -> Matrix<T> {
+        return Matrix::<T>::from_blocks(&[blocks.to_vec()]);
+    }
+
+    // Concatenates matrices with equal column counts on top of each other
+    pub fn vstack(blocks: &[&Matrix<T>]) -> Matrix<T> {
+        let grid: Vec<Vec<&Matrix<T>>> = blocks.iter().map(|b| vec![*b]).collect();
+        return Matrix::<T>::from_blocks(&grid);
+    }
+
+    // Places the blocks along the diagonal, with zeros elsewhere
+    pub fn block_diag(blocks: &[&Matrix<T>]) -> Matrix<T> {
+        let rows: usize = blocks.iter().map(|b| b.get_dim().0).sum();
+        let cols: usize = blocks.iter().map(|b| b.get_dim().1).sum();
+        let mut result: Matrix<T> = Matrix::<T>::new((rows, cols));
+        let (mut r0, mut c0) = (0, 0);
+        for block in blocks {
+            result.set_block(r0, c0, block);
+            r0 += block.get_dim().0;
+            c0 += block.get_dim().1;
+        }
+        return result;
+    }
+
+    // Assembles a matrix from a grid of blocks given row by row. All blocks in a grid row must
+    // have the same number of rows, and all blocks in a grid column the same number of columns.
+    pub fn from_blocks(grid: &[Vec<&Matrix<T>>]) -> Matrix<T> {
+        if grid.is_empty() {
+            return Matrix::<T>::new((0, 0));
+        }
+        let n_block_cols: usize = grid[0].len();
+        assert!(
+            grid.iter().all(|row| row.len() == n_block_cols),
+            "Every block row must contain the same number of blocks."
+        );
+
+        let heights: Vec<usize> = grid
+            .iter()
+            .map(|row| row.first().map_or(0, |b| b.get_dim().0))
+            .collect();
+        let widths: Vec<usize> = (0..n_block_cols).map(|j| grid[0][j].get_dim().1).collect();
+        for (i, row) in grid.iter().enumerate() {
+            for (j, block) in row.iter().enumerate() {
+                assert_eq!(
+                    block.get_dim(),
+                    (heights[i], widths[j]),
+                    "Block ({}, {}) has incompatible dimensions.",
+                    i,
+                    j
+                );
+            }
+        }
+
+        let mut result: Matrix<T> = Matrix::<T>::new((heights.iter().sum(), widths.iter().sum()));
+        let mut r0: usize = 0;
+        for (i, row) in grid.iter().enumerate() {
+            let mut c0: usize = 0;
+            for (j, block) in row.iter().enumerate() {
+                result.set_block(r0, c0, block);
+                c0 += widths[j];
+            }
+            r0 += heights[i];
+        }
+        return result;
+    }
+
+    // Repeats the whole matrix reps.0 times vertically and reps.1 times horizontally
+    pub fn tile(&self, reps: (usize, usize)) -> Matrix<T> {
+        return Matrix::<T>::ones(reps).kron(self);
+    }
+
+    // Repeats every entry into a reps.0 x reps.1 block
+    pub fn repeat(&self, reps: (usize, usize)) -> Matrix<T> {
+        return self.kron(&Matrix::<T>::ones(reps));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_set_block() {
+        let mut m: Matrix<f64> = Matrix::<f64>::new((3, 3));
+        let block: Matrix<f64> = Matrix::from_rows(&[[1.0], [2.0]]);
+        m.set_block(1, 2, &block);
+        assert_eq!(m.get(2, 2), 2.0);
+        assert_eq!(m.get_block(1, 2, (2, 1)), block);
+    }
+
+    #[test]
+    fn test_kron() {
+        let a: Matrix<f64> = Matrix::from_rows(&[[1.0, 2.0], [3.0, 4.0]]);
+        let b: Matrix<f64> = Matrix::from_rows(&[[0.0, 5.0]]);
+        let expected: Matrix<f64> =
+            Matrix::from_rows(&[[0.0, 5.0, 0.0, 10.0], [0.0, 15.0, 0.0, 20.0]]);
+        assert_eq!(a.kron(&b), expected);
+
+        // Mixed product property: (A kron B)(C kron D) = (AC) kron (BD)
+        let c: Matrix<f64> = Matrix::from_rows(&[[1.0], [-1.0]]);
+        let d: Matrix<f64> = Matrix::from_rows(&[[2.0], [3.0]]);
+        assert_eq!(&a.kron(&b) * &c.kron(&d), (&a * &c).kron(&(&b * &d)));
+    }
+
+    #[test]
+    fn test_stack() {
+        let a: Matrix<f64> = Matrix::from_rows(&[[1.0], [2.0]]);
+        let b: Matrix<f64> = Matrix::from_rows(&[[3.0, 4.0], [5.0, 6.0]]);
+        assert_eq!(
+            Matrix::hstack(&[&a, &b]),
+            Matrix::from_rows(&[[1.0, 3.0, 4.0], [2.0, 5.0, 6.0]])
+        );
+
+        let c: Matrix<f64> = Matrix::from_rows(&[[7.0, 8.0]]);
+        assert_eq!(
+            Matrix::vstack(&[&b, &c]),
+            Matrix::from_rows(&[[3.0, 4.0], [5.0, 6.0], [7.0, 8.0]])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Block (0, 1) has incompatible dimensions.")]
+    fn test_hstack_mismatched_rows() {
+        let a: Matrix<f64> = Matrix::<f64>::new((2, 1));
+        let b: Matrix<f64> = Matrix::<f64>::new((3, 1));
+        Matrix::hstack(&[&a, &b]);
+    }
+
+    #[test]
+    fn test_block_diag() {
+        let a: Matrix<f64> = Matrix::from_rows(&[[1.0, 2.0]]);
+        let b: Matrix<f64> = Matrix::from_rows(&[[3.0], [4.0]]);
+        assert_eq!(
+            Matrix::block_diag(&[&a, &b]),
+            Matrix::from_rows(&[[1.0, 2.0, 0.0], [0.0, 0.0, 3.0], [0.0, 0.0, 4.0]])
+        );
+    }
+
+    #[test]
+    fn test_from_blocks() {
+        // State space style system matrix [[A, B], [C, D]]
+        let a: Matrix<f64> = Matrix::<f64>::identity(2);
+        let b: Matrix<f64> = Matrix::from_rows(&[[5.0], [6.0]]);
+        let c: Matrix<f64> = Matrix::from_rows(&[[7.0, 8.0]]);
+        let d: Matrix<f64> = Matrix::from_rows(&[[9.0]]);
+        let system: Matrix<f64> = Matrix::from_blocks(&[vec![&a, &b], vec![&c, &d]]);
+        assert_eq!(
+            system,
+            Matrix::from_rows(&[[1.0, 0.0, 5.0], [0.0, 1.0, 6.0], [7.0, 8.0, 9.0]])
+        );
+    }
+
+    #[test]
+    fn test_tile_and_repeat() {
+        let a: Matrix<f64> = Matrix::from_rows(&[[1.0, 2.0]]);
+        assert_eq!(
+            a.tile((2, 2)),
+            Matrix::from_rows(&[[1.0, 2.0, 1.0, 2.0], [1.0, 2.0, 1.0, 2.0]])
+        );
+        assert_eq!(
+            a.repeat((2, 2)),
+            Matrix::from_rows(&[[1.0, 1.0, 2.0, 2.0], [1.0, 1.0, 2.0, 2.0]])
+        );
+    }
+}
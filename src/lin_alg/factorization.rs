@@ -0,0 +1,292 @@
+/*
+Factorise-once, solve-many wrappers around the QR and LU decompositions. The factorisation is
+computed when the object is built and every call to solve reuses it, so solving for many
+right-hand sides costs one decomposition plus a pair of triangular solves per column.
+*/
+
+use super::mat::Matrix;
+use super::permutation::Permutation;
+use super::triangular::{solve_triangular, Diagonal, Triangle};
+use super::LinAlgError;
+use super::Scalar;
+
+#[derive(Debug, Clone)]
+pub enum Factorization<F = f64> {
+    // A = Q * R with orthogonal Q and upper triangular R. Works for tall A, where solving gives
+    // the least squares solution.
+    Qr { Q: Matrix<F>, R: Matrix<F> },
+    // P * A = L * U with partial pivoting, where P is the row permutation `perm`. L has a unit
+    // diagonal and is stored below the diagonal of `LU`, with U on and above it.
+    Lu { LU: Matrix<F>, perm: Permutation },
+}
+
+impl<F: Scalar> Factorization<F> {
+    // Requires at least as many rows as columns
+    pub fn qr(A: &Matrix<F>) -> Result<Self, LinAlgError> {
+        let (rows, cols) = A.get_dim();
+        if rows < cols {
+            return Err(LinAlgError::DimensionMismatch {
+                expected: (cols, cols),
+                found: (rows, cols),
+            });
+        }
+        let (Q, R) = super::qr_decomposition(A);
+        return Ok(Factorization::Qr { Q, R });
+    }
+
+    // A zero pivot does not fail here, since the factors are still well defined. Solving with
+    // them reports LinAlgError::Singular instead, see check_singular.
+    pub fn lu(A: &Matrix<F>) -> Result<Self, LinAlgError> {
+        let n: usize = A.get_dim().0;
+        if A.get_dim().1 != n {
+            return Err(LinAlgError::DimensionMismatch {
+                expected: (n, n),
+                found: A.get_dim(),
+            });
+        }
+
+        let mut LU: Matrix<F> = A.clone();
+        let mut perm: Permutation = Permutation::identity(n);
+        for k in 0..n {
+            let mut pivot: usize = k;
+            for i in (k + 1)..n {
+                if LU.get(i, k).abs() > LU.get(pivot, k).abs() {
+                    pivot = i;
+                }
+            }
+            if pivot != k {
+                LU.swap_rows(k, pivot);
+                perm.swap(k, pivot);
+            }
+
+            let diag: F = LU.get(k, k);
+            if diag == F::zero() {
+                // The whole column is zero below the diagonal, so there is nothing to eliminate
+                continue;
+            }
+            for i in (k + 1)..n {
+                let l: F = LU.get(i, k) / diag;
+                LU.set(i, k, l);
+                for j in (k + 1)..n {
+                    LU.set(i, j, LU.get(i, j) - l * LU.get(k, j));
+                }
+            }
+        }
+
+        return Ok(Factorization::Lu { LU, perm });
+    }
+
+    // Dimension of the factorised matrix A
+    pub fn dim(&self) -> (usize, usize) {
+        return match self {
+            Factorization::Qr { Q, R } => (Q.get_dim().0, R.get_dim().1),
+            Factorization::Lu { LU, .. } => LU.get_dim(),
+        };
+    }
+
+    // Solves A * X = B for every column of B at once
+    pub fn solve(&self, B: &Matrix<F>) -> Result<Matrix<F>, LinAlgError> {
+        self.check_rhs(B, self.dim().0)?;
+        self.check_singular()?;
+        return match self {
+            Factorization::Qr { Q, R } => {
+                // Only the leading square block of R is non-zero
+                let n: usize = R.get_dim().1;
+                let Y: Matrix<F> = &Q.transpose() * B;
+                solve_triangular(
+                    &R.get_block(0, 0, (n, n)),
+                    &Y.get_block(0, 0, (n, B.get_dim().1)),
+                    Triangle::Upper,
+                    false,
+                    Diagonal::NonUnit,
+                )
+            }
+            Factorization::Lu { LU, perm } => {
+                let Y: Matrix<F> = solve_triangular(
+                    LU,
+                    &B.permute_rows(perm),
+                    Triangle::Lower,
+                    false,
+                    Diagonal::Unit,
+                )?;
+                solve_triangular(LU, &Y, Triangle::Upper, false, Diagonal::NonUnit)
+            }
+        };
+    }
+
+    // Solves A^T * X = B for square A without forming the transpose
+    pub fn solve_transposed(&self, B: &Matrix<F>) -> Result<Matrix<F>, LinAlgError> {
+        let (rows, cols) = self.dim();
+        if rows != cols {
+            return Err(LinAlgError::DimensionMismatch {
+                expected: (cols, cols),
+                found: (rows, cols),
+            });
+        }
+        self.check_rhs(B, cols)?;
+        self.check_singular()?;
+        return match self {
+            // A^T = R^T * Q^T
+            Factorization::Qr { Q, R } => {
+                let W: Matrix<F> =
+                    solve_triangular(R, B, Triangle::Upper, true, Diagonal::NonUnit)?;
+                Ok(Q * &W)
+            }
+            // A^T = U^T * L^T * P
+            Factorization::Lu { LU, perm } => {
+                let W: Matrix<F> =
+                    solve_triangular(LU, B, Triangle::Upper, true, Diagonal::NonUnit)?;
+                let Z: Matrix<F> = solve_triangular(LU, &W, Triangle::Lower, true, Diagonal::Unit)?;
+                Ok(Z.permute_rows(&perm.inverse()))
+            }
+        };
+    }
+
+    // The triangular factor R or U is treated as singular if one of its diagonal entries is
+    // negligible next to the largest entry, |t_kk| <= n * eps * max |t_ij|, since a solve would
+    // then amplify rounding errors beyond any useful accuracy
+    pub(crate) fn check_singular(&self) -> Result<(), LinAlgError> {
+        let (T, n) = match self {
+            Factorization::Qr { R, .. } => (R, R.get_dim().1),
+            Factorization::Lu { LU, .. } => (LU, LU.get_dim().0),
+        };
+        let mut largest: f64 = 0.0;
+        for i in 0..n {
+            for j in i..n {
+                largest = largest.max(T.get(i, j).to_f64().abs());
+            }
+        }
+        let diagonal: Vec<f64> = (0..n).map(|i| T.get(i, i).to_f64().abs()).collect();
+        let tolerance: f64 = n as f64 * epsilon::<F>() * largest;
+        if diagonal.iter().any(|&d| d <= tolerance) {
+            return Err(LinAlgError::Singular);
+        }
+        return Ok(());
+    }
+
+    fn check_rhs(&self, B: &Matrix<F>, rows: usize) -> Result<(), LinAlgError> {
+        if B.get_dim().0 != rows {
+            return Err(LinAlgError::DimensionMismatch {
+                expected: (rows, B.get_dim().1),
+                found: B.get_dim(),
+            });
+        }
+        return Ok(());
+    }
+}
+
+// Machine epsilon of the value type of F, e.g. 2^-23 for f32 and 2^-52 for f64 and dual numbers
+// over f64
+fn epsilon<F: Scalar>() -> f64 {
+    let mut eps: f64 = 1.0;
+    while F::one() + F::from_f64(eps / 2.0) != F::one() {
+        eps /= 2.0;
+    }
+    return eps;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lin_alg::example_matrix;
+    use crate::lin_alg::mat::Layout;
+
+    fn example_rhs() -> Matrix<f64> {
+        return Matrix::from_rows(&[
+            [1.0, 0.0, 0.0, 2.0],
+            [0.0, 1.0, 0.0, -1.0],
+            [0.0, 0.0, 1.0, 0.5],
+        ]);
+    }
+
+    #[test]
+    fn test_lu_factors() {
+        let A: Matrix<f64> = example_matrix();
+        let (LU, perm) = match Factorization::lu(&A).unwrap() {
+            Factorization::Lu { LU, perm } => (LU, perm),
+            _ => unreachable!(),
+        };
+        let mut L: Matrix<f64> = LU.lower_triangular();
+        L.set_diagonal(&[1.0, 1.0, 1.0]);
+        let U: Matrix<f64> = LU.upper_triangular();
+        crate::assert_matrix_approx_eq!(&L * &U, A.permute_rows(&perm));
+    }
+
+    #[test]
+    fn test_solve_many_columns() {
+        let A: Matrix<f64> = example_matrix();
+        let B: Matrix<f64> = example_rhs();
+        for f in [
+            Factorization::qr(&A).unwrap(),
+            Factorization::lu(&A).unwrap(),
+        ] {
+            let X: Matrix<f64> = f.solve(&B).unwrap();
+            assert_eq!(X.get_dim(), (3, 4));
+            crate::assert_matrix_approx_eq!(&A * &X, B);
+
+            let Xt: Matrix<f64> = f.solve_transposed(&B).unwrap();
+            crate::assert_matrix_approx_eq!(&A.transpose() * &Xt, B);
+        }
+    }
+
+    #[test]
+    fn test_singular_and_mismatched() {
+        let A: Matrix<f64> =
+            Matrix::from_rows(&[[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [0.0, 1.0, 1.0]]);
+        let f: Factorization = Factorization::lu(&A).unwrap();
+        assert_eq!(
+            f.solve(&Matrix::<f64>::ones((3, 1))).unwrap_err(),
+            LinAlgError::Singular
+        );
+        assert_eq!(
+            f.solve(&Matrix::<f64>::ones((2, 3))).unwrap_err(),
+            LinAlgError::DimensionMismatch {
+                expected: (3, 3),
+                found: (2, 3)
+            }
+        );
+        assert!(Factorization::lu(&Matrix::<f64>::new((3, 2))).is_err());
+        // Transposed solves need a square matrix
+        assert!(Factorization::qr(&Matrix::<f64>::ones((3, 2)))
+            .unwrap()
+            .solve_transposed(&Matrix::<f64>::ones((2, 1)))
+            .is_err());
+        // QR needs at least as many rows as columns
+        assert_eq!(
+            Factorization::qr(&Matrix::<f64>::ones((2, 3))).unwrap_err(),
+            LinAlgError::DimensionMismatch {
+                expected: (3, 3),
+                found: (2, 3)
+            }
+        );
+    }
+
+    #[test]
+    fn test_near_singular() {
+        // Rank one up to rounding: the second pivot of LU is -5.6e-17 rather than zero
+        let A: Matrix<f64> = Matrix::from_rows(&[[0.1, 0.3], [0.3, 0.9]]);
+        let B: Matrix<f64> = Matrix::<f64>::ones((2, 1));
+        for f in [
+            Factorization::qr(&A).unwrap(),
+            Factorization::lu(&A).unwrap(),
+        ] {
+            assert_eq!(f.solve(&B).unwrap_err(), LinAlgError::Singular);
+            assert_eq!(f.solve_transposed(&B).unwrap_err(), LinAlgError::Singular);
+        }
+
+        // Badly scaled but regular matrices are still solved
+        let scaled: Matrix<f64> = Matrix::from_rows(&[[1e-12, 0.0], [0.0, 1.0]]);
+        let X: Matrix<f64> = Factorization::lu(&scaled).unwrap().solve(&B).unwrap();
+        assert_eq!(X.get(0, 0), 1e12);
+
+        // The tolerance follows the precision of the element type
+        let A32: Matrix<f32> =
+            Matrix::<f32>::from_vec((2, 2), vec![1.0, 1.0, 1.0, 1.0 + 1e-7], Layout::RowMajor);
+        assert!(Factorization::lu(&A32)
+            .unwrap()
+            .solve(&Matrix::<f32>::ones((2, 1)))
+            .is_err());
+        assert_eq!(epsilon::<f32>(), f32::EPSILON as f64);
+        assert_eq!(epsilon::<f64>(), f64::EPSILON);
+    }
+}
@@ -0,0 +1,349 @@
+/*
+General matrix-matrix multiplication C = alpha * A * B + beta * C.
+
+The kernel follows the usual packed GEMM structure: B is packed into panels of NR columns and A
+into panels of MR rows so the innermost micro-kernel streams through contiguous memory and keeps
+an MR x NR block of C in registers. The loops are tiled by KC, MC and NC to keep the packed
+panels resident in cache. With the `parallel` feature, C is split into horizontal stripes that
+are computed on separate threads.
+
+A and B may have any layout since packing reads them through row and column strides. A
+column-major C is handled by computing the transposed product C^T = B^T * A^T instead.
+*/
+
+use super::mat::{Layout, Matrix};
+
+// Read-only strided view: element (r, c) lives at data[r * rs + c * cs]
+#[derive(Clone, Copy)]
+struct StridedRef<'a> {
+    data: &'a [f64],
+    rs: usize,
+    cs: usize,
+}
+
+impl<'a> StridedRef<'a> {
+    fn new(mat: &'a Matrix<f64>) -> Self {
+        let (rs, cs) = mat.strides();
+        return Self {
+            data: mat.data(),
+            rs,
+            cs,
+        };
+    }
+
+    fn transpose(self) -> Self {
+        return Self {
+            data: self.data,
+            rs: self.cs,
+            cs: self.rs,
+        };
+    }
+
+    fn get(&self, r: usize, c: usize) -> f64 {
+        return self.data[r * self.rs + c * self.cs];
+    }
+
+    // View starting at row `row0`
+    #[cfg(feature = "parallel")]
+    fn skip_rows(self, row0: usize) -> Self {
+        return Self {
+            data: &self.data[(row0 * self.rs).min(self.data.len())..],
+            rs: self.rs,
+            cs: self.cs,
+        };
+    }
+}
+
+const MR: usize = 4;
+const NR: usize = 8;
+const KC: usize = 256;
+const MC: usize = 64;
+const NC: usize = 1024;
+
+// Below this many multiply-adds the threading overhead outweighs the gain
+#[cfg(feature = "parallel")]
+const PARALLEL_THRESHOLD: usize = 64 * 64 * 64;
+
+pub fn gemm(alpha: f64, A: &Matrix<f64>, B: &Matrix<f64>, beta: f64, C: &mut Matrix<f64>) {
+    let (m, k) = A.get_dim();
+    let n: usize = B.get_dim().1;
+    assert_eq!(
+        k,
+        B.get_dim().0,
+        "Matrix dimensions must match for multiplication."
+    );
+    assert_eq!(
+        C.get_dim(),
+        (m, n),
+        "Output matrix has the wrong dimensions."
+    );
+
+    let (a, b) = (StridedRef::new(A), StridedRef::new(B));
+    match C.get_layout() {
+        Layout::RowMajor => gemm_slices(m, n, k, alpha, a, b, beta, C.data_mut()),
+        Layout::ColMajor => gemm_slices(
+            n,
+            m,
+            k,
+            alpha,
+            b.transpose(),
+            a.transpose(),
+            beta,
+            C.data_mut(),
+        ),
+    }
+}
+
+// a is m x k, b is k x n and c is a contiguous row-major m x n slice
+#[allow(clippy::too_many_arguments)]
+fn gemm_slices(
+    m: usize,
+    n: usize,
+    k: usize,
+    alpha: f64,
+    a: StridedRef,
+    b: StridedRef,
+    beta: f64,
+    c: &mut [f64],
+) {
+    // BLAS convention: beta == 0 overwrites C, so NaNs already in C do not propagate
+    if beta == 0.0 {
+        c.fill(0.0);
+    } else if beta != 1.0 {
+        c.iter_mut().for_each(|x| *x *= beta);
+    }
+    if m == 0 || n == 0 || k == 0 || alpha == 0.0 {
+        return;
+    }
+
+    #[cfg(feature = "parallel")]
+    {
+        let threads: usize = std::thread::available_parallelism()
+            .map(|t| t.get())
+            .unwrap_or(1)
+            .min(m.div_ceil(MR));
+        if threads > 1 && m * n * k >= PARALLEL_THRESHOLD {
+            let rows_per_thread: usize = m.div_ceil(threads);
+            std::thread::scope(|s| {
+                for (i, c_stripe) in c.chunks_mut(rows_per_thread * n).enumerate() {
+                    let row0: usize = i * rows_per_thread;
+                    let rows: usize = c_stripe.len() / n;
+                    let a_stripe: StridedRef = a.skip_rows(row0);
+                    s.spawn(move || gemm_serial(rows, n, k, alpha, a_stripe, b, c_stripe));
+                }
+            });
+            return;
+        }
+    }
+
+    gemm_serial(m, n, k, alpha, a, b, c);
+}
+
+// Accumulates alpha * A * B into C on the current thread
+fn gemm_serial(
+    m: usize,
+    n: usize,
+    k: usize,
+    alpha: f64,
+    a: StridedRef,
+    b: StridedRef,
+    c: &mut [f64],
+) {
+    let mut a_packed: Vec<f64> = vec![0.0; MC.div_ceil(MR) * MR * KC];
+    let mut b_packed: Vec<f64> = vec![0.0; NC.div_ceil(NR) * NR * KC];
+
+    for jc in (0..n).step_by(NC) {
+        let nc: usize = NC.min(n - jc);
+        for pc in (0..k).step_by(KC) {
+            let kc: usize = KC.min(k - pc);
+            pack_b(b, pc, jc, kc, nc, &mut b_packed);
+
+            for ic in (0..m).step_by(MC) {
+                let mc: usize = MC.min(m - ic);
+                pack_a(a, ic, pc, mc, kc, &mut a_packed);
+
+                for jr in (0..nc).step_by(NR) {
+                    let nr: usize = NR.min(nc - jr);
+                    let b_panel: &[f64] = &b_packed[(jr / NR) * NR * kc..][..NR * kc];
+                    for ir in (0..mc).step_by(MR) {
+                        let mr: usize = MR.min(mc - ir);
+                        let a_panel: &[f64] = &a_packed[(ir / MR) * MR * kc..][..MR * kc];
+                        let c_offset: usize = (ic + ir) * n + jc + jr;
+                        micro_kernel(kc, alpha, a_panel, b_panel, &mut c[c_offset..], n, mr, nr);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Packs the mc x kc block of A starting at (ic, pc) into row panels of height MR, stored so
+// that each k-step of a panel is MR contiguous values. Partial panels are padded with zeros.
+fn pack_a(a: StridedRef, ic: usize, pc: usize, mc: usize, kc: usize, out: &mut [f64]) {
+    for ir in (0..mc).step_by(MR) {
+        let panel: &mut [f64] = &mut out[(ir / MR) * MR * kc..][..MR * kc];
+        let mr: usize = MR.min(mc - ir);
+        for p in 0..kc {
+            for i in 0..MR {
+                panel[p * MR + i] = if i < mr {
+                    a.get(ic + ir + i, pc + p)
+                } else {
+                    0.0
+                };
+            }
+        }
+    }
+}
+
+// Packs the kc x nc block of B starting at (pc, jc) into column panels of width NR
+fn pack_b(b: StridedRef, pc: usize, jc: usize, kc: usize, nc: usize, out: &mut [f64]) {
+    for jr in (0..nc).step_by(NR) {
+        let panel: &mut [f64] = &mut out[(jr / NR) * NR * kc..][..NR * kc];
+        let nr: usize = NR.min(nc - jr);
+        for p in 0..kc {
+            for j in 0..NR {
+                panel[p * NR + j] = if j < nr {
+                    b.get(pc + p, jc + jr + j)
+                } else {
+                    0.0
+                };
+            }
+        }
+    }
+}
+
+// Computes an MR x NR block of alpha * A * B and adds the leading mr x nr part into C
+#[allow(clippy::too_many_arguments)]
+#[inline(always)]
+fn micro_kernel(
+    kc: usize,
+    alpha: f64,
+    a_panel: &[f64],
+    b_panel: &[f64],
+    c: &mut [f64],
+    ldc: usize,
+    mr: usize,
+    nr: usize,
+) {
+    let mut acc: [[f64; NR]; MR] = [[0.0; NR]; MR];
+    for (a_col, b_row) in a_panel
+        .chunks_exact(MR)
+        .zip(b_panel.chunks_exact(NR))
+        .take(kc)
+    {
+        for i in 0..MR {
+            let a_val: f64 = a_col[i];
+            for j in 0..NR {
+                acc[i][j] += a_val * b_row[j];
+            }
+        }
+    }
+
+    for i in 0..mr {
+        let c_row: &mut [f64] = &mut c[i * ldc..][..nr];
+        for j in 0..nr {
+            c_row[j] += alpha * acc[i][j];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deterministic pseudo-random matrix with entries in [-1, 1)
+    fn pseudo_random_matrix(dim: (usize, usize), seed: u64) -> Matrix<f64> {
+        let mut state: u64 = seed;
+        let mut m: Matrix<f64> = Matrix::<f64>::new(dim);
+        for r in 0..dim.0 {
+            for c in 0..dim.1 {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                m.set(
+                    r,
+                    c,
+                    ((state >> 11) as f64 / (1u64 << 53) as f64) * 2.0 - 1.0,
+                );
+            }
+        }
+        return m;
+    }
+
+    fn naive_product(a: &Matrix<f64>, b: &Matrix<f64>) -> Matrix<f64> {
+        let mut result: Matrix<f64> = Matrix::<f64>::new((a.get_dim().0, b.get_dim().1));
+        for r in 0..a.get_dim().0 {
+            for c in 0..b.get_dim().1 {
+                let mut sum: f64 = 0.0;
+                for k in 0..a.get_dim().1 {
+                    sum += a.get(r, k) * b.get(k, c);
+                }
+                result.set(r, c, sum);
+            }
+        }
+        return result;
+    }
+
+    #[test]
+    fn test_gemm_matches_naive_product() {
+        // Sizes chosen to exercise partial micro-panels and multiple cache blocks
+        for &(m, k, n) in &[
+            (1, 1, 1),
+            (3, 5, 7),
+            (17, 9, 13),
+            (70, 300, 33),
+            (130, 40, 1030),
+        ] {
+            let a: Matrix<f64> = pseudo_random_matrix((m, k), 1);
+            let b: Matrix<f64> = pseudo_random_matrix((k, n), 2);
+            let expected: Matrix<f64> = naive_product(&a, &b);
+            crate::assert_matrix_approx_eq!(&a * &b, expected, 1e-12, 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_gemm_mixed_layouts() {
+        let a: Matrix<f64> = pseudo_random_matrix((37, 21), 7);
+        let b: Matrix<f64> = pseudo_random_matrix((21, 45), 8);
+        let expected: Matrix<f64> = naive_product(&a, &b);
+        for la in [Layout::RowMajor, Layout::ColMajor] {
+            for lb in [Layout::RowMajor, Layout::ColMajor] {
+                for lc in [Layout::RowMajor, Layout::ColMajor] {
+                    let mut c: Matrix<f64> = Matrix::<f64>::new_with_layout((37, 45), lc);
+                    gemm(1.0, &a.to_layout(la), &b.to_layout(lb), 0.0, &mut c);
+                    crate::assert_matrix_approx_eq!(c, expected, 1e-12, 1e-12);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_gemm_alpha_beta() {
+        let a: Matrix<f64> = pseudo_random_matrix((6, 4), 3);
+        let b: Matrix<f64> = pseudo_random_matrix((4, 5), 4);
+        let c0: Matrix<f64> = pseudo_random_matrix((6, 5), 5);
+
+        let mut c: Matrix<f64> = c0.clone();
+        gemm(2.0, &a, &b, -0.5, &mut c);
+        let expected: Matrix<f64> = &(2.0 * &naive_product(&a, &b)) + &(-0.5 * &c0);
+        crate::assert_matrix_approx_eq!(c, expected, 1e-12, 1e-12);
+    }
+
+    #[test]
+    fn test_gemm_beta_zero_overwrites_nan() {
+        let a: Matrix<f64> = Matrix::<f64>::identity(2);
+        let b: Matrix<f64> = pseudo_random_matrix((2, 2), 6);
+        let mut c: Matrix<f64> = Matrix::<f64>::new((2, 2));
+        c.set(0, 0, f64::NAN);
+        gemm(1.0, &a, &b, 0.0, &mut c);
+        assert_eq!(c, b);
+    }
+
+    #[test]
+    #[should_panic(expected = "Output matrix has the wrong dimensions.")]
+    fn test_gemm_wrong_output_dim() {
+        let a: Matrix<f64> = Matrix::<f64>::identity(2);
+        let mut c: Matrix<f64> = Matrix::<f64>::new((3, 2));
+        gemm(1.0, &a, &a, 0.0, &mut c);
+    }
+}
@@ -0,0 +1,149 @@
+/*
+Determinants, inverses and integer powers of square matrices, computed from a pivoted LU
+factorisation rather than by cofactor expansion.
+*/
+
+use super::factorization::Factorization;
+use super::mat::Matrix;
+use super::permutation::Permutation;
+use super::LinAlgError;
+use super::Scalar;
+
+// Packed LU factors and row permutation of a square matrix
+fn lu_factors<F: Scalar>(A: &Matrix<F>) -> Result<(Matrix<F>, Permutation), LinAlgError> {
+    return match Factorization::lu(A)? {
+        Factorization::Lu { LU, perm } => Ok((LU, perm)),
+        Factorization::Qr { .. } => unreachable!(),
+    };
+}
+
+// log|det(A)| from the LU diagonal, the default of Scalar::log_det
+pub(crate) fn lu_log_det<F: Scalar>(A: &Matrix<F>) -> Result<F, LinAlgError> {
+    let (LU, _) = lu_factors(A)?;
+    return Ok(LU
+        .diagonal()
+        .into_iter()
+        .fold(F::zero(), |acc, d| acc + d.abs().ln()));
+}
+
+impl<F: Scalar> Matrix<F> {
+    // Zero for a singular matrix. May overflow or underflow for large matrices, see log_det.
+    pub fn det(&self) -> Result<F, LinAlgError> {
+        let (LU, perm) = lu_factors(self)?;
+        let sign: F = F::from_f64(perm.sign());
+        return Ok(LU.diagonal().into_iter().fold(sign, |acc, d| acc * d));
+    }
+
+    // Natural logarithm of |det(A)|, which stays finite when det(A) itself does not fit in an
+    // f64. Negative infinity for a singular matrix.
+    pub fn log_det(&self) -> Result<F, LinAlgError> {
+        return F::log_det(self);
+    }
+
+    // Sign of det(A) as +1, -1, or 0 for a singular matrix
+    pub fn sign_det(&self) -> Result<f64, LinAlgError> {
+        let (LU, perm) = lu_factors(self)?;
+        let mut sign: f64 = perm.sign();
+        for d in LU.diagonal() {
+            if d == F::zero() {
+                return Ok(0.0);
+            }
+            sign *= d.to_f64().signum();
+        }
+        return Ok(sign);
+    }
+
+    pub fn inverse(&self) -> Result<Matrix<F>, LinAlgError> {
+        return F::inverse(self);
+    }
+
+    // A^n by exponentiation by squaring. Negative powers invert first, so they fail for a
+    // singular matrix.
+    pub fn powi(&self, n: i32) -> Result<Matrix<F>, LinAlgError> {
+        let (rows, cols) = self.get_dim();
+        if rows != cols {
+            return Err(LinAlgError::DimensionMismatch {
+                expected: (rows, rows),
+                found: (rows, cols),
+            });
+        }
+
+        let mut base: Matrix<F> = if n < 0 { self.inverse()? } else { self.clone() };
+        let mut exp: u32 = n.unsigned_abs();
+        let mut result: Matrix<F> = Matrix::<F>::identity(rows);
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result *= &base;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = &base * &base;
+            }
+        }
+        return Ok(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lin_alg::example_matrix;
+    use crate::lin_alg::mat::Layout;
+
+    fn singular_matrix() -> Matrix<f64> {
+        return Matrix::<f64>::from_vec((2, 2), vec![1.0, 2.0, 2.0, 4.0], Layout::RowMajor);
+    }
+
+    #[test]
+    fn test_det() {
+        // Cofactor expansion along the first row: 0 - 2 * 2 + 1 * (-4) = -8
+        let a: Matrix<f64> = example_matrix();
+        assert!((a.det().unwrap() + 8.0).abs() < 1e-12);
+        assert_eq!(a.sign_det().unwrap(), -1.0);
+        assert!((a.log_det().unwrap() - 8.0_f64.ln()).abs() < 1e-12);
+
+        assert_eq!(singular_matrix().det().unwrap(), 0.0);
+        assert_eq!(singular_matrix().sign_det().unwrap(), 0.0);
+        assert_eq!(singular_matrix().log_det().unwrap(), f64::NEG_INFINITY);
+        assert!(Matrix::<f64>::new((2, 3)).det().is_err());
+    }
+
+    #[test]
+    fn test_log_det_beyond_f64_range() {
+        // det = -(100^200), which overflows
+        let mut a: Matrix<f64> = &Matrix::<f64>::identity(200) * 100.0;
+        a.swap_rows(0, 1);
+        assert_eq!(a.det().unwrap(), f64::NEG_INFINITY);
+        assert!((a.log_det().unwrap() - 200.0 * 100.0_f64.ln()).abs() < 1e-9);
+        assert_eq!(a.sign_det().unwrap(), -1.0);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let a: Matrix<f64> = example_matrix();
+        let inv: Matrix<f64> = a.inverse().unwrap();
+        crate::assert_matrix_approx_eq!(&a * &inv, Matrix::<f64>::identity(3));
+        crate::assert_matrix_approx_eq!(&inv * &a, Matrix::<f64>::identity(3));
+
+        assert_eq!(singular_matrix().inverse(), Err(LinAlgError::Singular));
+    }
+
+    #[test]
+    fn test_powi() {
+        let a: Matrix<f64> = example_matrix();
+        assert_eq!(a.powi(0).unwrap(), Matrix::<f64>::identity(3));
+        assert_eq!(a.powi(1).unwrap(), a);
+
+        let mut expected: Matrix<f64> = Matrix::<f64>::identity(3);
+        for _ in 0..7 {
+            expected *= &a;
+        }
+        crate::assert_matrix_approx_eq!(a.powi(7).unwrap(), expected);
+
+        let inv: Matrix<f64> = a.inverse().unwrap();
+        crate::assert_matrix_approx_eq!(a.powi(-2).unwrap(), &inv * &inv);
+
+        assert_eq!(singular_matrix().powi(-1), Err(LinAlgError::Singular));
+        assert!(singular_matrix().powi(3).is_ok());
+    }
+}
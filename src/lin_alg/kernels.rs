@@ -0,0 +1,315 @@
+/*
+Element-wise kernels on contiguous f64 slices, reached from Matrix<T> through the Scalar trait.
+
+Each kernel has a portable version written so the compiler can auto-vectorise it, and on x86_64
+a version selected at runtime with `is_x86_feature_detected!`. Only `dot` uses AVX intrinsics;
+the element-wise kernels compile the same scalar loop with AVX enabled, leaving vectorisation to
+the compiler, and give bit-identical results on every path. The dot product uses several partial
+sums, so its rounding differs slightly from a sequential sum.
+*/
+
+macro_rules! binary_kernel {
+    ($name:ident, $avx_name:ident, $op:tt) => {
+        pub fn $name(a: &[f64], b: &[f64], out: &mut [f64]) {
+            assert!(a.len() == b.len() && a.len() == out.len());
+            #[cfg(target_arch = "x86_64")]
+            {
+                if is_x86_feature_detected!("avx") {
+                    // Safety: the required CPU feature was detected above
+                    unsafe { $avx_name(a, b, out) };
+                    return;
+                }
+            }
+            for ((o, x), y) in out.iter_mut().zip(a).zip(b) {
+                *o = *x $op *y;
+            }
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        #[target_feature(enable = "avx")]
+        unsafe fn $avx_name(a: &[f64], b: &[f64], out: &mut [f64]) {
+            for ((o, x), y) in out.iter_mut().zip(a).zip(b) {
+                *o = *x $op *y;
+            }
+        }
+    };
+}
+
+macro_rules! scalar_kernel {
+    ($name:ident, $avx_name:ident, |$x:ident, $s:ident| $body:expr) => {
+        pub fn $name(a: &[f64], $s: f64, out: &mut [f64]) {
+            assert!(a.len() == out.len());
+            #[cfg(target_arch = "x86_64")]
+            {
+                if is_x86_feature_detected!("avx") {
+                    // Safety: the required CPU feature was detected above
+                    unsafe { $avx_name(a, $s, out) };
+                    return;
+                }
+            }
+            for (o, $x) in out.iter_mut().zip(a) {
+                *o = $body;
+            }
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        #[target_feature(enable = "avx")]
+        unsafe fn $avx_name(a: &[f64], $s: f64, out: &mut [f64]) {
+            for (o, $x) in out.iter_mut().zip(a) {
+                *o = $body;
+            }
+        }
+    };
+}
+
+// In-place variants overwrite the first operand, so owned matrices can reuse their buffer
+macro_rules! binary_assign_kernel {
+    ($name:ident, $avx_name:ident, |$x:ident, $y:ident| $body:expr) => {
+        pub fn $name(a: &mut [f64], b: &[f64]) {
+            assert!(a.len() == b.len());
+            #[cfg(target_arch = "x86_64")]
+            {
+                if is_x86_feature_detected!("avx") {
+                    // Safety: the required CPU feature was detected above
+                    unsafe { $avx_name(a, b) };
+                    return;
+                }
+            }
+            for (o, $y) in a.iter_mut().zip(b) {
+                let $x: f64 = *o;
+                *o = $body;
+            }
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        #[target_feature(enable = "avx")]
+        unsafe fn $avx_name(a: &mut [f64], b: &[f64]) {
+            for (o, $y) in a.iter_mut().zip(b) {
+                let $x: f64 = *o;
+                *o = $body;
+            }
+        }
+    };
+}
+
+macro_rules! scalar_assign_kernel {
+    ($name:ident, $avx_name:ident, |$x:ident, $s:ident| $body:expr) => {
+        pub fn $name(a: &mut [f64], $s: f64) {
+            #[cfg(target_arch = "x86_64")]
+            {
+                if is_x86_feature_detected!("avx") {
+                    // Safety: the required CPU feature was detected above
+                    unsafe { $avx_name(a, $s) };
+                    return;
+                }
+            }
+            for o in a.iter_mut() {
+                let $x: f64 = *o;
+                *o = $body;
+            }
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        #[target_feature(enable = "avx")]
+        unsafe fn $avx_name(a: &mut [f64], $s: f64) {
+            for o in a.iter_mut() {
+                let $x: f64 = *o;
+                *o = $body;
+            }
+        }
+    };
+}
+
+binary_kernel!(add, add_avx, +);
+binary_kernel!(sub, sub_avx, -);
+
+scalar_kernel!(add_scalar, add_scalar_avx, |x, s| *x + s);
+scalar_kernel!(sub_scalar, sub_scalar_avx, |x, s| *x - s);
+scalar_kernel!(scalar_sub, scalar_sub_avx, |x, s| s - *x);
+scalar_kernel!(mul_scalar, mul_scalar_avx, |x, s| *x * s);
+scalar_kernel!(div_scalar, div_scalar_avx, |x, s| *x / s);
+
+binary_assign_kernel!(add_assign, add_assign_avx, |x, y| x + *y);
+binary_assign_kernel!(sub_assign, sub_assign_avx, |x, y| x - *y);
+binary_assign_kernel!(rsub_assign, rsub_assign_avx, |x, y| *y - x);
+
+scalar_assign_kernel!(add_scalar_assign, add_scalar_assign_avx, |x, s| x + s);
+scalar_assign_kernel!(sub_scalar_assign, sub_scalar_assign_avx, |x, s| x - s);
+scalar_assign_kernel!(scalar_sub_assign, scalar_sub_assign_avx, |x, s| s - x);
+scalar_assign_kernel!(mul_scalar_assign, mul_scalar_assign_avx, |x, s| x * s);
+scalar_assign_kernel!(div_scalar_assign, div_scalar_assign_avx, |x, s| x / s);
+
+pub fn abs(a: &[f64], out: &mut [f64]) {
+    // Clearing the sign bit is exact, so a single auto-vectorised loop serves every target
+    for (o, x) in out.iter_mut().zip(a) {
+        *o = x.abs();
+    }
+}
+
+pub fn dot(a: &[f64], b: &[f64]) -> f64 {
+    assert!(a.len() == b.len());
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx") {
+            // Safety: the required CPU feature was detected above
+            return unsafe { dot_avx(a, b) };
+        }
+    }
+    return dot_portable(a, b);
+}
+
+// Four independent partial sums break the dependency chain of a sequential reduction
+fn dot_portable(a: &[f64], b: &[f64]) -> f64 {
+    let mut acc: [f64; 4] = [0.0; 4];
+    let chunks_a = a.chunks_exact(4);
+    let chunks_b = b.chunks_exact(4);
+    let (rem_a, rem_b) = (chunks_a.remainder(), chunks_b.remainder());
+    for (x, y) in chunks_a.zip(chunks_b) {
+        for i in 0..4 {
+            acc[i] += x[i] * y[i];
+        }
+    }
+    let mut sum: f64 = (acc[0] + acc[1]) + (acc[2] + acc[3]);
+    for (x, y) in rem_a.iter().zip(rem_b) {
+        sum += x * y;
+    }
+    return sum;
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx")]
+unsafe fn dot_avx(a: &[f64], b: &[f64]) -> f64 {
+    use std::arch::x86_64::*;
+
+    let n: usize = a.len();
+    let mut acc0: __m256d = _mm256_setzero_pd();
+    let mut acc1: __m256d = _mm256_setzero_pd();
+    let mut i: usize = 0;
+    while i + 8 <= n {
+        // Safety: i + 8 <= n for both slices, and the unaligned loads have no alignment demand
+        unsafe {
+            let x0: __m256d = _mm256_loadu_pd(a.as_ptr().add(i));
+            let y0: __m256d = _mm256_loadu_pd(b.as_ptr().add(i));
+            let x1: __m256d = _mm256_loadu_pd(a.as_ptr().add(i + 4));
+            let y1: __m256d = _mm256_loadu_pd(b.as_ptr().add(i + 4));
+            acc0 = _mm256_add_pd(acc0, _mm256_mul_pd(x0, y0));
+            acc1 = _mm256_add_pd(acc1, _mm256_mul_pd(x1, y1));
+        }
+        i += 8;
+    }
+
+    let mut lanes: [f64; 4] = [0.0; 4];
+    // Safety: lanes holds exactly four f64 values
+    unsafe { _mm256_storeu_pd(lanes.as_mut_ptr(), _mm256_add_pd(acc0, acc1)) };
+    let mut sum: f64 = (lanes[0] + lanes[1]) + (lanes[2] + lanes[3]);
+    while i < n {
+        sum += a[i] * b[i];
+        i += 1;
+    }
+    return sum;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(n: usize, seed: f64) -> Vec<f64> {
+        return (0..n)
+            .map(|i| ((i as f64 + seed) * 0.7315).sin() * 10.0)
+            .collect();
+    }
+
+    #[test]
+    fn test_elementwise_kernels_bit_identical() {
+        // Lengths around the vector width exercise the remainder handling
+        for n in [0, 1, 3, 4, 5, 8, 13, 64, 1001] {
+            let a: Vec<f64> = sample(n, 0.0);
+            let b: Vec<f64> = sample(n, 3.0);
+            let mut out: Vec<f64> = vec![0.0; n];
+
+            add(&a, &b, &mut out);
+            assert!(out
+                .iter()
+                .zip(a.iter().zip(&b))
+                .all(|(o, (x, y))| *o == x + y));
+            sub(&a, &b, &mut out);
+            assert!(out
+                .iter()
+                .zip(a.iter().zip(&b))
+                .all(|(o, (x, y))| *o == x - y));
+            add_scalar(&a, 2.5, &mut out);
+            assert!(out.iter().zip(&a).all(|(o, x)| *o == x + 2.5));
+            sub_scalar(&a, 2.5, &mut out);
+            assert!(out.iter().zip(&a).all(|(o, x)| *o == x - 2.5));
+            scalar_sub(&a, 2.5, &mut out);
+            assert!(out.iter().zip(&a).all(|(o, x)| *o == 2.5 - x));
+            mul_scalar(&a, -1.5, &mut out);
+            assert!(out.iter().zip(&a).all(|(o, x)| *o == x * -1.5));
+            div_scalar(&a, 3.0, &mut out);
+            assert!(out.iter().zip(&a).all(|(o, x)| *o == x / 3.0));
+            abs(&a, &mut out);
+            assert!(out.iter().zip(&a).all(|(o, x)| *o == x.abs()));
+        }
+    }
+
+    #[test]
+    fn test_assign_kernels_match_out_of_place() {
+        for n in [0, 1, 5, 8, 13, 1001] {
+            let a: Vec<f64> = sample(n, 0.5);
+            let b: Vec<f64> = sample(n, 4.0);
+            let mut expected: Vec<f64> = vec![0.0; n];
+
+            let mut x: Vec<f64> = a.clone();
+            add_assign(&mut x, &b);
+            add(&a, &b, &mut expected);
+            assert_eq!(x, expected);
+
+            x.clone_from(&a);
+            sub_assign(&mut x, &b);
+            sub(&a, &b, &mut expected);
+            assert_eq!(x, expected);
+
+            x.clone_from(&a);
+            rsub_assign(&mut x, &b);
+            sub(&b, &a, &mut expected);
+            assert_eq!(x, expected);
+
+            x.clone_from(&a);
+            add_scalar_assign(&mut x, 1.25);
+            add_scalar(&a, 1.25, &mut expected);
+            assert_eq!(x, expected);
+
+            x.clone_from(&a);
+            sub_scalar_assign(&mut x, 1.25);
+            sub_scalar(&a, 1.25, &mut expected);
+            assert_eq!(x, expected);
+
+            x.clone_from(&a);
+            scalar_sub_assign(&mut x, 1.25);
+            scalar_sub(&a, 1.25, &mut expected);
+            assert_eq!(x, expected);
+
+            x.clone_from(&a);
+            mul_scalar_assign(&mut x, -3.0);
+            mul_scalar(&a, -3.0, &mut expected);
+            assert_eq!(x, expected);
+
+            x.clone_from(&a);
+            div_scalar_assign(&mut x, 7.0);
+            div_scalar(&a, 7.0, &mut expected);
+            assert_eq!(x, expected);
+        }
+    }
+
+    #[test]
+    fn test_dot_matches_reference() {
+        for n in [0, 1, 3, 7, 8, 9, 100, 1001] {
+            let a: Vec<f64> = sample(n, 1.0);
+            let b: Vec<f64> = sample(n, 2.0);
+            let reference: f64 = a.iter().zip(&b).map(|(x, y)| x * y).sum();
+            let scale: f64 = a.iter().zip(&b).map(|(x, y)| (x * y).abs()).sum();
+            assert!((dot(&a, &b) - reference).abs() <= 1e-14 * scale);
+            assert!((dot_portable(&a, &b) - reference).abs() <= 1e-14 * scale);
+        }
+    }
+}
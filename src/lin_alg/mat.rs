@@ -1,110 +1,283 @@
 /*
 Implements a basic 2D matrix struct and some fundamental operations.
+
+The element type is any Scalar: f64, f32 or the automatic differentiation types. Element-wise
+operations and products go through the Scalar kernels, which are vectorised for f64. Operators
+with a scalar on the left are only provided for f64, since Rust cannot implement them for a
+generic left-hand side.
 */
 
+use super::scalar::Scalar;
+use std::borrow::Cow;
+
+// Order in which the entries are stored in memory. Row-major stores element (r, c) at
+// r * cols + c, column-major (Fortran order) at c * rows + r.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    RowMajor,
+    ColMajor,
+}
+
+impl Layout {
+    pub fn flip(self) -> Self {
+        return match self {
+            Layout::RowMajor => Layout::ColMajor,
+            Layout::ColMajor => Layout::RowMajor,
+        };
+    }
+}
+
 #[derive(Debug)]
 pub struct Matrix<T> {
     data: Vec<T>,
     dim: (usize, usize),
+    layout: Layout,
 }
 
-impl Matrix<f64> {
+impl<T: Scalar> Matrix<T> {
     // Basic constructor, getters and setters
     pub fn new(dim: (usize, usize)) -> Self {
-        let data: Vec<f64> = vec![0.0; dim.0 * dim.1];
-        return Self { data, dim };
+        return Self::new_with_layout(dim, Layout::RowMajor);
+    }
+
+    pub fn new_with_layout(dim: (usize, usize), layout: Layout) -> Self {
+        let data: Vec<T> = vec![T::zero(); dim.0 * dim.1];
+        return Self { data, dim, layout };
+    }
+
+    // Takes ownership of existing storage without copying, e.g. Fortran-ordered data
+    pub fn from_vec(dim: (usize, usize), data: Vec<T>, layout: Layout) -> Self {
+        assert_eq!(
+            data.len(),
+            dim.0 * dim.1,
+            "Data length does not match matrix dimensions."
+        );
+        return Self { data, dim, layout };
+    }
+
+    // Row-major matrix from nested rows, as SMatrix::from_rows
+    pub fn from_rows<const C: usize>(rows: &[[T; C]]) -> Self {
+        let data: Vec<T> = rows.iter().flatten().copied().collect();
+        return Self::from_vec((rows.len(), C), data, Layout::RowMajor);
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        return self.data;
+    }
+
+    pub fn ones(dim: (usize, usize)) -> Self {
+        let data: Vec<T> = vec![T::one(); dim.0 * dim.1];
+        return Self {
+            data,
+            dim,
+            layout: Layout::RowMajor,
+        };
     }
 
     pub fn identity(size: usize) -> Self {
-        let mut mat: Matrix<f64> = Matrix::<f64>::new((size, size));
+        let mut mat: Matrix<T> = Matrix::<T>::new((size, size));
         for i in 0..size {
-            mat.set(i, i, 1.0);
+            mat.set(i, i, T::one());
         }
         return mat;
     }
 
-    pub fn set(&mut self, r: usize, c: usize, val: f64) {
+    pub fn set(&mut self, r: usize, c: usize, val: T) {
         if r >= self.dim.0 || c >= self.dim.1 {
             panic!("Index out of bounds.");
         }
-        self.data[r * self.dim.1 + c] = val;
+        let idx: usize = self.index(r, c);
+        self.data[idx] = val;
     }
 
-    pub fn get(&self, r: usize, c: usize) -> f64 {
+    pub fn get(&self, r: usize, c: usize) -> T {
         if r >= self.dim.0 || c >= self.dim.1 {
             panic!("Index out of bounds.");
         }
-        return self.data[r * self.dim.1 + c];
+        return self.data[self.index(r, c)];
     }
 
     pub fn get_dim(&self) -> (usize, usize) {
         return self.dim;
     }
 
-    // Matrix specific mathematical operations
-    pub fn transpose(&self) -> Self {
-        let mut result: Matrix<f64> = Matrix::<f64>::new((self.dim.1, self.dim.0));
+    pub fn get_layout(&self) -> Layout {
+        return self.layout;
+    }
+
+    // Storage in the matrix' own layout, for kernels that work on contiguous memory
+    pub(crate) fn data(&self) -> &[T] {
+        return &self.data;
+    }
+
+    pub(crate) fn data_mut(&mut self) -> &mut [T] {
+        return &mut self.data;
+    }
+
+    // Distance in memory between consecutive rows and consecutive columns
+    pub(crate) fn strides(&self) -> (usize, usize) {
+        return match self.layout {
+            Layout::RowMajor => (self.dim.1, 1),
+            Layout::ColMajor => (1, self.dim.0),
+        };
+    }
+
+    fn index(&self, r: usize, c: usize) -> usize {
+        let (rs, cs) = self.strides();
+        return r * rs + c * cs;
+    }
+
+    // Storage rearranged into the given layout, borrowed if it already matches. Lets
+    // element-wise kernels run on operands with different layouts.
+    fn data_in_layout(&self, layout: Layout) -> Cow<'_, [T]> {
+        if self.layout == layout {
+            return Cow::Borrowed(&self.data);
+        }
+        return Cow::Owned(self.to_layout(layout).data);
+    }
+
+    pub fn to_layout(&self, layout: Layout) -> Self {
+        if self.layout == layout {
+            return self.clone();
+        }
+        let mut result: Matrix<T> = Matrix::<T>::new_with_layout(self.dim, layout);
         for r in 0..self.dim.0 {
             for c in 0..self.dim.1 {
-                result.set(c, r, self.get(r, c));
+                result.set(r, c, self.get(r, c));
             }
         }
         return result;
     }
 
-    pub fn dot_product(&self, rhs: &Self) -> f64 {
-        assert_eq!(self.dim, rhs.dim);
-        let mut sum: f64 = 0.0;
+    // Matrix specific mathematical operations
+    // Transposes without moving any data by reinterpreting the storage in the other layout
+    pub fn into_transpose(self) -> Self {
+        return Self {
+            data: self.data,
+            dim: (self.dim.1, self.dim.0),
+            layout: self.layout.flip(),
+        };
+    }
+
+    // Transposed copy in the same layout as self. Use into_transpose to avoid the copy when the
+    // layout of the result does not matter.
+    pub fn transpose(&self) -> Self {
+        let mut result: Matrix<T> =
+            Matrix::<T>::new_with_layout((self.dim.1, self.dim.0), self.layout);
         for r in 0..self.dim.0 {
             for c in 0..self.dim.1 {
-                sum += self.get(r, c) * rhs.get(r, c);
+                result.set(c, r, self.get(r, c));
             }
         }
-        return sum;
+        return result;
+    }
+
+    pub fn dot_product(&self, rhs: &Self) -> T {
+        assert_eq!(self.dim, rhs.dim);
+        return T::slice_dot(&self.data, &rhs.data_in_layout(self.layout));
     }
 
-    pub fn norm(&self) -> f64 {
+    pub fn norm(&self) -> T {
         return self.dot_product(self).sqrt();
     }
 
-    pub fn abs(&self) -> Matrix<f64> {
-        let mut result: Matrix<f64> = Matrix::<f64>::new(self.dim);
+    pub fn abs(&self) -> Matrix<T> {
+        let mut result: Matrix<T> = Matrix::<T>::new_with_layout(self.dim, self.layout);
+        T::slice_abs(&self.data, &mut result.data);
+        return result;
+    }
+}
 
-        for r in 0..self.dim.0 {
-            for c in 0..self.dim.1 {
-                result.set(r, c, self.get(r, c).abs())
-            }
+impl Matrix<f64> {
+    // Approximate comparisons
+    pub fn approx_eq(&self, other: &Self, abs_tol: f64, rel_tol: f64) -> bool {
+        if self.dim != other.dim {
+            return false;
         }
+        return self
+            .data
+            .iter()
+            .zip(other.data_in_layout(self.layout).iter())
+            .all(|(a, b)| crate::approx::approx_eq_f64(*a, *b, abs_tol, rel_tol));
+    }
 
-        return result;
+    pub fn ulps_eq(&self, other: &Self, max_ulps: u64) -> bool {
+        if self.dim != other.dim {
+            return false;
+        }
+        return self
+            .data
+            .iter()
+            .zip(other.data_in_layout(self.layout).iter())
+            .all(|(a, b)| crate::approx::ulps_eq_f64(*a, *b, max_ulps));
     }
 }
 
 // Mathematical operations overloading
 // Reference + Reference
-impl std::ops::Add<&Matrix<f64>> for &Matrix<f64> {
-    type Output = Matrix<f64>;
+impl<T: Scalar> std::ops::Add<&Matrix<T>> for &Matrix<T> {
+    type Output = Matrix<T>;
 
-    fn add(self, rhs: &Matrix<f64>) -> Self::Output {
+    fn add(self, rhs: &Matrix<T>) -> Self::Output {
         assert_eq!(
             self.dim, rhs.dim,
             "Matrix dimensions must match for addition."
         );
-        let mut result: Matrix<f64> = Matrix::<f64>::new(self.dim);
-        for r in 0..self.dim.0 {
-            for c in 0..self.dim.1 {
-                result.set(r, c, self.get(r, c) + rhs.get(r, c));
-            }
-        }
+        let mut result: Matrix<T> = Matrix::<T>::new_with_layout(self.dim, self.layout);
+        T::slice_add(
+            &self.data,
+            &rhs.data_in_layout(self.layout),
+            &mut result.data,
+        );
         return result;
     }
 }
 
+// Owned + Reference (reuses the left buffer)
+impl<T: Scalar> std::ops::Add<&Matrix<T>> for Matrix<T> {
+    type Output = Matrix<T>;
+    fn add(mut self, rhs: &Matrix<T>) -> Self::Output {
+        self += rhs;
+        return self;
+    }
+}
+
+// Reference + Owned (reuses the right buffer)
+impl<T: Scalar> std::ops::Add<Matrix<T>> for &Matrix<T> {
+    type Output = Matrix<T>;
+    fn add(self, rhs: Matrix<T>) -> Self::Output {
+        rhs + self
+    }
+}
+
 // Owned + Owned
-impl std::ops::Add for Matrix<f64> {
-    type Output = Matrix<f64>;
+impl<T: Scalar> std::ops::Add for Matrix<T> {
+    type Output = Matrix<T>;
     fn add(self, rhs: Self) -> Self::Output {
-        &self + &rhs
+        self + &rhs
+    }
+}
+
+// Reference += Reference
+impl<T: Scalar> std::ops::AddAssign<&Matrix<T>> for Matrix<T> {
+    fn add_assign(&mut self, rhs: &Matrix<T>) {
+        assert_eq!(
+            self.dim, rhs.dim,
+            "Matrix dimensions must match for addition."
+        );
+        T::slice_add_assign(&mut self.data, &rhs.data_in_layout(self.layout));
+    }
+}
+
+impl<T: Scalar> std::ops::AddAssign for Matrix<T> {
+    fn add_assign(&mut self, rhs: Self) {
+        *self += &rhs;
+    }
+}
+
+impl<T: Scalar> std::ops::AddAssign<T> for Matrix<T> {
+    fn add_assign(&mut self, rhs: T) {
+        T::slice_add_scalar_assign(&mut self.data, rhs);
     }
 }
 
@@ -113,49 +286,108 @@ impl std::ops::Add<&Matrix<f64>> for f64 {
     type Output = Matrix<f64>;
 
     fn add(self, rhs: &Matrix<f64>) -> Self::Output {
-        let mut result: Matrix<f64> = Matrix::<f64>::new(rhs.dim);
-        for r in 0..rhs.dim.0 {
-            for c in 0..rhs.dim.1 {
-                result.set(r, c, self + rhs.get(r, c));
-            }
-        }
-        return result;
+        return rhs + self;
     }
 }
 
 // Reference + Scalar (Scalar on the right)
-impl std::ops::Add<f64> for &Matrix<f64> {
+impl<T: Scalar> std::ops::Add<T> for &Matrix<T> {
+    type Output = Matrix<T>;
+
+    fn add(self, rhs: T) -> Self::Output {
+        let mut result: Matrix<T> = Matrix::<T>::new_with_layout(self.dim, self.layout);
+        T::slice_add_scalar(&self.data, rhs, &mut result.data);
+        return result;
+    }
+}
+
+// Scalar + Owned and Owned + Scalar (reuse the matrix buffer)
+impl std::ops::Add<Matrix<f64>> for f64 {
     type Output = Matrix<f64>;
+    fn add(self, mut rhs: Matrix<f64>) -> Self::Output {
+        rhs += self;
+        return rhs;
+    }
+}
 
-    fn add(self, rhs: f64) -> Self::Output {
-        rhs + self
+impl<T: Scalar> std::ops::Add<T> for Matrix<T> {
+    type Output = Matrix<T>;
+    fn add(mut self, rhs: T) -> Self::Output {
+        self += rhs;
+        return self;
     }
 }
 
 // Reference - Reference
-impl std::ops::Sub<&Matrix<f64>> for &Matrix<f64> {
-    type Output = Matrix<f64>;
+impl<T: Scalar> std::ops::Sub<&Matrix<T>> for &Matrix<T> {
+    type Output = Matrix<T>;
 
-    fn sub(self, rhs: &Matrix<f64>) -> Self::Output {
+    fn sub(self, rhs: &Matrix<T>) -> Self::Output {
         assert_eq!(
             self.dim, rhs.dim,
             "Matrix dimensions must match for subtraction."
         );
-        let mut result: Matrix<f64> = Matrix::<f64>::new(self.dim);
-        for r in 0..self.dim.0 {
-            for c in 0..self.dim.1 {
-                result.set(r, c, self.get(r, c) - rhs.get(r, c));
-            }
-        }
+        let mut result: Matrix<T> = Matrix::<T>::new_with_layout(self.dim, self.layout);
+        T::slice_sub(
+            &self.data,
+            &rhs.data_in_layout(self.layout),
+            &mut result.data,
+        );
         return result;
     }
 }
 
+// Owned - Reference (reuses the left buffer)
+impl<T: Scalar> std::ops::Sub<&Matrix<T>> for Matrix<T> {
+    type Output = Matrix<T>;
+    fn sub(mut self, rhs: &Matrix<T>) -> Self::Output {
+        self -= rhs;
+        return self;
+    }
+}
+
+// Reference - Owned (reuses the right buffer)
+impl<T: Scalar> std::ops::Sub<Matrix<T>> for &Matrix<T> {
+    type Output = Matrix<T>;
+    fn sub(self, mut rhs: Matrix<T>) -> Self::Output {
+        assert_eq!(
+            self.dim, rhs.dim,
+            "Matrix dimensions must match for subtraction."
+        );
+        let layout: Layout = rhs.layout;
+        T::slice_rsub_assign(&mut rhs.data, &self.data_in_layout(layout));
+        return rhs;
+    }
+}
+
 // Owned - Owned
-impl std::ops::Sub for Matrix<f64> {
-    type Output = Matrix<f64>;
+impl<T: Scalar> std::ops::Sub for Matrix<T> {
+    type Output = Matrix<T>;
     fn sub(self, rhs: Self) -> Self::Output {
-        &self - &rhs
+        self - &rhs
+    }
+}
+
+// Reference -= Reference
+impl<T: Scalar> std::ops::SubAssign<&Matrix<T>> for Matrix<T> {
+    fn sub_assign(&mut self, rhs: &Matrix<T>) {
+        assert_eq!(
+            self.dim, rhs.dim,
+            "Matrix dimensions must match for subtraction."
+        );
+        T::slice_sub_assign(&mut self.data, &rhs.data_in_layout(self.layout));
+    }
+}
+
+impl<T: Scalar> std::ops::SubAssign for Matrix<T> {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self -= &rhs;
+    }
+}
+
+impl<T: Scalar> std::ops::SubAssign<T> for Matrix<T> {
+    fn sub_assign(&mut self, rhs: T) {
+        T::slice_sub_scalar_assign(&mut self.data, rhs);
     }
 }
 
@@ -164,83 +396,141 @@ impl std::ops::Sub<&Matrix<f64>> for f64 {
     type Output = Matrix<f64>;
 
     fn sub(self, rhs: &Matrix<f64>) -> Self::Output {
-        let mut result: Matrix<f64> = Matrix::<f64>::new(rhs.dim);
-        for r in 0..rhs.dim.0 {
-            for c in 0..rhs.dim.1 {
-                result.set(r, c, self - rhs.get(r, c));
-            }
-        }
+        let mut result: Matrix<f64> = Matrix::<f64>::new_with_layout(rhs.dim, rhs.layout);
+        f64::slice_scalar_sub(&rhs.data, self, &mut result.data);
         return result;
     }
 }
 
 // Reference - Scalar (Scalar on the right)
-impl std::ops::Sub<f64> for &Matrix<f64> {
-    type Output = Matrix<f64>;
+impl<T: Scalar> std::ops::Sub<T> for &Matrix<T> {
+    type Output = Matrix<T>;
 
-    fn sub(self, rhs: f64) -> Self::Output {
-        let mut result: Matrix<f64> = Matrix::<f64>::new(self.dim);
-        for r in 0..self.dim.0 {
-            for c in 0..self.dim.1 {
-                result.set(r, c, self.get(r, c) - rhs);
-            }
-        }
+    fn sub(self, rhs: T) -> Self::Output {
+        let mut result: Matrix<T> = Matrix::<T>::new_with_layout(self.dim, self.layout);
+        T::slice_sub_scalar(&self.data, rhs, &mut result.data);
         return result;
     }
 }
 
-// Reference * Reference
-impl std::ops::Mul<&Matrix<f64>> for &Matrix<f64> {
+// Scalar - Owned and Owned - Scalar (reuse the matrix buffer)
+impl std::ops::Sub<Matrix<f64>> for f64 {
     type Output = Matrix<f64>;
+    fn sub(self, mut rhs: Matrix<f64>) -> Self::Output {
+        f64::slice_scalar_sub_assign(&mut rhs.data, self);
+        return rhs;
+    }
+}
 
-    fn mul(self, rhs: &Matrix<f64>) -> Self::Output {
+impl<T: Scalar> std::ops::Sub<T> for Matrix<T> {
+    type Output = Matrix<T>;
+    fn sub(mut self, rhs: T) -> Self::Output {
+        self -= rhs;
+        return self;
+    }
+}
+
+// Negation
+impl<T: Scalar> std::ops::Neg for &Matrix<T> {
+    type Output = Matrix<T>;
+    fn neg(self) -> Self::Output {
+        self * -T::one()
+    }
+}
+
+impl<T: Scalar> std::ops::Neg for Matrix<T> {
+    type Output = Matrix<T>;
+    fn neg(mut self) -> Self::Output {
+        self *= -T::one();
+        return self;
+    }
+}
+
+// Reference * Reference
+impl<T: Scalar> std::ops::Mul<&Matrix<T>> for &Matrix<T> {
+    type Output = Matrix<T>;
+
+    fn mul(self, rhs: &Matrix<T>) -> Self::Output {
         assert!(self.dim.1 == rhs.dim.0);
-        let mut result: Matrix<f64> = Matrix::<f64>::new((self.dim.0, rhs.dim.1));
-        for r1 in 0..self.dim.0 {
-            for c2 in 0..rhs.dim.1 {
-                let mut sum = 0.0;
-                for c1 in 0..self.dim.1 {
-                    sum += self.get(r1, c1) * rhs.get(c1, c2);
-                }
-                result.set(r1, c2, sum);
-            }
-        }
+        let mut result: Matrix<T> = Matrix::<T>::new((self.dim.0, rhs.dim.1));
+        T::gemm(T::one(), self, rhs, T::zero(), &mut result);
         return result;
     }
 }
 
 // Owned * Owned (forward to reference version)
-impl std::ops::Mul for Matrix<f64> {
-    type Output = Matrix<f64>;
+impl<T: Scalar> std::ops::Mul for Matrix<T> {
+    type Output = Matrix<T>;
     fn mul(self, rhs: Self) -> Self::Output {
         &self * &rhs
     }
 }
 
+// A product cannot be formed in the storage of its operands, so mixed versions forward too
+impl<T: Scalar> std::ops::Mul<&Matrix<T>> for Matrix<T> {
+    type Output = Matrix<T>;
+    fn mul(self, rhs: &Matrix<T>) -> Self::Output {
+        &self * rhs
+    }
+}
+
+impl<T: Scalar> std::ops::Mul<Matrix<T>> for &Matrix<T> {
+    type Output = Matrix<T>;
+    fn mul(self, rhs: Matrix<T>) -> Self::Output {
+        self * &rhs
+    }
+}
+
+// Reference *= Reference
+impl<T: Scalar> std::ops::MulAssign<&Matrix<T>> for Matrix<T> {
+    fn mul_assign(&mut self, rhs: &Matrix<T>) {
+        *self = &*self * rhs;
+    }
+}
+
+impl<T: Scalar> std::ops::MulAssign<T> for Matrix<T> {
+    fn mul_assign(&mut self, rhs: T) {
+        T::slice_mul_scalar_assign(&mut self.data, rhs);
+    }
+}
+
 // Scalar * Reference (Scalar on the left)
 impl std::ops::Mul<&Matrix<f64>> for f64 {
     type Output = Matrix<f64>;
     fn mul(self, rhs: &Matrix<f64>) -> Self::Output {
-        let mut result: Matrix<f64> = Matrix::<f64>::new(rhs.dim);
-        for r in 0..rhs.dim.0 {
-            for c in 0..rhs.dim.1 {
-                result.set(r, c, self * rhs.get(r, c));
-            }
-        }
-        return result;
+        rhs * self
     }
 }
 
 // Reference * Scalar (Scalar on the right)
-impl std::ops::Mul<f64> for &Matrix<f64> {
+impl<T: Scalar> std::ops::Mul<T> for &Matrix<T> {
+    type Output = Matrix<T>;
+    fn mul(self, rhs: T) -> Self::Output {
+        let mut result: Matrix<T> = Matrix::<T>::new_with_layout(self.dim, self.layout);
+        T::slice_mul_scalar(&self.data, rhs, &mut result.data);
+        return result;
+    }
+}
+
+// Scalar * Owned and Owned * Scalar (reuse the matrix buffer)
+impl std::ops::Mul<Matrix<f64>> for f64 {
     type Output = Matrix<f64>;
-    fn mul(self, rhs: f64) -> Self::Output {
-        rhs * self
+    fn mul(self, mut rhs: Matrix<f64>) -> Self::Output {
+        rhs *= self;
+        return rhs;
+    }
+}
+
+impl<T: Scalar> std::ops::Mul<T> for Matrix<T> {
+    type Output = Matrix<T>;
+    fn mul(mut self, rhs: T) -> Self::Output {
+        self *= rhs;
+        return self;
     }
 }
 
 // Reference == Reference
-impl PartialEq for Matrix<f64> {
+impl<T: Scalar> PartialEq for Matrix<T> {
     fn eq(&self, other: &Self) -> bool {
         if self.dim != other.dim {
             return false;
@@ -257,27 +547,37 @@ impl PartialEq for Matrix<f64> {
 }
 
 // Reference / Scalar
-impl std::ops::Div<f64> for &Matrix<f64> {
-    type Output = Matrix<f64>;
-    fn div(self, rhs: f64) -> Self::Output {
-        let mut result: Matrix<f64> = Matrix::<f64>::new(self.dim);
-        for r in 0..self.dim.0 {
-            for c in 0..self.dim.1 {
-                result.set(r, c, self.get(r, c) / rhs);
-            }
-        }
+impl<T: Scalar> std::ops::Div<T> for &Matrix<T> {
+    type Output = Matrix<T>;
+    fn div(self, rhs: T) -> Self::Output {
+        let mut result: Matrix<T> = Matrix::<T>::new_with_layout(self.dim, self.layout);
+        T::slice_div_scalar(&self.data, rhs, &mut result.data);
         return result;
     }
 }
 
+// Owned / Scalar (reuses the buffer)
+impl<T: Scalar> std::ops::Div<T> for Matrix<T> {
+    type Output = Matrix<T>;
+    fn div(mut self, rhs: T) -> Self::Output {
+        self /= rhs;
+        return self;
+    }
+}
+
+impl<T: Scalar> std::ops::DivAssign<T> for Matrix<T> {
+    fn div_assign(&mut self, rhs: T) {
+        T::slice_div_scalar_assign(&mut self.data, rhs);
+    }
+}
+
 // Data management operations
-impl Clone for Matrix<f64> {
+impl<T: Clone> Clone for Matrix<T> {
     fn clone(&self) -> Self {
-        let mut new_data: Vec<f64> = vec![0.0; self.dim.0 * self.dim.1];
-        new_data.clone_from_slice(&self.data);
         return Self {
-            data: new_data,
+            data: self.data.clone(),
             dim: self.dim,
+            layout: self.layout,
         };
     }
 }
@@ -582,6 +882,52 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_compound_assignment() {
+        let mut m: Matrix<f64> =
+            Matrix::<f64>::from_vec((2, 2), vec![1.0, 2.0, 3.0, 4.0], Layout::RowMajor);
+        let other: Matrix<f64> = m.to_layout(Layout::ColMajor);
+
+        m += &other;
+        assert_eq!(m.clone().into_vec(), vec![2.0, 4.0, 6.0, 8.0]);
+        m -= other.clone();
+        assert_eq!(m, other);
+        m *= 3.0;
+        m /= 2.0;
+        m += 1.0;
+        m -= 0.5;
+        assert_eq!(m.clone().into_vec(), vec![2.0, 3.5, 5.0, 6.5]);
+
+        let mut p: Matrix<f64> = Matrix::<f64>::identity(2);
+        p *= &other;
+        p *= &other;
+        assert_eq!(p, &other * &other);
+    }
+
+    #[test]
+    fn test_owned_operators_reuse_buffer() {
+        let a: Matrix<f64> =
+            Matrix::<f64>::from_vec((2, 2), vec![1.0, 2.0, 3.0, 4.0], Layout::RowMajor);
+        let b: Matrix<f64> = a.to_layout(Layout::ColMajor);
+
+        // Results of owned operands keep the owned operand's storage
+        let ptr: *const f64 = b.data().as_ptr();
+        let sum: Matrix<f64> = &a + b;
+        assert_eq!(sum.data().as_ptr(), ptr);
+        assert_eq!(sum, 2.0 * &a);
+
+        let ptr: *const f64 = sum.data().as_ptr();
+        let diff: Matrix<f64> = &a - sum;
+        assert_eq!(diff.data().as_ptr(), ptr);
+        assert_eq!(diff, -&a);
+
+        let scaled: Matrix<f64> = (2.0 * (a.clone() + &a) - 1.0) / 2.0;
+        assert_eq!(scaled.clone().into_vec(), vec![1.5, 3.5, 5.5, 7.5]);
+        assert_eq!(10.0 - (a.clone() + 1.0), 9.0 - &a);
+        assert_eq!(-a.clone(), &a * -1.0);
+        assert_eq!(a.clone() * &a, &a * a.clone());
+    }
+
     #[test]
     fn test_matrix_clone() {
         let mut m1: Matrix<f64> = Matrix::<f64>::new((2, 2));
@@ -608,4 +954,105 @@ mod tests {
         assert_eq!(m1.get(0, 0), 1.0); // Ensure original matrix is unchanged
         assert_eq!(m2.get(0, 0), 5.0); // Ensure cloned matrix is changed
     }
+
+    #[test]
+    fn test_col_major_storage() {
+        // Fortran-ordered 2x3 matrix [[1, 2, 3], [4, 5, 6]]
+        let m: Matrix<f64> =
+            Matrix::<f64>::from_vec((2, 3), vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0], Layout::ColMajor);
+        assert_eq!(m.get_layout(), Layout::ColMajor);
+        assert_eq!(m.get(0, 2), 3.0);
+        assert_eq!(m.get(1, 0), 4.0);
+
+        let m_row: Matrix<f64> = m.to_layout(Layout::RowMajor);
+        assert_eq!(m_row.get_layout(), Layout::RowMajor);
+        assert_eq!(m_row.clone().into_vec(), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        // Equality compares entries, not storage
+        assert_eq!(m, m_row);
+        assert_eq!(Matrix::from_rows(&[[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]), m);
+        assert_eq!(Matrix::<f64>::from_rows::<2>(&[]).get_dim(), (0, 2));
+    }
+
+    #[test]
+    fn test_into_transpose_is_layout_flip() {
+        let m: Matrix<f64> =
+            Matrix::<f64>::from_vec((2, 3), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], Layout::RowMajor);
+        let mt: Matrix<f64> = m.clone().into_transpose();
+        assert_eq!(mt.get_dim(), (3, 2));
+        assert_eq!(mt.get_layout(), Layout::ColMajor);
+        assert_eq!(mt.get(2, 1), 6.0);
+        assert_eq!(mt.get(1, 0), 2.0);
+        // transpose keeps the layout of its input
+        assert_eq!(m.transpose().get_layout(), Layout::RowMajor);
+        assert_eq!(m.transpose(), mt);
+        // Storage is untouched
+        assert_eq!(mt.into_vec(), m.into_vec());
+    }
+
+    #[test]
+    fn test_mixed_layout_arithmetic() {
+        let a: Matrix<f64> =
+            Matrix::<f64>::from_vec((2, 2), vec![1.0, 2.0, 3.0, 4.0], Layout::RowMajor);
+        let b: Matrix<f64> = a.to_layout(Layout::ColMajor);
+        let b_t: Matrix<f64> = a.transpose();
+
+        assert_eq!(&a + &b, 2.0 * &a);
+        assert_eq!(&b - &a, Matrix::<f64>::new((2, 2)));
+        assert_eq!(a.dot_product(&b), 30.0);
+        assert!(a.approx_eq(&b, 0.0, 0.0));
+
+        let mut expected: Matrix<f64> = Matrix::<f64>::new((2, 2));
+        expected.set(0, 0, 5.0);
+        expected.set(0, 1, 11.0);
+        expected.set(1, 0, 11.0);
+        expected.set(1, 1, 25.0);
+        assert_eq!(&a * &b_t, expected);
+        assert_eq!(&b * &b.transpose(), expected);
+        assert_eq!(&b_t.transpose() * &b_t, expected);
+    }
+
+    #[test]
+    fn test_matrix_approx_eq() {
+        let mut m1: Matrix<f64> = Matrix::<f64>::new((2, 2));
+        m1.set(0, 0, 1.0);
+        m1.set(0, 1, -2.0);
+        m1.set(1, 0, 3.0);
+        m1.set(1, 1, 1e8);
+
+        let mut m2: Matrix<f64> = m1.clone();
+        m2.set(0, 1, -2.0 + 1e-12);
+        m2.set(1, 1, 1e8 + 1e-3);
+        assert!(m1.approx_eq(&m2, 1e-10, 1e-10));
+        assert!(!m1.approx_eq(&m2, 1e-10, 0.0));
+
+        // Differences of either sign are detected
+        m2.set(0, 0, 0.5);
+        assert!(!m1.approx_eq(&m2, 1e-10, 1e-10));
+        assert!(!m2.approx_eq(&m1, 1e-10, 1e-10));
+
+        assert!(!m1.approx_eq(&Matrix::<f64>::new((2, 3)), 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_matrix_ulps_eq() {
+        let mut m1: Matrix<f64> = Matrix::<f64>::new((1, 2));
+        m1.set(0, 0, 0.3);
+        m1.set(0, 1, 1.0);
+
+        let mut m2: Matrix<f64> = Matrix::<f64>::new((1, 2));
+        m2.set(0, 0, 0.1 + 0.2);
+        m2.set(0, 1, 1.0);
+        assert!(m1.ulps_eq(&m2, 1));
+        assert!(!m1.ulps_eq(&m2, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "(0, 1): left = 2e0, right = 2.5e0")]
+    fn test_assert_matrix_approx_eq_reports_entries() {
+        let mut m1: Matrix<f64> = Matrix::<f64>::new((2, 2));
+        m1.set(0, 1, 2.0);
+        let mut m2: Matrix<f64> = Matrix::<f64>::new((2, 2));
+        m2.set(0, 1, 2.5);
+        crate::assert_matrix_approx_eq!(m1, m2);
+    }
 }
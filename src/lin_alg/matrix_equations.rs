@@ -0,0 +1,312 @@
+/*
+Solvers for the linear and quadratic matrix equations of control theory:
+
+    Sylvester             A X + X B = C
+    continuous Lyapunov   A X + X A^T + Q = 0
+    discrete Lyapunov     A X A^T - X + Q = 0
+    continuous Riccati    A^T X + X A - X B R^-1 B^T X + Q = 0
+    discrete Riccati      A^T X A - X - A^T X B (R + B^T X B)^-1 B^T X A + Q = 0
+
+The Sylvester equation is solved by the Bartels-Stewart algorithm on the real Schur forms of A
+and B, and both Lyapunov equations reduce to it. The continuous Riccati equation uses the
+matrix sign function of the Hamiltonian, and the discrete one the structure preserving doubling
+algorithm. Both return the stabilising solution.
+*/
+
+use super::factorization::Factorization;
+use super::mat::{Layout, Matrix};
+use super::schur::{diagonal_blocks, real_schur};
+use super::LinAlgError;
+
+const MAX_ITER: usize = 100;
+
+fn check_dim(M: &Matrix<f64>, expected: (usize, usize)) -> Result<(), LinAlgError> {
+    if M.get_dim() != expected {
+        return Err(LinAlgError::DimensionMismatch {
+            expected,
+            found: M.get_dim(),
+        });
+    }
+    return Ok(());
+}
+
+// Relative change between successive iterates, and whether an iteration with quadratic
+// convergence should stop: either the change is at rounding level, or it has stopped shrinking
+// because rounding errors dominate
+fn converged(next: &Matrix<f64>, prev: &Matrix<f64>, last_change: &mut f64) -> bool {
+    let n: usize = next.get_dim().0;
+    let scale: f64 = next.norm_1();
+    let change: f64 = if scale > 0.0 {
+        (next - prev).norm_1() / scale
+    } else {
+        0.0
+    };
+    let done: bool =
+        change <= (n as f64).sqrt() * f64::EPSILON || (change < 1e-8 && change >= *last_change);
+    *last_change = change;
+    return done;
+}
+
+// Solves A X + X B = C for X, with A m x m, B n x n and C m x n. Fails with
+// LinAlgError::Singular when A and -B share an eigenvalue.
+pub fn solve_sylvester(
+    A: &Matrix<f64>,
+    B: &Matrix<f64>,
+    C: &Matrix<f64>,
+) -> Result<Matrix<f64>, LinAlgError> {
+    let m: usize = A.get_dim().0;
+    let n: usize = B.get_dim().0;
+    check_dim(A, (m, m))?;
+    check_dim(B, (n, n))?;
+    check_dim(C, (m, n))?;
+
+    // With A = U S U^T and B = V T V^T the equation becomes S Y + Y T = U^T C V, X = U Y V^T
+    let (U, S) = real_schur(A)?;
+    let (V, T) = real_schur(B)?;
+    let F: Matrix<f64> = &(&U.transpose() * C) * &V;
+
+    // S and T are quasi-triangular, so the blocks of Y can be found one at a time: upwards
+    // through the row blocks of S and rightwards through the column blocks of T
+    let mut Y: Matrix<f64> = Matrix::<f64>::new((m, n));
+    let row_blocks: Vec<(usize, usize)> = diagonal_blocks(&S);
+    for (j0, q) in diagonal_blocks(&T) {
+        for &(i0, p) in row_blocks.iter().rev() {
+            let below: usize = m - i0 - p;
+            let rhs: Matrix<f64> = &(&F.get_block(i0, j0, (p, q))
+                - &(&S.get_block(i0, i0 + p, (p, below)) * &Y.get_block(i0 + p, j0, (below, q))))
+                - &(&Y.get_block(i0, 0, (p, j0)) * &T.get_block(0, j0, (j0, q)));
+
+            // S_ii Y_ij + Y_ij T_jj = rhs in vectorised form, at most 4 x 4
+            let K: Matrix<f64> = &Matrix::<f64>::identity(q).kron(&S.get_block(i0, i0, (p, p)))
+                + &T.get_block(j0, j0, (q, q))
+                    .transpose()
+                    .kron(&Matrix::<f64>::identity(p));
+            let vec_rhs: Matrix<f64> = Matrix::<f64>::from_vec(
+                (p * q, 1),
+                rhs.to_layout(Layout::ColMajor).into_vec(),
+                Layout::ColMajor,
+            );
+            let vec_Y: Matrix<f64> = Factorization::lu(&K)?.solve(&vec_rhs)?;
+            Y.set_block(
+                i0,
+                j0,
+                &Matrix::<f64>::from_vec((p, q), vec_Y.into_vec(), Layout::ColMajor),
+            );
+        }
+    }
+
+    return Ok(&(&U * &Y) * &V.transpose());
+}
+
+// Solves A X + X A^T + Q = 0. For stable A and positive semi-definite Q, X is the positive
+// semi-definite Gramian.
+pub fn solve_continuous_lyapunov(
+    A: &Matrix<f64>,
+    Q: &Matrix<f64>,
+) -> Result<Matrix<f64>, LinAlgError> {
+    return solve_sylvester(A, &A.transpose(), &(-Q));
+}
+
+// Solves A X A^T - X + Q = 0 through the bilinear transform Ac = (A - I)(A + I)^-1, which turns
+// it into Ac X + X Ac^T + 2 (A + I)^-1 Q (A + I)^-T = 0. Fails if -1 is an eigenvalue of A.
+pub fn solve_discrete_lyapunov(
+    A: &Matrix<f64>,
+    Q: &Matrix<f64>,
+) -> Result<Matrix<f64>, LinAlgError> {
+    let n: usize = A.get_dim().0;
+    check_dim(A, (n, n))?;
+    check_dim(Q, (n, n))?;
+
+    let I: Matrix<f64> = Matrix::<f64>::identity(n);
+    let M: Matrix<f64> = (A + &I).inverse()?;
+    let Ac: Matrix<f64> = &(A - &I) * &M;
+    let Qc: Matrix<f64> = &(&M * Q) * &(&M.transpose() * 2.0);
+    return solve_continuous_lyapunov(&Ac, &Qc);
+}
+
+// G = B R^-1 B^T after checking the dimensions of a Riccati problem
+fn riccati_gain_term(
+    A: &Matrix<f64>,
+    B: &Matrix<f64>,
+    Q: &Matrix<f64>,
+    R: &Matrix<f64>,
+) -> Result<Matrix<f64>, LinAlgError> {
+    let (n, m) = B.get_dim();
+    check_dim(A, (n, n))?;
+    check_dim(Q, (n, n))?;
+    check_dim(R, (m, m))?;
+    return Ok(B * &Factorization::lu(R)?.solve(&B.transpose())?);
+}
+
+// Stabilising solution of A^T X + X A - X B R^-1 B^T X + Q = 0, so that A - B R^-1 B^T X is
+// stable. The optimal state feedback gain is then K = R^-1 B^T X.
+pub fn solve_continuous_are(
+    A: &Matrix<f64>,
+    B: &Matrix<f64>,
+    Q: &Matrix<f64>,
+    R: &Matrix<f64>,
+) -> Result<Matrix<f64>, LinAlgError> {
+    let G: Matrix<f64> = riccati_gain_term(A, B, Q, R)?;
+    let n: usize = A.get_dim().0;
+
+    // The stable invariant subspace [I; X] of the Hamiltonian H is the null space of sign(H) + I
+    let H: Matrix<f64> =
+        Matrix::<f64>::from_blocks(&[vec![A, &(-&G)], vec![&(-Q), &(-&A.transpose())]]);
+
+    // Newton iteration for the sign function with determinant scaling
+    let mut Z: Matrix<f64> = H;
+    let mut last_change: f64 = f64::INFINITY;
+    let mut done: bool = false;
+    for _ in 0..MAX_ITER {
+        let Z_inv: Matrix<f64> = Z.inverse()?;
+        let c: f64 = (-Z.log_det()? / (2 * n) as f64).exp();
+        let Z_next: Matrix<f64> = (&Z * c + &Z_inv / c) * 0.5;
+        done = converged(&Z_next, &Z, &mut last_change);
+        Z = Z_next;
+        if done {
+            break;
+        }
+    }
+    if !done {
+        return Err(LinAlgError::NotConverged);
+    }
+
+    let I: Matrix<f64> = Matrix::<f64>::identity(n);
+    let lhs: Matrix<f64> = Matrix::<f64>::vstack(&[
+        &Z.get_block(0, n, (n, n)),
+        &(&Z.get_block(n, n, (n, n)) + &I),
+    ]);
+    let rhs: Matrix<f64> = -Matrix::<f64>::vstack(&[
+        &(&Z.get_block(0, 0, (n, n)) + &I),
+        &Z.get_block(n, 0, (n, n)),
+    ]);
+    let X: Matrix<f64> = Factorization::qr(&lhs)?.solve(&rhs)?;
+    return Ok((&X + &X.transpose()) * 0.5);
+}
+
+// Stabilising solution of A^T X A - X - A^T X B (R + B^T X B)^-1 B^T X A + Q = 0. The optimal
+// state feedback gain is then K = (R + B^T X B)^-1 B^T X A.
+pub fn solve_discrete_are(
+    A: &Matrix<f64>,
+    B: &Matrix<f64>,
+    Q: &Matrix<f64>,
+    R: &Matrix<f64>,
+) -> Result<Matrix<f64>, LinAlgError> {
+    let n: usize = A.get_dim().0;
+    let I: Matrix<f64> = Matrix::<f64>::identity(n);
+
+    // The equation is X = A^T X (I + G X)^-1 A + Q, and each doubling step squares the
+    // convergence factor of the fixed point iteration
+    let mut A_k: Matrix<f64> = A.clone();
+    let mut G_k: Matrix<f64> = riccati_gain_term(A, B, Q, R)?;
+    let mut H_k: Matrix<f64> = Q.clone();
+    let mut last_change: f64 = f64::INFINITY;
+    for _ in 0..MAX_ITER {
+        let W: Factorization = Factorization::lu(&(&I + &(&G_k * &H_k)))?;
+        let W_inv_A: Matrix<f64> = W.solve(&A_k)?;
+        let W_inv_G: Matrix<f64> = W.solve(&G_k)?;
+
+        let H_next: Matrix<f64> = &H_k + &(&(&A_k.transpose() * &H_k) * &W_inv_A);
+        G_k = &G_k + &(&(&A_k * &W_inv_G) * &A_k.transpose());
+        A_k = &A_k * &W_inv_A;
+
+        let done: bool = converged(&H_next, &H_k, &mut last_change);
+        H_k = H_next;
+        if done {
+            return Ok((&H_k + &H_k.transpose()) * 0.5);
+        }
+    }
+    return Err(LinAlgError::NotConverged);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stable_matrix() -> Matrix<f64> {
+        // Eigenvalues -1 +- 2i and -3
+        return Matrix::from_rows(&[[-1.0, 2.0, 0.5], [-2.0, -1.0, 1.0], [0.0, 0.0, -3.0]]);
+    }
+
+    #[test]
+    fn test_sylvester() {
+        let A: Matrix<f64> = stable_matrix();
+        let B: Matrix<f64> = Matrix::from_rows(&[[2.0, 1.0], [-1.0, 3.0]]);
+        let C: Matrix<f64> = Matrix::from_rows(&[[1.0, 0.0], [-2.0, 4.0], [0.5, 1.0]]);
+        let X: Matrix<f64> = solve_sylvester(&A, &B, &C).unwrap();
+        crate::assert_matrix_approx_eq!(&(&A * &X) + &(&X * &B), C);
+
+        // A and -A share every eigenvalue
+        assert_eq!(
+            solve_sylvester(&A, &(-&A), &Matrix::<f64>::ones((3, 3))),
+            Err(LinAlgError::Singular)
+        );
+        assert!(solve_sylvester(&A, &B, &Matrix::<f64>::ones((2, 3))).is_err());
+    }
+
+    #[test]
+    fn test_lyapunov() {
+        let A: Matrix<f64> = stable_matrix();
+        let Q: Matrix<f64> =
+            Matrix::from_rows(&[[2.0, 1.0, 0.0], [1.0, 2.0, 0.0], [0.0, 0.0, 1.0]]);
+
+        let X: Matrix<f64> = solve_continuous_lyapunov(&A, &Q).unwrap();
+        crate::assert_matrix_approx_eq!(
+            &(&(&A * &X) + &(&X * &A.transpose())) + &Q,
+            Matrix::<f64>::new((3, 3))
+        );
+        crate::assert_matrix_approx_eq!(X.transpose(), X);
+
+        let Ad: Matrix<f64> = &A * 0.2;
+        let Xd: Matrix<f64> = solve_discrete_lyapunov(&Ad, &Q).unwrap();
+        crate::assert_matrix_approx_eq!(
+            &(&(&(&Ad * &Xd) * &Ad.transpose()) - &Xd) + &Q,
+            Matrix::<f64>::new((3, 3))
+        );
+    }
+
+    #[test]
+    fn test_scalar_riccati() {
+        let one: Matrix<f64> = Matrix::<f64>::identity(1);
+        // 2x - x^2 + 1 = 0
+        let x: Matrix<f64> = solve_continuous_are(&one, &one, &one, &one).unwrap();
+        assert!((x.get(0, 0) - (1.0 + 2.0_f64.sqrt())).abs() < 1e-12);
+        // x^2 - x - 1 = 0
+        let x: Matrix<f64> = solve_discrete_are(&one, &one, &one, &one).unwrap();
+        assert!((x.get(0, 0) - (1.0 + 5.0_f64.sqrt()) / 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_continuous_are_double_integrator() {
+        let A: Matrix<f64> = Matrix::from_rows(&[[0.0, 1.0], [0.0, 0.0]]);
+        let B: Matrix<f64> = Matrix::from_rows(&[[0.0], [1.0]]);
+        let Q: Matrix<f64> = Matrix::<f64>::identity(2);
+        let R: Matrix<f64> = Matrix::<f64>::identity(1);
+        let X: Matrix<f64> = solve_continuous_are(&A, &B, &Q, &R).unwrap();
+        let s: f64 = 3.0_f64.sqrt();
+        crate::assert_matrix_approx_eq!(X, Matrix::from_rows(&[[s, 1.0], [1.0, s]]), 1e-12, 1e-12);
+    }
+
+    #[test]
+    fn test_discrete_are() {
+        let A: Matrix<f64> = Matrix::from_rows(&[[1.0, 0.1], [0.0, 1.0]]);
+        let B: Matrix<f64> = Matrix::from_rows(&[[0.005], [0.1]]);
+        let Q: Matrix<f64> = Matrix::from_rows(&[[1.0, 0.0], [0.0, 0.5]]);
+        let R: Matrix<f64> = Matrix::from_rows(&[[0.1]]);
+        let X: Matrix<f64> = solve_discrete_are(&A, &B, &Q, &R).unwrap();
+
+        let XB: Matrix<f64> = &X * &B;
+        let S: Matrix<f64> = &R + &(&B.transpose() * &XB);
+        let K: Matrix<f64> = Factorization::lu(&S)
+            .unwrap()
+            .solve(&(&XB.transpose() * &A))
+            .unwrap();
+        let residual: Matrix<f64> =
+            &(&(&(&A.transpose() * &X) * &A) - &X) - &(&(&A.transpose() * &XB) * &K) + Q;
+        crate::assert_matrix_approx_eq!(residual, Matrix::<f64>::new((2, 2)), 1e-9, 0.0);
+
+        // The closed loop A - B K is stable
+        let closed: Matrix<f64> = &A - &(&B * &K);
+        assert!(closed.powi(200).unwrap().norm_1() < 1e-3);
+    }
+}
@@ -0,0 +1,366 @@
+/*
+Matrix exponential, logarithm and square root of square matrices, together with the Frechet
+derivative of the exponential.
+
+expm uses scaling and squaring with diagonal Pade approximants (Higham, "The scaling and
+squaring method for the matrix exponential revisited", 2005). sqrtm uses the Denman-Beavers
+iteration, and logm uses inverse scaling and squaring: repeated square roots bring A close to
+the identity, where log(I + X) is evaluated by Gauss-Legendre quadrature of
+int_0^1 X (I + t X)^-1 dt, which gives the diagonal Pade approximant of the same order.
+*/
+
+use super::factorization::Factorization;
+use super::mat::Matrix;
+use super::LinAlgError;
+
+// Pade coefficients b_0..b_m of the [m/m] approximant to exp, for m = 3, 5, 7, 9
+const PADE_3: [f64; 4] = [120.0, 60.0, 12.0, 1.0];
+const PADE_5: [f64; 6] = [30240.0, 15120.0, 3360.0, 420.0, 30.0, 1.0];
+const PADE_7: [f64; 8] = [
+    17297280.0, 8648640.0, 1995840.0, 277200.0, 25200.0, 1512.0, 56.0, 1.0,
+];
+const PADE_9: [f64; 10] = [
+    17643225600.0,
+    8821612800.0,
+    2075673600.0,
+    302702400.0,
+    30270240.0,
+    2162160.0,
+    110880.0,
+    3960.0,
+    90.0,
+    1.0,
+];
+const PADE_13: [f64; 14] = [
+    64764752532480000.0,
+    32382376266240000.0,
+    7771770303897600.0,
+    1187353796428800.0,
+    129060195264000.0,
+    10559470521600.0,
+    670442572800.0,
+    33522128640.0,
+    1323241920.0,
+    40840800.0,
+    960960.0,
+    16380.0,
+    182.0,
+    1.0,
+];
+
+// Largest 1-norm for which the [m/m] approximant is accurate to double precision
+const THETA: [(usize, f64); 4] = [
+    (3, 1.495585217958292e-2),
+    (5, 2.53939833006323e-1),
+    (7, 9.504178996162932e-1),
+    (9, 2.097847961257068e0),
+];
+const THETA_13: f64 = 5.371920351148152e0;
+
+// logm takes square roots until ||A - I||_1 falls below this, where the 4-point rule is accurate
+const LOG_PADE_RADIUS: f64 = 0.05;
+const MAX_SQRT_STEPS: usize = 64;
+const MAX_DB_ITER: usize = 100;
+
+fn check_square(A: &Matrix<f64>) -> Result<usize, LinAlgError> {
+    let (rows, cols) = A.get_dim();
+    if rows != cols {
+        return Err(LinAlgError::DimensionMismatch {
+            expected: (rows, rows),
+            found: (rows, cols),
+        });
+    }
+    return Ok(rows);
+}
+
+// The scaling steps are chosen from the 1-norm, which must be finite: an infinite norm would ask
+// for 2^31 squarings. The entries are checked as well, since the norm skips NaN.
+fn finite_norm_1(A: &Matrix<f64>) -> Result<f64, LinAlgError> {
+    let (rows, cols) = A.get_dim();
+    for r in 0..rows {
+        for c in 0..cols {
+            if !A.get(r, c).is_finite() {
+                return Err(LinAlgError::NotFinite);
+            }
+        }
+    }
+    let norm: f64 = A.norm_1();
+    if !norm.is_finite() {
+        return Err(LinAlgError::NotFinite);
+    }
+    return Ok(norm);
+}
+
+// Solves (V - U) * R = V + U for the Pade approximant R = r_m(A)
+fn pade_quotient(U: &Matrix<f64>, V: &Matrix<f64>) -> Result<Matrix<f64>, LinAlgError> {
+    return Factorization::lu(&(V - U))?.solve(&(V + U));
+}
+
+// Odd and even parts U, V of the [m/m] numerator for m <= 9
+fn pade_low_order(A: &Matrix<f64>, b: &[f64]) -> (Matrix<f64>, Matrix<f64>) {
+    let n: usize = A.get_dim().0;
+    let A2: Matrix<f64> = A * A;
+    let mut power: Matrix<f64> = Matrix::<f64>::identity(n);
+    let mut U: Matrix<f64> = Matrix::<f64>::new((n, n));
+    let mut V: Matrix<f64> = Matrix::<f64>::new((n, n));
+    for j in 0..(b.len() / 2) {
+        U += &(&power * b[2 * j + 1]);
+        V += &(&power * b[2 * j]);
+        power = &power * &A2;
+    }
+    return (A * &U, V);
+}
+
+// Odd and even parts U, V of the [13/13] numerator, using only A^2, A^4 and A^6
+fn pade_13(A: &Matrix<f64>) -> (Matrix<f64>, Matrix<f64>) {
+    let b: &[f64; 14] = &PADE_13;
+    let n: usize = A.get_dim().0;
+    let I: Matrix<f64> = Matrix::<f64>::identity(n);
+    let A2: Matrix<f64> = A * A;
+    let A4: Matrix<f64> = &A2 * &A2;
+    let A6: Matrix<f64> = &A4 * &A2;
+
+    let U_high: Matrix<f64> = &A6 * &(&(&(&A6 * b[13]) + &(&A4 * b[11])) + &(&A2 * b[9]));
+    let U_low: Matrix<f64> = &(&(&(&A6 * b[7]) + &(&A4 * b[5])) + &(&A2 * b[3])) + &(&I * b[1]);
+    let U: Matrix<f64> = A * &(U_high + U_low);
+
+    let V_high: Matrix<f64> = &A6 * &(&(&(&A6 * b[12]) + &(&A4 * b[10])) + &(&A2 * b[8]));
+    let V_low: Matrix<f64> = &(&(&(&A6 * b[6]) + &(&A4 * b[4])) + &(&A2 * b[2])) + &(&I * b[0]);
+    return (U, V_high + V_low);
+}
+
+impl Matrix<f64> {
+    pub fn expm(&self) -> Result<Matrix<f64>, LinAlgError> {
+        check_square(self)?;
+        let norm: f64 = finite_norm_1(self)?;
+
+        for (m, theta) in THETA {
+            if norm <= theta {
+                let b: &[f64] = match m {
+                    3 => &PADE_3,
+                    5 => &PADE_5,
+                    7 => &PADE_7,
+                    _ => &PADE_9,
+                };
+                let (U, V) = pade_low_order(self, b);
+                return pade_quotient(&U, &V);
+            }
+        }
+
+        // exp(A) = exp(A / 2^s)^(2^s)
+        let s: i32 = (norm / THETA_13).log2().ceil().max(0.0) as i32;
+        let scaled: Matrix<f64> = self / 2.0_f64.powi(s);
+        let (U, V) = pade_13(&scaled);
+        let mut result: Matrix<f64> = pade_quotient(&U, &V)?;
+        for _ in 0..s {
+            result = &result * &result;
+        }
+        return Ok(result);
+    }
+
+    // Frechet derivative L(A, E) of expm at A in direction E, so that
+    // expm(A + h E) = expm(A) + h L(A, E) + O(h^2). It is read off the top-right block of
+    // expm([[A, E], [0, A]]).
+    pub fn expm_frechet(&self, E: &Matrix<f64>) -> Result<Matrix<f64>, LinAlgError> {
+        let n: usize = check_square(self)?;
+        if E.get_dim() != (n, n) {
+            return Err(LinAlgError::DimensionMismatch {
+                expected: (n, n),
+                found: E.get_dim(),
+            });
+        }
+
+        // L is linear in E, so rescale E to the size of A to avoid needless extra squarings
+        let norm_A: f64 = finite_norm_1(self)?;
+        let norm_E: f64 = finite_norm_1(E)?;
+        if norm_E == 0.0 {
+            return Ok(Matrix::<f64>::new((n, n)));
+        }
+        let scale: f64 = if norm_A > 0.0 {
+            norm_A / norm_E
+        } else {
+            1.0 / norm_E
+        };
+        let E_scaled: Matrix<f64> = E * scale;
+
+        let zero: Matrix<f64> = Matrix::<f64>::new((n, n));
+        let block: Matrix<f64> =
+            Matrix::<f64>::from_blocks(&[vec![self, &E_scaled], vec![&zero, self]]);
+        let L: Matrix<f64> = block.expm()?.get_block(0, n, (n, n));
+        return Ok(L / scale);
+    }
+
+    // Principal square root. A must have no eigenvalues on the closed negative real axis, else
+    // the iteration breaks down with LinAlgError::Singular or LinAlgError::NotConverged.
+    pub fn sqrtm(&self) -> Result<Matrix<f64>, LinAlgError> {
+        let n: usize = check_square(self)?;
+
+        // Denman-Beavers: Y -> A^(1/2) and Z -> A^(-1/2)
+        let mut Y: Matrix<f64> = self.clone();
+        let mut Z: Matrix<f64> = Matrix::<f64>::identity(n);
+        let tol: f64 = (n as f64).sqrt() * f64::EPSILON;
+        let mut last_change: f64 = f64::INFINITY;
+        for _ in 0..MAX_DB_ITER {
+            let Y_next: Matrix<f64> = (&Y + &Z.inverse()?) * 0.5;
+            let Z_next: Matrix<f64> = (&Z + &Y.inverse()?) * 0.5;
+
+            let change: f64 = (&Y_next - &Y).norm_1() / Y_next.norm_1();
+            Y = Y_next;
+            Z = Z_next;
+            // Stop at convergence, or once rounding errors keep the change from shrinking
+            if change <= tol || (change < 1e-8 && change >= last_change) {
+                return Ok(Y);
+            }
+            last_change = change;
+        }
+        return Err(LinAlgError::NotConverged);
+    }
+
+    // Principal logarithm, under the same conditions on the eigenvalues as sqrtm
+    pub fn logm(&self) -> Result<Matrix<f64>, LinAlgError> {
+        let n: usize = check_square(self)?;
+        finite_norm_1(self)?;
+        let I: Matrix<f64> = Matrix::<f64>::identity(n);
+
+        // log(A) = 2^k log(A^(1/2^k))
+        let mut X: Matrix<f64> = self.clone();
+        let mut k: i32 = 0;
+        while (&X - &I).norm_1() > LOG_PADE_RADIUS {
+            if k as usize == MAX_SQRT_STEPS {
+                return Err(LinAlgError::NotConverged);
+            }
+            X = X.sqrtm()?;
+            k += 1;
+        }
+
+        let E: Matrix<f64> = X - &I;
+        let mut L: Matrix<f64> = Matrix::<f64>::new((n, n));
+        for (t, w) in crate::glq_interval(0.0, 1.0, 4) {
+            let shifted: Matrix<f64> = &I + &(&E * t);
+            L += &(Factorization::lu(&shifted)?.solve(&E)? * w);
+        }
+        return Ok(L * 2.0_f64.powi(k));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rotation_generator(t: f64) -> Matrix<f64> {
+        return Matrix::from_rows(&[[0.0, -t], [t, 0.0]]);
+    }
+
+    fn rotation(t: f64) -> Matrix<f64> {
+        return Matrix::from_rows(&[[t.cos(), -t.sin()], [t.sin(), t.cos()]]);
+    }
+
+    fn example_matrix() -> Matrix<f64> {
+        return Matrix::from_rows(&[[4.0, 1.0, 0.0], [1.0, 3.0, 1.0], [0.0, 1.0, 2.0]]);
+    }
+
+    #[test]
+    fn test_expm() {
+        assert_eq!(
+            Matrix::<f64>::new((3, 3)).expm().unwrap(),
+            Matrix::<f64>::identity(3)
+        );
+
+        // Nilpotent: exp(N) = I + N
+        let N: Matrix<f64> = Matrix::from_rows(&[[0.0, 1.0], [0.0, 0.0]]);
+        crate::assert_matrix_approx_eq!(N.expm().unwrap(), &Matrix::<f64>::identity(2) + &N);
+
+        // Covers every Pade degree and the scaling and squaring branch
+        for t in [1e-3, 0.1, 0.5, 1.5, 4.0, 30.0] {
+            crate::assert_matrix_approx_eq!(
+                rotation_generator(t).expm().unwrap(),
+                rotation(t),
+                1e-12,
+                1e-12
+            );
+        }
+
+        let a: Matrix<f64> = example_matrix();
+        crate::assert_matrix_approx_eq!(
+            &a.expm().unwrap() * &(-&a).expm().unwrap(),
+            Matrix::<f64>::identity(3)
+        );
+        assert!(Matrix::<f64>::new((2, 3)).expm().is_err());
+    }
+
+    #[test]
+    fn test_expm_frechet() {
+        let a: Matrix<f64> = &example_matrix() * 0.5;
+        let e: Matrix<f64> =
+            Matrix::from_rows(&[[0.0, 1.0, 0.0], [-1.0, 0.0, 2.0], [0.5, 0.0, 1.0]]);
+        let L: Matrix<f64> = a.expm_frechet(&e).unwrap();
+
+        let h: f64 = 1e-5;
+        let central: Matrix<f64> =
+            (&(&a + &(&e * h)).expm().unwrap() - &(&a - &(&e * h)).expm().unwrap()) / (2.0 * h);
+        crate::assert_matrix_approx_eq!(L, central, 1e-6, 1e-6);
+
+        // For commuting A and E, L(A, E) = E exp(A)
+        let commuting: Matrix<f64> = &a * 2.0;
+        crate::assert_matrix_approx_eq!(
+            a.expm_frechet(&commuting).unwrap(),
+            &commuting * &a.expm().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sqrtm() {
+        let upper: Matrix<f64> = Matrix::from_rows(&[[4.0, 1.0], [0.0, 9.0]]);
+        crate::assert_matrix_approx_eq!(
+            upper.sqrtm().unwrap(),
+            Matrix::from_rows(&[[2.0, 0.2], [0.0, 3.0]])
+        );
+
+        let a: Matrix<f64> = example_matrix();
+        let root: Matrix<f64> = a.sqrtm().unwrap();
+        crate::assert_matrix_approx_eq!(&root * &root, a);
+
+        // -I has no real principal square root
+        assert!((-&Matrix::<f64>::identity(2)).sqrtm().is_err());
+    }
+
+    #[test]
+    fn test_logm() {
+        crate::assert_matrix_approx_eq!(
+            Matrix::<f64>::identity(3).logm().unwrap(),
+            Matrix::<f64>::new((3, 3))
+        );
+        crate::assert_matrix_approx_eq!(
+            rotation(1.0).logm().unwrap(),
+            rotation_generator(1.0),
+            1e-12,
+            1e-12
+        );
+
+        let a: Matrix<f64> = example_matrix();
+        crate::assert_matrix_approx_eq!(a.logm().unwrap().expm().unwrap(), a, 1e-12, 1e-12);
+        let small: Matrix<f64> = &a * 0.3;
+        crate::assert_matrix_approx_eq!(small.expm().unwrap().logm().unwrap(), small, 1e-12, 1e-12);
+    }
+
+    #[test]
+    fn test_not_finite() {
+        let mut a: Matrix<f64> = example_matrix();
+        a.set(1, 2, f64::INFINITY);
+        assert_eq!(a.expm().unwrap_err(), LinAlgError::NotFinite);
+        assert_eq!(a.logm().unwrap_err(), LinAlgError::NotFinite);
+        let b: Matrix<f64> = example_matrix();
+        assert_eq!(b.expm_frechet(&a).unwrap_err(), LinAlgError::NotFinite);
+        assert_eq!(a.expm_frechet(&b).unwrap_err(), LinAlgError::NotFinite);
+
+        a.set(1, 2, f64::NAN);
+        assert!(a.norm_1().is_finite());
+        assert_eq!(a.expm().unwrap_err(), LinAlgError::NotFinite);
+        assert_eq!(a.logm().unwrap_err(), LinAlgError::NotFinite);
+        assert_eq!(b.expm_frechet(&a).unwrap_err(), LinAlgError::NotFinite);
+
+        // Finite entries whose norm overflows
+        let huge: Matrix<f64> = Matrix::<f64>::ones((2, 2)) * f64::MAX;
+        assert_eq!(huge.expm().unwrap_err(), LinAlgError::NotFinite);
+    }
+}
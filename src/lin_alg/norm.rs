@@ -0,0 +1,194 @@
+/*
+Matrix and vector norms beyond the Frobenius norm provided by `Matrix::norm`.
+*/
+
+use super::mat::Matrix;
+
+impl Matrix<f64> {
+    // Maximum absolute column sum
+    pub fn norm_1(&self) -> f64 {
+        let (rows, cols) = self.get_dim();
+        let mut max_sum: f64 = 0.0;
+        for c in 0..cols {
+            let mut sum: f64 = 0.0;
+            for r in 0..rows {
+                sum += self.get(r, c).abs();
+            }
+            max_sum = max_sum.max(sum);
+        }
+        return max_sum;
+    }
+
+    // Maximum absolute row sum
+    pub fn norm_inf(&self) -> f64 {
+        let (rows, cols) = self.get_dim();
+        let mut max_sum: f64 = 0.0;
+        for r in 0..rows {
+            let mut sum: f64 = 0.0;
+            for c in 0..cols {
+                sum += self.get(r, c).abs();
+            }
+            max_sum = max_sum.max(sum);
+        }
+        return max_sum;
+    }
+
+    // Largest absolute entry
+    pub fn norm_max(&self) -> f64 {
+        let (rows, cols) = self.get_dim();
+        let mut max_abs: f64 = 0.0;
+        for r in 0..rows {
+            for c in 0..cols {
+                max_abs = max_abs.max(self.get(r, c).abs());
+            }
+        }
+        return max_abs;
+    }
+
+    // Spectral norm, i.e. the largest singular value
+    pub fn norm_2(&self) -> f64 {
+        return self.singular_values().first().copied().unwrap_or(0.0);
+    }
+
+    // Sum of the singular values
+    pub fn norm_nuclear(&self) -> f64 {
+        return self.singular_values().iter().sum();
+    }
+
+    // Vector p-norm of all entries, for p >= 1. p = f64::INFINITY gives the max-abs norm.
+    pub fn norm_p(&self, p: f64) -> f64 {
+        assert!(p >= 1.0, "p-norm requires p >= 1.");
+        if p == f64::INFINITY {
+            return self.norm_max();
+        }
+
+        // Scale by the largest entry to avoid overflow in |x|^p
+        let scale: f64 = self.norm_max();
+        if scale == 0.0 {
+            return 0.0;
+        }
+        let (rows, cols) = self.get_dim();
+        let mut sum: f64 = 0.0;
+        for r in 0..rows {
+            for c in 0..cols {
+                sum += (self.get(r, c).abs() / scale).powf(p);
+            }
+        }
+        return scale * sum.powf(1.0 / p);
+    }
+
+    // Singular values in descending order, computed with one-sided Jacobi rotations
+    pub fn singular_values(&self) -> Vec<f64> {
+        let (rows, cols) = self.get_dim();
+
+        // Work on the orientation with at least as many rows as columns
+        let (m, n) = if rows >= cols {
+            (rows, cols)
+        } else {
+            (cols, rows)
+        };
+        let mut u: Vec<Vec<f64>> = vec![vec![0.0; m]; n];
+        for r in 0..rows {
+            for c in 0..cols {
+                if rows >= cols {
+                    u[c][r] = self.get(r, c);
+                } else {
+                    u[r][c] = self.get(r, c);
+                }
+            }
+        }
+
+        const MAX_SWEEPS: usize = 60;
+        for _ in 0..MAX_SWEEPS {
+            let mut rotated: bool = false;
+            for p in 0..n {
+                for q in (p + 1)..n {
+                    let alpha: f64 = u[p].iter().map(|x| x * x).sum();
+                    let beta: f64 = u[q].iter().map(|x| x * x).sum();
+                    let gamma: f64 = u[p].iter().zip(u[q].iter()).map(|(x, y)| x * y).sum();
+                    if gamma.abs() <= f64::EPSILON * (alpha * beta).sqrt() || gamma == 0.0 {
+                        continue;
+                    }
+                    rotated = true;
+
+                    let zeta: f64 = (beta - alpha) / (2.0 * gamma);
+                    let t: f64 = zeta.signum() / (zeta.abs() + (1.0 + zeta * zeta).sqrt());
+                    let cs: f64 = 1.0 / (1.0 + t * t).sqrt();
+                    let sn: f64 = cs * t;
+                    for k in 0..m {
+                        let (up, uq) = (u[p][k], u[q][k]);
+                        u[p][k] = cs * up - sn * uq;
+                        u[q][k] = sn * up + cs * uq;
+                    }
+                }
+            }
+            if !rotated {
+                break;
+            }
+        }
+
+        let mut sigma: Vec<f64> = u
+            .iter()
+            .map(|col| col.iter().map(|x| x * x).sum::<f64>().sqrt())
+            .collect();
+        sigma.sort_by(|a, b| b.total_cmp(a));
+        return sigma;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_matrix() -> Matrix<f64> {
+        let mut m: Matrix<f64> = Matrix::<f64>::new((2, 3));
+        m.set(0, 0, 1.0);
+        m.set(0, 1, -2.0);
+        m.set(0, 2, 3.0);
+        m.set(1, 0, -4.0);
+        m.set(1, 1, 5.0);
+        m.set(1, 2, -6.0);
+        return m;
+    }
+
+    #[test]
+    fn test_entrywise_norms() {
+        let m: Matrix<f64> = example_matrix();
+        assert_eq!(m.norm_1(), 9.0);
+        assert_eq!(m.norm_inf(), 15.0);
+        assert_eq!(m.norm_max(), 6.0);
+        assert!((m.norm_p(1.0) - 21.0).abs() < 1e-12);
+        assert!((m.norm_p(2.0) - m.norm()).abs() < 1e-12);
+        assert_eq!(m.norm_p(f64::INFINITY), 6.0);
+    }
+
+    #[test]
+    fn test_singular_value_norms() {
+        // Singular values of this matrix are 9.508032, 0.772869
+        let m: Matrix<f64> = example_matrix();
+        let sigma: Vec<f64> = m.singular_values();
+        assert_eq!(sigma.len(), 2);
+        assert!((sigma[0] - 9.508032000695723).abs() < 1e-10);
+        assert!((sigma[1] - 0.7728696356734837).abs() < 1e-10);
+
+        // Transposing does not change the singular values
+        let sigma_t: Vec<f64> = m.transpose().singular_values();
+        assert!((sigma[0] - sigma_t[0]).abs() < 1e-12);
+        assert!((sigma[1] - sigma_t[1]).abs() < 1e-12);
+
+        assert!((m.norm_2() - sigma[0]).abs() < 1e-12);
+        assert!((m.norm_nuclear() - (sigma[0] + sigma[1])).abs() < 1e-12);
+        // Frobenius norm is the 2-norm of the singular values
+        assert!((m.norm() - (sigma[0].powi(2) + sigma[1].powi(2)).sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_norm_2_diagonal() {
+        let mut m: Matrix<f64> = Matrix::<f64>::identity(3);
+        m.set(1, 1, -7.0);
+        m.set(2, 2, 0.5);
+        assert!((m.norm_2() - 7.0).abs() < 1e-12);
+        assert!((m.norm_nuclear() - 8.5).abs() < 1e-12);
+        assert_eq!(Matrix::<f64>::new((2, 2)).norm_2(), 0.0);
+    }
+}
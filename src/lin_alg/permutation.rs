@@ -0,0 +1,167 @@
+/*
+Row and column permutations, as produced by pivoted factorisations.
+*/
+
+use super::mat::Matrix;
+use super::Scalar;
+
+// A permutation of 0..n stored as an index map: applying it to the rows of a matrix places
+// row perm[i] of the input at row i of the result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Permutation {
+    perm: Vec<usize>,
+}
+
+impl Permutation {
+    pub fn identity(n: usize) -> Self {
+        return Self {
+            perm: (0..n).collect(),
+        };
+    }
+
+    pub fn from_vec(perm: Vec<usize>) -> Self {
+        let mut seen: Vec<bool> = vec![false; perm.len()];
+        for &p in &perm {
+            assert!(
+                p < perm.len() && !seen[p],
+                "Not a permutation of 0..{}.",
+                perm.len()
+            );
+            seen[p] = true;
+        }
+        return Self { perm };
+    }
+
+    pub fn len(&self) -> usize {
+        return self.perm.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.perm.is_empty();
+    }
+
+    pub fn get(&self, i: usize) -> usize {
+        return self.perm[i];
+    }
+
+    pub fn as_slice(&self) -> &[usize] {
+        return &self.perm;
+    }
+
+    // Exchanges entries i and j, i.e. composes with a row swap
+    pub fn swap(&mut self, i: usize, j: usize) {
+        self.perm.swap(i, j);
+    }
+
+    pub fn inverse(&self) -> Self {
+        let mut inv: Vec<usize> = vec![0; self.perm.len()];
+        for (i, &p) in self.perm.iter().enumerate() {
+            inv[p] = i;
+        }
+        return Self { perm: inv };
+    }
+
+    // +1 for an even permutation, -1 for an odd one
+    pub fn sign(&self) -> f64 {
+        let mut visited: Vec<bool> = vec![false; self.perm.len()];
+        let mut sign: f64 = 1.0;
+        for start in 0..self.perm.len() {
+            if visited[start] {
+                continue;
+            }
+            // A cycle of length L is made of L - 1 transpositions
+            let mut len: usize = 0;
+            let mut i: usize = start;
+            while !visited[i] {
+                visited[i] = true;
+                i = self.perm[i];
+                len += 1;
+            }
+            if len.is_multiple_of(2) {
+                sign = -sign;
+            }
+        }
+        return sign;
+    }
+
+    // Permutation matrix P such that P * A permutes the rows of A
+    pub fn to_matrix(&self) -> Matrix<f64> {
+        let n: usize = self.perm.len();
+        let mut result: Matrix<f64> = Matrix::<f64>::new((n, n));
+        for (i, &p) in self.perm.iter().enumerate() {
+            result.set(i, p, 1.0);
+        }
+        return result;
+    }
+}
+
+impl<T: Scalar> Matrix<T> {
+    pub fn permute_rows(&self, perm: &Permutation) -> Matrix<T> {
+        let (rows, cols) = self.get_dim();
+        assert_eq!(perm.len(), rows, "Permutation length must match row count.");
+        let mut result: Matrix<T> = Matrix::<T>::new_with_layout((rows, cols), self.get_layout());
+        for r in 0..rows {
+            for c in 0..cols {
+                result.set(r, c, self.get(perm.get(r), c));
+            }
+        }
+        return result;
+    }
+
+    pub fn permute_cols(&self, perm: &Permutation) -> Matrix<T> {
+        let (rows, cols) = self.get_dim();
+        assert_eq!(
+            perm.len(),
+            cols,
+            "Permutation length must match column count."
+        );
+        let mut result: Matrix<T> = Matrix::<T>::new_with_layout((rows, cols), self.get_layout());
+        for r in 0..rows {
+            for c in 0..cols {
+                result.set(r, c, self.get(r, perm.get(c)));
+            }
+        }
+        return result;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lin_alg::mat::Layout;
+
+    #[test]
+    fn test_permute_rows_and_cols() {
+        let m: Matrix<f64> =
+            Matrix::<f64>::from_vec((3, 2), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], Layout::RowMajor);
+        let p: Permutation = Permutation::from_vec(vec![2, 0, 1]);
+        let permuted: Matrix<f64> = m.permute_rows(&p);
+        assert_eq!(
+            permuted.clone().into_vec(),
+            vec![5.0, 6.0, 1.0, 2.0, 3.0, 4.0]
+        );
+        assert_eq!(permuted, &p.to_matrix() * &m);
+        assert_eq!(permuted.permute_rows(&p.inverse()), m);
+
+        let q: Permutation = Permutation::from_vec(vec![1, 0]);
+        assert_eq!(m.permute_cols(&q), &m * &q.to_matrix().transpose());
+    }
+
+    #[test]
+    fn test_permutation_sign() {
+        assert_eq!(Permutation::identity(4).sign(), 1.0);
+        let mut p: Permutation = Permutation::identity(4);
+        p.swap(0, 3);
+        assert_eq!(p.sign(), -1.0);
+        p.swap(1, 2);
+        assert_eq!(p.sign(), 1.0);
+        // A 3-cycle is even
+        assert_eq!(Permutation::from_vec(vec![1, 2, 0]).sign(), 1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not a permutation of 0..3.")]
+    fn test_invalid_permutation() {
+        Permutation::from_vec(vec![0, 2, 2]);
+    }
+}
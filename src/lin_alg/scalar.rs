@@ -0,0 +1,250 @@
+/*
+Element types of Matrix. The real Float types are Scalars, so matrices of dual numbers and Taylor
+series run the same generic code as matrices of f64, which alone is routed to the vectorised
+kernels. Var records square solves, inverses and log-determinants as one tape rule each instead
+of taping every step of the factorisation. Complex is not a Scalar, since norms and dot products
+here do not conjugate.
+*/
+
+use super::adjoint;
+use super::factorization::Factorization;
+use super::kernels;
+use super::mat::Matrix;
+use super::LinAlgError;
+use crate::auto_diff::DiffNum;
+use crate::float::Float;
+use crate::multi_dual::MultiDual;
+use crate::reverse::Var;
+use crate::taylor::Taylor;
+
+// Element type of a Matrix. The slice kernels default to plain loops that work for any Float,
+// and f64 overrides them with the vectorised kernels and the blocked gemm, so generic matrix code
+// keeps the fast paths for plain floats.
+pub trait Scalar: Float {
+    fn slice_add(a: &[Self], b: &[Self], out: &mut [Self]) {
+        for ((o, x), y) in out.iter_mut().zip(a).zip(b) {
+            *o = *x + *y;
+        }
+    }
+
+    fn slice_sub(a: &[Self], b: &[Self], out: &mut [Self]) {
+        for ((o, x), y) in out.iter_mut().zip(a).zip(b) {
+            *o = *x - *y;
+        }
+    }
+
+    fn slice_add_scalar(a: &[Self], s: Self, out: &mut [Self]) {
+        for (o, x) in out.iter_mut().zip(a) {
+            *o = *x + s;
+        }
+    }
+
+    fn slice_sub_scalar(a: &[Self], s: Self, out: &mut [Self]) {
+        for (o, x) in out.iter_mut().zip(a) {
+            *o = *x - s;
+        }
+    }
+
+    fn slice_scalar_sub(a: &[Self], s: Self, out: &mut [Self]) {
+        for (o, x) in out.iter_mut().zip(a) {
+            *o = s - *x;
+        }
+    }
+
+    fn slice_mul_scalar(a: &[Self], s: Self, out: &mut [Self]) {
+        for (o, x) in out.iter_mut().zip(a) {
+            *o = *x * s;
+        }
+    }
+
+    fn slice_div_scalar(a: &[Self], s: Self, out: &mut [Self]) {
+        for (o, x) in out.iter_mut().zip(a) {
+            *o = *x / s;
+        }
+    }
+
+    fn slice_add_assign(a: &mut [Self], b: &[Self]) {
+        for (o, y) in a.iter_mut().zip(b) {
+            *o += *y;
+        }
+    }
+
+    fn slice_sub_assign(a: &mut [Self], b: &[Self]) {
+        for (o, y) in a.iter_mut().zip(b) {
+            *o -= *y;
+        }
+    }
+
+    fn slice_rsub_assign(a: &mut [Self], b: &[Self]) {
+        for (o, y) in a.iter_mut().zip(b) {
+            *o = *y - *o;
+        }
+    }
+
+    fn slice_add_scalar_assign(a: &mut [Self], s: Self) {
+        for o in a.iter_mut() {
+            *o += s;
+        }
+    }
+
+    fn slice_sub_scalar_assign(a: &mut [Self], s: Self) {
+        for o in a.iter_mut() {
+            *o -= s;
+        }
+    }
+
+    fn slice_scalar_sub_assign(a: &mut [Self], s: Self) {
+        for o in a.iter_mut() {
+            *o = s - *o;
+        }
+    }
+
+    fn slice_mul_scalar_assign(a: &mut [Self], s: Self) {
+        for o in a.iter_mut() {
+            *o *= s;
+        }
+    }
+
+    fn slice_div_scalar_assign(a: &mut [Self], s: Self) {
+        for o in a.iter_mut() {
+            *o /= s;
+        }
+    }
+
+    fn slice_abs(a: &[Self], out: &mut [Self]) {
+        for (o, x) in out.iter_mut().zip(a) {
+            *o = x.abs();
+        }
+    }
+
+    fn slice_dot(a: &[Self], b: &[Self]) -> Self {
+        assert!(a.len() == b.len());
+        let mut sum: Self = Self::zero();
+        for (x, y) in a.iter().zip(b) {
+            sum += *x * *y;
+        }
+        return sum;
+    }
+
+    // C = alpha * A * B + beta * C, with the same conventions as lin_alg::gemm
+    fn gemm(alpha: Self, A: &Matrix<Self>, B: &Matrix<Self>, beta: Self, C: &mut Matrix<Self>) {
+        let (m, k) = A.get_dim();
+        let n: usize = B.get_dim().1;
+        assert_eq!(
+            k,
+            B.get_dim().0,
+            "Matrix dimensions must match for multiplication."
+        );
+        assert_eq!(
+            C.get_dim(),
+            (m, n),
+            "Output matrix has the wrong dimensions."
+        );
+        for r in 0..m {
+            for c in 0..n {
+                let mut sum: Self = Self::zero();
+                for i in 0..k {
+                    sum += A.get(r, i) * B.get(i, c);
+                }
+                // beta == 0 overwrites C, as in BLAS
+                let old: Self = if beta == Self::zero() {
+                    Self::zero()
+                } else {
+                    beta * C.get(r, c)
+                };
+                C.set(r, c, alpha * sum + old);
+            }
+        }
+    }
+
+    // Solver behind lin_solve, Matrix::inverse and Matrix::log_det. The defaults differentiate
+    // through the factorisation element by element.
+    fn lin_solve(A: &Matrix<Self>, B: &Matrix<Self>) -> Result<Matrix<Self>, LinAlgError> {
+        return Factorization::qr(A)?.solve(B);
+    }
+
+    fn inverse(A: &Matrix<Self>) -> Result<Matrix<Self>, LinAlgError> {
+        return Factorization::lu(A)?.solve(&Matrix::<Self>::identity(A.get_dim().0));
+    }
+
+    fn log_det(A: &Matrix<Self>) -> Result<Self, LinAlgError> {
+        return super::inverse::lu_log_det(A);
+    }
+}
+
+// Forwards trait kernels to the free f64 kernels
+macro_rules! forward_kernels {
+    (binary: $($b:ident => $bk:ident),*; scalar: $($u:ident => $uk:ident),*;
+     binary_assign: $($ba:ident => $bak:ident),*; scalar_assign: $($ua:ident => $uak:ident),*) => {
+        $(
+            fn $b(a: &[f64], b: &[f64], out: &mut [f64]) {
+                kernels::$bk(a, b, out);
+            }
+        )*
+        $(
+            fn $u(a: &[f64], s: f64, out: &mut [f64]) {
+                kernels::$uk(a, s, out);
+            }
+        )*
+        $(
+            fn $ba(a: &mut [f64], b: &[f64]) {
+                kernels::$bak(a, b);
+            }
+        )*
+        $(
+            fn $ua(a: &mut [f64], s: f64) {
+                kernels::$uak(a, s);
+            }
+        )*
+    };
+}
+
+impl Scalar for f64 {
+    forward_kernels!(
+        binary: slice_add => add, slice_sub => sub;
+        scalar: slice_add_scalar => add_scalar, slice_sub_scalar => sub_scalar,
+            slice_scalar_sub => scalar_sub, slice_mul_scalar => mul_scalar,
+            slice_div_scalar => div_scalar;
+        binary_assign: slice_add_assign => add_assign, slice_sub_assign => sub_assign,
+            slice_rsub_assign => rsub_assign;
+        scalar_assign: slice_add_scalar_assign => add_scalar_assign,
+            slice_sub_scalar_assign => sub_scalar_assign,
+            slice_scalar_sub_assign => scalar_sub_assign,
+            slice_mul_scalar_assign => mul_scalar_assign,
+            slice_div_scalar_assign => div_scalar_assign
+    );
+
+    fn slice_abs(a: &[f64], out: &mut [f64]) {
+        kernels::abs(a, out);
+    }
+
+    fn slice_dot(a: &[f64], b: &[f64]) -> f64 {
+        return kernels::dot(a, b);
+    }
+
+    fn gemm(alpha: f64, A: &Matrix<f64>, B: &Matrix<f64>, beta: f64, C: &mut Matrix<f64>) {
+        super::gemm(alpha, A, B, beta, C);
+    }
+}
+
+impl Scalar for f32 {}
+
+impl<T: Float> Scalar for DiffNum<T> {}
+
+impl<T: Float, const K: usize> Scalar for Taylor<T, K> {}
+
+impl<T: Float, const N: usize> Scalar for MultiDual<T, N> {}
+
+impl Scalar for Var<'_> {
+    fn lin_solve(A: &Matrix<Self>, B: &Matrix<Self>) -> Result<Matrix<Self>, LinAlgError> {
+        return adjoint::record_lin_solve(A, B);
+    }
+
+    fn inverse(A: &Matrix<Self>) -> Result<Matrix<Self>, LinAlgError> {
+        return adjoint::record_inverse(A);
+    }
+
+    fn log_det(A: &Matrix<Self>) -> Result<Self, LinAlgError> {
+        return adjoint::record_log_det(A);
+    }
+}
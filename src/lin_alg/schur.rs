@@ -0,0 +1,292 @@
+/*
+Real Schur decomposition A = U * T * U^T with orthogonal U and upper quasi-triangular T. Real
+eigenvalues of A appear as 1x1 diagonal blocks of T and complex conjugate pairs as 2x2 blocks.
+Computed by Householder reduction to Hessenberg form followed by the Francis double shift QR
+iteration (Golub and Van Loan, Algorithms 7.4.2 and 7.5.2).
+*/
+
+use super::mat::Matrix;
+use super::LinAlgError;
+
+// QR sweeps allowed per eigenvalue before giving up
+const MAX_SWEEPS_PER_EIGENVALUE: usize = 60;
+
+// Householder vector v and beta with (I - beta v v^T) x = -sign(x_0) ||x|| e_1. Returns None
+// when x is already zero.
+fn householder(x: &[f64]) -> Option<(Vec<f64>, f64)> {
+    let norm: f64 = x.iter().map(|xi| xi * xi).sum::<f64>().sqrt();
+    if norm == 0.0 {
+        return None;
+    }
+    let mut v: Vec<f64> = x.to_vec();
+    v[0] += if x[0] >= 0.0 { norm } else { -norm };
+    let v_dot: f64 = v.iter().map(|vi| vi * vi).sum();
+    return Some((v, 2.0 / v_dot));
+}
+
+// M = (I - beta v v^T) M restricted to rows r0..r0 + v.len() and the given columns
+fn reflect_rows(
+    M: &mut Matrix<f64>,
+    v: &[f64],
+    beta: f64,
+    r0: usize,
+    cols: std::ops::Range<usize>,
+) {
+    for c in cols {
+        let mut s: f64 = 0.0;
+        for (j, vj) in v.iter().enumerate() {
+            s += vj * M.get(r0 + j, c);
+        }
+        for (j, vj) in v.iter().enumerate() {
+            M.set(r0 + j, c, M.get(r0 + j, c) - beta * s * vj);
+        }
+    }
+}
+
+// M = M (I - beta v v^T) restricted to columns c0..c0 + v.len() and the given rows
+fn reflect_cols(
+    M: &mut Matrix<f64>,
+    v: &[f64],
+    beta: f64,
+    c0: usize,
+    rows: std::ops::Range<usize>,
+) {
+    for r in rows {
+        let mut s: f64 = 0.0;
+        for (j, vj) in v.iter().enumerate() {
+            s += M.get(r, c0 + j) * vj;
+        }
+        for (j, vj) in v.iter().enumerate() {
+            M.set(r, c0 + j, M.get(r, c0 + j) - beta * s * vj);
+        }
+    }
+}
+
+// Returns (U, T)
+pub fn real_schur(A: &Matrix<f64>) -> Result<(Matrix<f64>, Matrix<f64>), LinAlgError> {
+    let n: usize = A.get_dim().0;
+    if A.get_dim().1 != n {
+        return Err(LinAlgError::DimensionMismatch {
+            expected: (n, n),
+            found: A.get_dim(),
+        });
+    }
+    let mut T: Matrix<f64> = A.clone();
+    let mut U: Matrix<f64> = Matrix::<f64>::identity(n);
+
+    // Reduce to upper Hessenberg form
+    for k in 0..n.saturating_sub(2) {
+        let x: Vec<f64> = ((k + 1)..n).map(|i| T.get(i, k)).collect();
+        if let Some((v, beta)) = householder(&x) {
+            reflect_rows(&mut T, &v, beta, k + 1, k..n);
+            reflect_cols(&mut T, &v, beta, k + 1, 0..n);
+            reflect_cols(&mut U, &v, beta, k + 1, 0..n);
+        }
+        for i in (k + 2)..n {
+            T.set(i, k, 0.0);
+        }
+    }
+
+    // Francis iteration on the active window lo..=hi, deflating from the bottom
+    let norm: f64 = T.norm_max();
+    let mut hi: usize = n.saturating_sub(1);
+    let mut sweeps: usize = 0;
+    while hi > 0 {
+        // Find the start of the unreduced block ending at hi
+        let mut lo: usize = hi;
+        while lo > 0 {
+            let mut scale: f64 = T.get(lo - 1, lo - 1).abs() + T.get(lo, lo).abs();
+            if scale == 0.0 {
+                scale = norm;
+            }
+            if T.get(lo, lo - 1).abs() <= f64::EPSILON * scale {
+                T.set(lo, lo - 1, 0.0);
+                break;
+            }
+            lo -= 1;
+        }
+
+        if lo == hi {
+            hi -= 1;
+            sweeps = 0;
+            continue;
+        }
+        if lo + 1 == hi {
+            hi = hi.saturating_sub(2);
+            sweeps = 0;
+            continue;
+        }
+
+        sweeps += 1;
+        if sweeps > MAX_SWEEPS_PER_EIGENVALUE {
+            return Err(LinAlgError::NotConverged);
+        }
+
+        // Shifts are the eigenvalues of the trailing 2x2 block, except for occasional
+        // exceptional shifts that break cycles
+        let (s, t) = if sweeps.is_multiple_of(10) {
+            let w: f64 = T.get(hi, hi - 1).abs() + T.get(hi - 1, hi - 2).abs();
+            (1.5 * w, w * w)
+        } else {
+            (
+                T.get(hi - 1, hi - 1) + T.get(hi, hi),
+                T.get(hi - 1, hi - 1) * T.get(hi, hi) - T.get(hi - 1, hi) * T.get(hi, hi - 1),
+            )
+        };
+
+        // First column of (T - s1 I)(T - s2 I), chased down the diagonal as a bulge
+        let mut x: f64 = T.get(lo, lo) * T.get(lo, lo) + T.get(lo, lo + 1) * T.get(lo + 1, lo)
+            - s * T.get(lo, lo)
+            + t;
+        let mut y: f64 = T.get(lo + 1, lo) * (T.get(lo, lo) + T.get(lo + 1, lo + 1) - s);
+        let mut z: f64 = T.get(lo + 1, lo) * T.get(lo + 2, lo + 1);
+        for k in lo..(hi - 1) {
+            if let Some((v, beta)) = householder(&[x, y, z]) {
+                let c0: usize = if k > lo { k - 1 } else { lo };
+                reflect_rows(&mut T, &v, beta, k, c0..n);
+                reflect_cols(&mut T, &v, beta, k, 0..(hi + 1).min(k + 4));
+                reflect_cols(&mut U, &v, beta, k, 0..n);
+            }
+            x = T.get(k + 1, k);
+            y = T.get(k + 2, k);
+            if k + 3 <= hi {
+                z = T.get(k + 3, k);
+            }
+        }
+        if let Some((v, beta)) = householder(&[x, y]) {
+            reflect_rows(&mut T, &v, beta, hi - 1, (hi - 2)..n);
+            reflect_cols(&mut T, &v, beta, hi - 1, 0..(hi + 1));
+            reflect_cols(&mut U, &v, beta, hi - 1, 0..n);
+        }
+    }
+
+    // Clear rounding residue below the quasi-triangular structure
+    for c in 0..n {
+        for r in (c + 2)..n {
+            T.set(r, c, 0.0);
+        }
+    }
+    for (start, size) in diagonal_blocks(&T) {
+        if size == 2 {
+            split_real_pair(&mut T, &mut U, start);
+        }
+    }
+    return Ok((U, T));
+}
+
+// Triangularises the 2x2 diagonal block at (i, i) if its eigenvalues are real, by a reflection
+// that maps the first basis vector onto an eigenvector of the block
+fn split_real_pair(T: &mut Matrix<f64>, U: &mut Matrix<f64>, i: usize) {
+    let n: usize = T.get_dim().0;
+    let (a, b) = (T.get(i, i), T.get(i, i + 1));
+    let (c, d) = (T.get(i + 1, i), T.get(i + 1, i + 1));
+    let disc: f64 = (a - d) * (a - d) + 4.0 * b * c;
+    if disc < 0.0 {
+        return;
+    }
+
+    // Eigenvalue furthest from the block's mean, which avoids cancellation
+    let half_gap: f64 = 0.5 * (a - d);
+    let root: f64 = 0.5 * disc.sqrt();
+    let lambda: f64 = 0.5 * (a + d) + if half_gap >= 0.0 { root } else { -root };
+    let x1: [f64; 2] = [b, lambda - a];
+    let x2: [f64; 2] = [lambda - d, c];
+    let x: [f64; 2] = if x1[0].hypot(x1[1]) >= x2[0].hypot(x2[1]) {
+        x1
+    } else {
+        x2
+    };
+
+    if let Some((v, beta)) = householder(&x) {
+        reflect_rows(T, &v, beta, i, 0..n);
+        reflect_cols(T, &v, beta, i, 0..n);
+        reflect_cols(U, &v, beta, i, 0..n);
+    }
+    T.set(i + 1, i, 0.0);
+}
+
+// Start index and size (1 or 2) of each diagonal block of a quasi-triangular matrix
+pub(crate) fn diagonal_blocks(T: &Matrix<f64>) -> Vec<(usize, usize)> {
+    let n: usize = T.get_dim().0;
+    let mut blocks: Vec<(usize, usize)> = Vec::new();
+    let mut i: usize = 0;
+    while i < n {
+        if i + 1 < n && T.get(i + 1, i) != 0.0 {
+            blocks.push((i, 2));
+            i += 2;
+        } else {
+            blocks.push((i, 1));
+            i += 1;
+        }
+    }
+    return blocks;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lin_alg::mat::Layout;
+
+    fn check_schur(A: &Matrix<f64>) {
+        let n: usize = A.get_dim().0;
+        let (U, T) = real_schur(A).unwrap();
+        crate::assert_matrix_approx_eq!(&U.transpose() * &U, Matrix::<f64>::identity(n));
+        crate::assert_matrix_approx_eq!(&(&U * &T) * &U.transpose(), A.clone(), 1e-10, 1e-10);
+
+        // Quasi-triangular, with 2x2 blocks only for complex conjugate pairs
+        for (start, size) in diagonal_blocks(&T) {
+            if size == 2 {
+                let (a, b) = (T.get(start, start), T.get(start, start + 1));
+                let (c, d) = (T.get(start + 1, start), T.get(start + 1, start + 1));
+                assert!((a - d).powi(2) + 4.0 * b * c < 0.0);
+            }
+            if start + size < n {
+                assert_eq!(T.get(start + size, start + size - 1), 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_real_schur() {
+        // Eigenvalues 1 +- 2i, 3 and -1
+        let A: Matrix<f64> = Matrix::<f64>::from_vec(
+            (4, 4),
+            vec![
+                1.0, -2.0, 0.5, 1.0, 2.0, 1.0, -1.0, 0.0, 0.0, 0.0, 3.0, 2.0, 0.0, 0.0, 0.0, -1.0,
+            ],
+            Layout::RowMajor,
+        );
+        // Hide the structure behind an orthogonal similarity
+        let (Q, _) = crate::lin_alg::qr_decomposition(&Matrix::<f64>::from_vec(
+            (4, 4),
+            vec![
+                2.0, 1.0, 0.0, 1.0, -1.0, 3.0, 1.0, 0.0, 0.5, 0.0, 2.0, 1.0, 1.0, 1.0, -1.0, 4.0,
+            ],
+            Layout::RowMajor,
+        ));
+        check_schur(&(&(&Q * &A) * &Q.transpose()));
+
+        // Symmetric input gives a diagonal T
+        let S: Matrix<f64> = Matrix::<f64>::from_vec(
+            (3, 3),
+            vec![4.0, 1.0, 0.0, 1.0, 3.0, 1.0, 0.0, 1.0, 2.0],
+            Layout::RowMajor,
+        );
+        check_schur(&S);
+        let (_, T) = real_schur(&S).unwrap();
+        let trace: f64 = T.trace();
+        assert!((trace - 9.0).abs() < 1e-12);
+
+        // Dense non-symmetric matrix with a mix of real and complex eigenvalues
+        let mut D: Matrix<f64> = Matrix::<f64>::new((7, 7));
+        for r in 0..7 {
+            for c in 0..7 {
+                D.set(r, c, ((3 * r + 5 * c * c + 1) % 11) as f64 - 5.0);
+            }
+        }
+        check_schur(&D);
+
+        check_schur(&Matrix::<f64>::new((3, 3)));
+        check_schur(&Matrix::<f64>::identity(1));
+    }
+}
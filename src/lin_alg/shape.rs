@@ -0,0 +1,166 @@
+/*
+Shape manipulation and structural parts of a matrix: reshaping, row and column swaps,
+triangular parts and the diagonal.
+*/
+
+use super::mat::Matrix;
+use super::Scalar;
+
+impl<T: Scalar> Matrix<T> {
+    // Reinterprets the entries, read in row-major order, as a matrix of a new shape
+    pub fn reshape(&self, dim: (usize, usize)) -> Matrix<T> {
+        let (rows, cols) = self.get_dim();
+        assert_eq!(
+            dim.0 * dim.1,
+            rows * cols,
+            "Reshape must preserve the number of entries."
+        );
+        let mut result: Matrix<T> = Matrix::<T>::new(dim);
+        for r in 0..rows {
+            for c in 0..cols {
+                let idx: usize = r * cols + c;
+                result.set(idx / dim.1, idx % dim.1, self.get(r, c));
+            }
+        }
+        return result;
+    }
+
+    // All entries in row-major order as a column vector
+    pub fn flatten(&self) -> Matrix<T> {
+        let (rows, cols) = self.get_dim();
+        return self.reshape((rows * cols, 1));
+    }
+
+    pub fn swap_rows(&mut self, i: usize, j: usize) {
+        for c in 0..self.get_dim().1 {
+            let tmp: T = self.get(i, c);
+            self.set(i, c, self.get(j, c));
+            self.set(j, c, tmp);
+        }
+    }
+
+    pub fn swap_cols(&mut self, i: usize, j: usize) {
+        for r in 0..self.get_dim().0 {
+            let tmp: T = self.get(r, i);
+            self.set(r, i, self.get(r, j));
+            self.set(r, j, tmp);
+        }
+    }
+
+    // Copy with every entry below the main diagonal set to zero
+    pub fn upper_triangular(&self) -> Matrix<T> {
+        let mut result: Matrix<T> = self.clone();
+        let (rows, cols) = self.get_dim();
+        for r in 0..rows {
+            for c in 0..r.min(cols) {
+                result.set(r, c, T::zero());
+            }
+        }
+        return result;
+    }
+
+    // Copy with every entry above the main diagonal set to zero
+    pub fn lower_triangular(&self) -> Matrix<T> {
+        let mut result: Matrix<T> = self.clone();
+        let (rows, cols) = self.get_dim();
+        for r in 0..rows {
+            for c in (r + 1)..cols {
+                result.set(r, c, T::zero());
+            }
+        }
+        return result;
+    }
+
+    // Main diagonal, of length min(rows, cols)
+    pub fn diagonal(&self) -> Vec<T> {
+        let (rows, cols) = self.get_dim();
+        return (0..rows.min(cols)).map(|i| self.get(i, i)).collect();
+    }
+
+    pub fn set_diagonal(&mut self, diag: &[T]) {
+        let (rows, cols) = self.get_dim();
+        assert_eq!(
+            diag.len(),
+            rows.min(cols),
+            "Diagonal length must be min(rows, cols)."
+        );
+        for (i, &d) in diag.iter().enumerate() {
+            self.set(i, i, d);
+        }
+    }
+
+    pub fn trace(&self) -> T {
+        return self
+            .diagonal()
+            .into_iter()
+            .fold(T::zero(), |acc, d| acc + d);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lin_alg::mat::Layout;
+
+    fn example_matrix() -> Matrix<f64> {
+        return Matrix::<f64>::from_vec(
+            (2, 3),
+            vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0],
+            Layout::RowMajor,
+        );
+    }
+
+    #[test]
+    fn test_reshape_and_flatten() {
+        let m: Matrix<f64> = example_matrix();
+        let reshaped: Matrix<f64> = m.reshape((3, 2));
+        assert_eq!(reshaped.get(1, 0), 3.0);
+        assert_eq!(reshaped.get(2, 1), 6.0);
+        assert_eq!(reshaped.reshape((2, 3)), m);
+
+        // Reading order does not depend on the storage layout
+        let flat: Matrix<f64> = m.to_layout(Layout::ColMajor).flatten();
+        assert_eq!(flat.get_dim(), (6, 1));
+        assert_eq!(flat.into_vec(), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn test_swap_rows_and_cols() {
+        let mut m: Matrix<f64> = example_matrix();
+        m.swap_rows(0, 1);
+        assert_eq!(m.clone().into_vec(), vec![4.0, 5.0, 6.0, 1.0, 2.0, 3.0]);
+        m.swap_cols(0, 2);
+        assert_eq!(m.into_vec(), vec![6.0, 5.0, 4.0, 3.0, 2.0, 1.0]);
+    }
+
+    #[test]
+    fn test_triangular_parts() {
+        let m: Matrix<f64> = Matrix::<f64>::ones((3, 3));
+        let upper: Matrix<f64> = m.upper_triangular();
+        let lower: Matrix<f64> = m.lower_triangular();
+        assert_eq!(&upper + &lower, &m + &Matrix::<f64>::identity(3));
+        assert_eq!(upper.get(2, 0), 0.0);
+        assert_eq!(upper.get(0, 2), 1.0);
+        assert_eq!(lower.get(0, 2), 0.0);
+
+        let wide: Matrix<f64> = example_matrix();
+        assert_eq!(
+            wide.upper_triangular().into_vec(),
+            vec![1.0, 2.0, 3.0, 0.0, 5.0, 6.0]
+        );
+        assert_eq!(
+            wide.lower_triangular().into_vec(),
+            vec![1.0, 0.0, 0.0, 4.0, 5.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn test_diagonal_and_trace() {
+        let mut m: Matrix<f64> = example_matrix();
+        assert_eq!(m.diagonal(), vec![1.0, 5.0]);
+        assert_eq!(m.trace(), 6.0);
+        m.set_diagonal(&[-1.0, -2.0]);
+        assert_eq!(m.diagonal(), vec![-1.0, -2.0]);
+        assert_eq!(m.trace(), -3.0);
+    }
+}
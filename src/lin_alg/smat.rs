@@ -0,0 +1,596 @@
+/*
+Stack-allocated matrices with dimensions fixed at compile time.
+
+Mirrors the `Matrix<f64>` API for small sizes such as 2x2 to 4x4 geometry, where heap allocation
+and runtime dimension checks would dominate the cost. Mismatched dimensions in products and
+sums are rejected by the compiler.
+*/
+
+use super::mat::Matrix;
+use super::LinAlgError;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SMatrix<const R: usize, const C: usize> {
+    data: [[f64; C]; R],
+}
+
+impl<const R: usize, const C: usize> SMatrix<R, C> {
+    // Basic constructor, getters and setters
+    pub fn new() -> Self {
+        return Self {
+            data: [[0.0; C]; R],
+        };
+    }
+
+    pub fn from_rows(rows: [[f64; C]; R]) -> Self {
+        return Self { data: rows };
+    }
+
+    pub fn ones() -> Self {
+        return Self {
+            data: [[1.0; C]; R],
+        };
+    }
+
+    pub fn set(&mut self, r: usize, c: usize, val: f64) {
+        if r >= R || c >= C {
+            panic!("Index out of bounds.");
+        }
+        self.data[r][c] = val;
+    }
+
+    pub fn get(&self, r: usize, c: usize) -> f64 {
+        if r >= R || c >= C {
+            panic!("Index out of bounds.");
+        }
+        return self.data[r][c];
+    }
+
+    pub fn get_dim(&self) -> (usize, usize) {
+        return (R, C);
+    }
+
+    // Matrix specific mathematical operations
+    pub fn transpose(&self) -> SMatrix<C, R> {
+        let mut result: SMatrix<C, R> = SMatrix::new();
+        for r in 0..R {
+            for c in 0..C {
+                result.data[c][r] = self.data[r][c];
+            }
+        }
+        return result;
+    }
+
+    pub fn dot_product(&self, rhs: &Self) -> f64 {
+        let mut sum: f64 = 0.0;
+        for r in 0..R {
+            for c in 0..C {
+                sum += self.data[r][c] * rhs.data[r][c];
+            }
+        }
+        return sum;
+    }
+
+    pub fn norm(&self) -> f64 {
+        return self.dot_product(self).sqrt();
+    }
+
+    pub fn abs(&self) -> Self {
+        return self.map(|x| x.abs());
+    }
+
+    // Norms as in lin_alg::norm. The spectral and nuclear norms need the singular values, which
+    // are computed on the heap-allocated copy.
+    pub fn norm_1(&self) -> f64 {
+        return (0..C)
+            .map(|c| (0..R).map(|r| self.data[r][c].abs()).sum())
+            .fold(0.0, f64::max);
+    }
+
+    pub fn norm_inf(&self) -> f64 {
+        return self
+            .data
+            .iter()
+            .map(|row| row.iter().map(|x| x.abs()).sum())
+            .fold(0.0, f64::max);
+    }
+
+    pub fn norm_max(&self) -> f64 {
+        return self
+            .data
+            .iter()
+            .flatten()
+            .fold(0.0, |acc, x| acc.max(x.abs()));
+    }
+
+    pub fn norm_2(&self) -> f64 {
+        return Matrix::from(*self).norm_2();
+    }
+
+    pub fn norm_nuclear(&self) -> f64 {
+        return Matrix::from(*self).norm_nuclear();
+    }
+
+    pub fn norm_p(&self, p: f64) -> f64 {
+        assert!(p >= 1.0, "p-norm requires p >= 1.");
+        if p == f64::INFINITY {
+            return self.norm_max();
+        }
+
+        // Scale by the largest entry to avoid overflow in |x|^p
+        let scale: f64 = self.norm_max();
+        if scale == 0.0 {
+            return 0.0;
+        }
+        let sum: f64 = self
+            .data
+            .iter()
+            .flatten()
+            .map(|x| (x.abs() / scale).powf(p))
+            .sum();
+        return scale * sum.powf(1.0 / p);
+    }
+
+    // Approximate comparisons
+    pub fn approx_eq(&self, other: &Self, abs_tol: f64, rel_tol: f64) -> bool {
+        return self
+            .data
+            .iter()
+            .flatten()
+            .zip(other.data.iter().flatten())
+            .all(|(a, b)| crate::approx::approx_eq_f64(*a, *b, abs_tol, rel_tol));
+    }
+
+    pub fn ulps_eq(&self, other: &Self, max_ulps: u64) -> bool {
+        return self
+            .data
+            .iter()
+            .flatten()
+            .zip(other.data.iter().flatten())
+            .all(|(a, b)| crate::approx::ulps_eq_f64(*a, *b, max_ulps));
+    }
+
+    fn map(&self, f: impl Fn(f64) -> f64) -> Self {
+        let mut result: Self = *self;
+        result.data.iter_mut().flatten().for_each(|x| *x = f(*x));
+        return result;
+    }
+
+    fn zip_map(&self, other: &Self, f: impl Fn(f64, f64) -> f64) -> Self {
+        let mut result: Self = *self;
+        for r in 0..R {
+            for c in 0..C {
+                result.data[r][c] = f(self.data[r][c], other.data[r][c]);
+            }
+        }
+        return result;
+    }
+}
+
+impl<const N: usize> SMatrix<N, N> {
+    pub fn identity() -> Self {
+        let mut mat: Self = Self::new();
+        for i in 0..N {
+            mat.data[i][i] = 1.0;
+        }
+        return mat;
+    }
+
+    // Rejects det as singular when it is negligible next to the volume of the rows,
+    // |det| <= N * eps * prod ||row_i||_2, where Hadamard's inequality bounds |det| by that
+    // product. Like the pivot test of Matrix::inverse it is relative, so scaling A does not
+    // change the verdict and nearly singular input fails in both APIs.
+    fn invertible_det(&self, det: f64) -> Result<f64, LinAlgError> {
+        let volume: f64 = self
+            .data
+            .iter()
+            .map(|row| row.iter().map(|x| x * x).sum::<f64>().sqrt())
+            .product();
+        if det.abs() <= N as f64 * f64::EPSILON * volume {
+            return Err(LinAlgError::Singular);
+        }
+        return Ok(det);
+    }
+}
+
+impl<const R: usize, const C: usize> Default for SMatrix<R, C> {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+// Closed-form determinants and inverses for small square matrices
+impl SMatrix<1, 1> {
+    pub fn det(&self) -> f64 {
+        return self.data[0][0];
+    }
+
+    pub fn inverse(&self) -> Result<Self, LinAlgError> {
+        let det: f64 = self.invertible_det(self.det())?;
+        return Ok(Self::from_rows([[1.0 / det]]));
+    }
+}
+
+impl SMatrix<2, 2> {
+    pub fn det(&self) -> f64 {
+        let m = &self.data;
+        return m[0][0] * m[1][1] - m[0][1] * m[1][0];
+    }
+
+    pub fn inverse(&self) -> Result<Self, LinAlgError> {
+        let det: f64 = self.invertible_det(self.det())?;
+        let m = &self.data;
+        let adj: Self = Self::from_rows([[m[1][1], -m[0][1]], [-m[1][0], m[0][0]]]);
+        return Ok(adj * (1.0 / det));
+    }
+}
+
+impl SMatrix<3, 3> {
+    pub fn det(&self) -> f64 {
+        let m = &self.data;
+        return m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+    }
+
+    pub fn inverse(&self) -> Result<Self, LinAlgError> {
+        let det: f64 = self.invertible_det(self.det())?;
+        let m = &self.data;
+        // Adjugate: transposed matrix of cofactors
+        let adj: Self = Self::from_rows([
+            [
+                m[1][1] * m[2][2] - m[1][2] * m[2][1],
+                m[0][2] * m[2][1] - m[0][1] * m[2][2],
+                m[0][1] * m[1][2] - m[0][2] * m[1][1],
+            ],
+            [
+                m[1][2] * m[2][0] - m[1][0] * m[2][2],
+                m[0][0] * m[2][2] - m[0][2] * m[2][0],
+                m[0][2] * m[1][0] - m[0][0] * m[1][2],
+            ],
+            [
+                m[1][0] * m[2][1] - m[1][1] * m[2][0],
+                m[0][1] * m[2][0] - m[0][0] * m[2][1],
+                m[0][0] * m[1][1] - m[0][1] * m[1][0],
+            ],
+        ]);
+        return Ok(adj * (1.0 / det));
+    }
+}
+
+impl SMatrix<4, 4> {
+    // 2x2 sub-determinants of the top and bottom row pairs, shared by det and inverse
+    fn sub_dets(&self) -> ([f64; 6], [f64; 6]) {
+        let m = &self.data;
+        let s: [f64; 6] = [
+            m[0][0] * m[1][1] - m[1][0] * m[0][1],
+            m[0][0] * m[1][2] - m[1][0] * m[0][2],
+            m[0][0] * m[1][3] - m[1][0] * m[0][3],
+            m[0][1] * m[1][2] - m[1][1] * m[0][2],
+            m[0][1] * m[1][3] - m[1][1] * m[0][3],
+            m[0][2] * m[1][3] - m[1][2] * m[0][3],
+        ];
+        let c: [f64; 6] = [
+            m[2][0] * m[3][1] - m[3][0] * m[2][1],
+            m[2][0] * m[3][2] - m[3][0] * m[2][2],
+            m[2][0] * m[3][3] - m[3][0] * m[2][3],
+            m[2][1] * m[3][2] - m[3][1] * m[2][2],
+            m[2][1] * m[3][3] - m[3][1] * m[2][3],
+            m[2][2] * m[3][3] - m[3][2] * m[2][3],
+        ];
+        return (s, c);
+    }
+
+    pub fn det(&self) -> f64 {
+        let (s, c) = self.sub_dets();
+        return s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0];
+    }
+
+    pub fn inverse(&self) -> Result<Self, LinAlgError> {
+        let det: f64 = self.invertible_det(self.det())?;
+        let (s, c) = self.sub_dets();
+        let m = &self.data;
+        let adj: Self = Self::from_rows([
+            [
+                m[1][1] * c[5] - m[1][2] * c[4] + m[1][3] * c[3],
+                -m[0][1] * c[5] + m[0][2] * c[4] - m[0][3] * c[3],
+                m[3][1] * s[5] - m[3][2] * s[4] + m[3][3] * s[3],
+                -m[2][1] * s[5] + m[2][2] * s[4] - m[2][3] * s[3],
+            ],
+            [
+                -m[1][0] * c[5] + m[1][2] * c[2] - m[1][3] * c[1],
+                m[0][0] * c[5] - m[0][2] * c[2] + m[0][3] * c[1],
+                -m[3][0] * s[5] + m[3][2] * s[2] - m[3][3] * s[1],
+                m[2][0] * s[5] - m[2][2] * s[2] + m[2][3] * s[1],
+            ],
+            [
+                m[1][0] * c[4] - m[1][1] * c[2] + m[1][3] * c[0],
+                -m[0][0] * c[4] + m[0][1] * c[2] - m[0][3] * c[0],
+                m[3][0] * s[4] - m[3][1] * s[2] + m[3][3] * s[0],
+                -m[2][0] * s[4] + m[2][1] * s[2] - m[2][3] * s[0],
+            ],
+            [
+                -m[1][0] * c[3] + m[1][1] * c[1] - m[1][2] * c[0],
+                m[0][0] * c[3] - m[0][1] * c[1] + m[0][2] * c[0],
+                -m[3][0] * s[3] + m[3][1] * s[1] - m[3][2] * s[0],
+                m[2][0] * s[3] - m[2][1] * s[1] + m[2][2] * s[0],
+            ],
+        ]);
+        return Ok(adj * (1.0 / det));
+    }
+}
+
+// Mathematical operations overloading. SMatrix is Copy, so operators take values.
+impl<const R: usize, const C: usize> std::ops::Add for SMatrix<R, C> {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        return self.zip_map(&rhs, |a, b| a + b);
+    }
+}
+
+impl<const R: usize, const C: usize> std::ops::Sub for SMatrix<R, C> {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        return self.zip_map(&rhs, |a, b| a - b);
+    }
+}
+
+impl<const R: usize, const K: usize, const C: usize> std::ops::Mul<SMatrix<K, C>>
+    for SMatrix<R, K>
+{
+    type Output = SMatrix<R, C>;
+    fn mul(self, rhs: SMatrix<K, C>) -> Self::Output {
+        let mut result: SMatrix<R, C> = SMatrix::new();
+        for r in 0..R {
+            for k in 0..K {
+                let a: f64 = self.data[r][k];
+                for c in 0..C {
+                    result.data[r][c] += a * rhs.data[k][c];
+                }
+            }
+        }
+        return result;
+    }
+}
+
+impl<const R: usize, const C: usize> std::ops::Neg for SMatrix<R, C> {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        return self.map(|x| -x);
+    }
+}
+
+// Scalar operations
+impl<const R: usize, const C: usize> std::ops::Add<f64> for SMatrix<R, C> {
+    type Output = Self;
+    fn add(self, rhs: f64) -> Self::Output {
+        return self.map(|x| x + rhs);
+    }
+}
+
+impl<const R: usize, const C: usize> std::ops::Add<SMatrix<R, C>> for f64 {
+    type Output = SMatrix<R, C>;
+    fn add(self, rhs: SMatrix<R, C>) -> Self::Output {
+        return rhs + self;
+    }
+}
+
+impl<const R: usize, const C: usize> std::ops::Sub<f64> for SMatrix<R, C> {
+    type Output = Self;
+    fn sub(self, rhs: f64) -> Self::Output {
+        return self.map(|x| x - rhs);
+    }
+}
+
+impl<const R: usize, const C: usize> std::ops::Sub<SMatrix<R, C>> for f64 {
+    type Output = SMatrix<R, C>;
+    fn sub(self, rhs: SMatrix<R, C>) -> Self::Output {
+        return rhs.map(|x| self - x);
+    }
+}
+
+impl<const R: usize, const C: usize> std::ops::Mul<f64> for SMatrix<R, C> {
+    type Output = Self;
+    fn mul(self, rhs: f64) -> Self::Output {
+        return self.map(|x| x * rhs);
+    }
+}
+
+impl<const R: usize, const C: usize> std::ops::Mul<SMatrix<R, C>> for f64 {
+    type Output = SMatrix<R, C>;
+    fn mul(self, rhs: SMatrix<R, C>) -> Self::Output {
+        return rhs * self;
+    }
+}
+
+impl<const R: usize, const C: usize> std::ops::Div<f64> for SMatrix<R, C> {
+    type Output = Self;
+    fn div(self, rhs: f64) -> Self::Output {
+        return self.map(|x| x / rhs);
+    }
+}
+
+// Compound assignment. Multiplying in place keeps the shape, so the right factor is square.
+impl<const R: usize, const C: usize> std::ops::AddAssign for SMatrix<R, C> {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<const R: usize, const C: usize> std::ops::SubAssign for SMatrix<R, C> {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<const R: usize, const C: usize> std::ops::MulAssign<SMatrix<C, C>> for SMatrix<R, C> {
+    fn mul_assign(&mut self, rhs: SMatrix<C, C>) {
+        *self = *self * rhs;
+    }
+}
+
+impl<const R: usize, const C: usize> std::ops::AddAssign<f64> for SMatrix<R, C> {
+    fn add_assign(&mut self, rhs: f64) {
+        *self = *self + rhs;
+    }
+}
+
+impl<const R: usize, const C: usize> std::ops::SubAssign<f64> for SMatrix<R, C> {
+    fn sub_assign(&mut self, rhs: f64) {
+        *self = *self - rhs;
+    }
+}
+
+impl<const R: usize, const C: usize> std::ops::MulAssign<f64> for SMatrix<R, C> {
+    fn mul_assign(&mut self, rhs: f64) {
+        *self = *self * rhs;
+    }
+}
+
+impl<const R: usize, const C: usize> std::ops::DivAssign<f64> for SMatrix<R, C> {
+    fn div_assign(&mut self, rhs: f64) {
+        *self = *self / rhs;
+    }
+}
+
+impl<const R: usize, const C: usize> PartialEq for SMatrix<R, C> {
+    fn eq(&self, other: &Self) -> bool {
+        return self.data == other.data;
+    }
+}
+
+// Conversions to and from the heap-allocated matrix
+impl<const R: usize, const C: usize> From<SMatrix<R, C>> for Matrix<f64> {
+    fn from(value: SMatrix<R, C>) -> Self {
+        let mut result: Matrix<f64> = Matrix::<f64>::new((R, C));
+        for r in 0..R {
+            for c in 0..C {
+                result.set(r, c, value.data[r][c]);
+            }
+        }
+        return result;
+    }
+}
+
+impl<const R: usize, const C: usize> TryFrom<&Matrix<f64>> for SMatrix<R, C> {
+    type Error = LinAlgError;
+
+    fn try_from(value: &Matrix<f64>) -> Result<Self, Self::Error> {
+        if value.get_dim() != (R, C) {
+            return Err(LinAlgError::DimensionMismatch {
+                expected: (R, C),
+                found: value.get_dim(),
+            });
+        }
+        let mut result: Self = Self::new();
+        for r in 0..R {
+            for c in 0..C {
+                result.data[r][c] = value.get(r, c);
+            }
+        }
+        return Ok(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smatrix_arithmetic() {
+        let a: SMatrix<2, 3> = SMatrix::from_rows([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        let b: SMatrix<3, 2> = SMatrix::from_rows([[7.0, 8.0], [9.0, 10.0], [11.0, 12.0]]);
+
+        let product: SMatrix<2, 2> = a * b;
+        assert_eq!(product, SMatrix::from_rows([[58.0, 64.0], [139.0, 154.0]]));
+        assert_eq!(a + a, 2.0 * a);
+        assert_eq!(a - a, SMatrix::new());
+        assert_eq!((a * 10.0) / 10.0, a);
+        assert_eq!(1.0 - a, -(a - 1.0));
+        assert_eq!(a.transpose().get_dim(), (3, 2));
+        assert_eq!(a.transpose().get(2, 1), 6.0);
+        assert_eq!(a.dot_product(&a), 91.0);
+        assert_eq!((-a).abs(), a);
+
+        let mut c: SMatrix<2, 3> = a;
+        c += a;
+        c -= SMatrix::ones();
+        c *= 3.0;
+        c /= 3.0;
+        c += 1.0;
+        c -= 2.0;
+        assert_eq!(c, 2.0 * a - 2.0);
+        c *= SMatrix::<3, 3>::identity() * 2.0;
+        assert_eq!(c, 4.0 * a - 4.0);
+    }
+
+    #[test]
+    fn test_smatrix_norms() {
+        let a: SMatrix<2, 3> = SMatrix::from_rows([[1.0, -2.0, 3.0], [-4.0, 5.0, -6.0]]);
+        let m: Matrix<f64> = Matrix::from(a);
+        assert_eq!(a.norm_1(), m.norm_1());
+        assert_eq!(a.norm_inf(), m.norm_inf());
+        assert_eq!(a.norm_max(), 6.0);
+        assert_eq!(a.norm_p(1.0), 21.0);
+        assert!((a.norm_p(2.0) - a.norm()).abs() < 1e-14);
+        assert_eq!(a.norm_p(f64::INFINITY), 6.0);
+        assert_eq!(a.norm_2(), m.norm_2());
+        assert_eq!(a.norm_nuclear(), m.norm_nuclear());
+        assert_eq!(SMatrix::<2, 2>::ones().norm_1(), 2.0);
+    }
+
+    #[test]
+    fn test_smatrix_matches_matrix() {
+        let a: SMatrix<3, 3> =
+            SMatrix::from_rows([[2.0, -1.0, 0.0], [1.0, 2.0, 1.0], [0.0, 1.0, 3.0]]);
+        let b: SMatrix<3, 2> = SMatrix::from_rows([[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]]);
+        let dyn_product: Matrix<f64> = &Matrix::from(a) * &Matrix::from(b);
+        assert_eq!(dyn_product, Matrix::from(a * b));
+
+        let back: SMatrix<3, 2> = SMatrix::try_from(&dyn_product).unwrap();
+        assert_eq!(back, a * b);
+        assert!(SMatrix::<2, 2>::try_from(&dyn_product).is_err());
+    }
+
+    #[test]
+    fn test_smatrix_det_inverse() {
+        let m1: SMatrix<1, 1> = SMatrix::from_rows([[4.0]]);
+        assert_eq!(m1.inverse().unwrap().get(0, 0), 0.25);
+
+        let m2: SMatrix<2, 2> = SMatrix::from_rows([[4.0, 7.0], [2.0, 6.0]]);
+        assert_eq!(m2.det(), 10.0);
+        assert!((m2 * m2.inverse().unwrap()).approx_eq(&SMatrix::identity(), 1e-12, 0.0));
+
+        let m3: SMatrix<3, 3> =
+            SMatrix::from_rows([[2.0, -1.0, 0.0], [1.0, 2.0, 1.0], [0.0, 1.0, 3.0]]);
+        assert_eq!(m3.det(), 13.0);
+        assert!((m3 * m3.inverse().unwrap()).approx_eq(&SMatrix::identity(), 1e-12, 0.0));
+
+        let m4: SMatrix<4, 4> = SMatrix::from_rows([
+            [4.0, 3.0, 2.0, 1.0],
+            [0.0, 1.0, -1.0, 2.0],
+            [1.0, 0.0, 3.0, 0.0],
+            [2.0, 1.0, 0.0, 5.0],
+        ]);
+        assert!((m4.det() - 46.0).abs() < 1e-12);
+        assert!((m4 * m4.inverse().unwrap()).approx_eq(&SMatrix::identity(), 1e-12, 0.0));
+        assert!((m4.inverse().unwrap() * m4).approx_eq(&SMatrix::identity(), 1e-12, 0.0));
+
+        let singular: SMatrix<2, 2> = SMatrix::from_rows([[1.0, 2.0], [2.0, 4.0]]);
+        assert_eq!(singular.inverse().unwrap_err(), LinAlgError::Singular);
+
+        // Rounding leaves det = 1.7e-17 here instead of zero, which Matrix rejects as well
+        let rows: [[f64; 3]; 3] = [[0.1, 0.2, 0.3], [0.4, 0.5, 0.6], [0.7, 0.8, 0.9]];
+        let nearly: SMatrix<3, 3> = SMatrix::from_rows(rows);
+        assert_ne!(nearly.det(), 0.0);
+        assert_eq!(nearly.inverse().unwrap_err(), LinAlgError::Singular);
+        assert_eq!(
+            Matrix::from_rows(&rows).inverse().unwrap_err(),
+            LinAlgError::Singular
+        );
+
+        // Scaling does not change the verdict
+        assert!((m3 * 1e-100).inverse().is_ok());
+        assert!((nearly * 1e100).inverse().is_err());
+    }
+}
@@ -0,0 +1,277 @@
+/*
+Sparse matrices in compressed sparse row (CSR) format.
+
+A SparsityPattern records which entries may be non-zero: row i owns the column indices
+col_idx[row_ptr[i]..row_ptr[i + 1]], sorted and without duplicates. CsrMatrix pairs a pattern with
+one value per stored entry. Entries outside the pattern read as zero, and stored entries may
+themselves be zero, e.g. a Jacobian entry that vanishes at the evaluation point.
+*/
+
+use super::mat::Matrix;
+use std::ops::Mul;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparsityPattern {
+    dim: (usize, usize),
+    row_ptr: Vec<usize>,
+    col_idx: Vec<usize>,
+}
+
+impl SparsityPattern {
+    // Pattern from (row, col) positions in any order, duplicates are merged
+    pub fn from_entries(dim: (usize, usize), entries: &[(usize, usize)]) -> Self {
+        let mut rows: Vec<Vec<usize>> = vec![Vec::new(); dim.0];
+        for &(i, j) in entries {
+            if i >= dim.0 || j >= dim.1 {
+                panic!("Index out of bounds.");
+            }
+            rows[i].push(j);
+        }
+        let mut row_ptr: Vec<usize> = Vec::with_capacity(dim.0 + 1);
+        let mut col_idx: Vec<usize> = Vec::with_capacity(entries.len());
+        row_ptr.push(0);
+        for mut row in rows {
+            row.sort_unstable();
+            row.dedup();
+            col_idx.extend(row);
+            row_ptr.push(col_idx.len());
+        }
+        return Self {
+            dim,
+            row_ptr,
+            col_idx,
+        };
+    }
+
+    // All entries with -kl <= j - i <= ku of a rows x cols matrix
+    pub fn banded(dim: (usize, usize), kl: usize, ku: usize) -> Self {
+        let mut entries: Vec<(usize, usize)> = Vec::new();
+        for i in 0..dim.0 {
+            for j in i.saturating_sub(kl)..(i + ku + 1).min(dim.1) {
+                entries.push((i, j));
+            }
+        }
+        return Self::from_entries(dim, &entries);
+    }
+
+    // Positions of the entries of A that are not exactly zero
+    pub fn from_matrix(A: &Matrix<f64>) -> Self {
+        let (rows, cols) = A.get_dim();
+        let mut entries: Vec<(usize, usize)> = Vec::new();
+        for i in 0..rows {
+            for j in 0..cols {
+                if A.get(i, j) != 0.0 {
+                    entries.push((i, j));
+                }
+            }
+        }
+        return Self::from_entries((rows, cols), &entries);
+    }
+
+    pub fn get_dim(&self) -> (usize, usize) {
+        return self.dim;
+    }
+
+    // Number of stored entries
+    pub fn nnz(&self) -> usize {
+        return self.col_idx.len();
+    }
+
+    // Sorted column indices of row i
+    pub fn row(&self, i: usize) -> &[usize] {
+        return &self.col_idx[self.row_ptr[i]..self.row_ptr[i + 1]];
+    }
+
+    // Position of entry (i, j) in the value array, if it is stored
+    pub fn index(&self, i: usize, j: usize) -> Option<usize> {
+        if i >= self.dim.0 || j >= self.dim.1 {
+            panic!("Index out of bounds.");
+        }
+        return self
+            .row(i)
+            .binary_search(&j)
+            .ok()
+            .map(|k| self.row_ptr[i] + k);
+    }
+
+    pub fn contains(&self, i: usize, j: usize) -> bool {
+        return self.index(i, j).is_some();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsrMatrix {
+    pattern: SparsityPattern,
+    values: Vec<f64>,
+}
+
+impl CsrMatrix {
+    // Zero matrix with the given pattern
+    pub fn new(pattern: SparsityPattern) -> Self {
+        let nnz: usize = pattern.nnz();
+        return Self {
+            pattern,
+            values: vec![0.0; nnz],
+        };
+    }
+
+    // `values` follows the row-major order of the stored entries
+    pub fn from_pattern(pattern: SparsityPattern, values: Vec<f64>) -> Self {
+        assert_eq!(
+            values.len(),
+            pattern.nnz(),
+            "Expected one value per stored entry."
+        );
+        return Self { pattern, values };
+    }
+
+    // Copies the entries of A that lie in the pattern, ignoring all others
+    pub fn from_matrix(A: &Matrix<f64>, pattern: SparsityPattern) -> Self {
+        assert_eq!(
+            A.get_dim(),
+            pattern.get_dim(),
+            "Incompatible matrix dimensions"
+        );
+        let mut values: Vec<f64> = Vec::with_capacity(pattern.nnz());
+        for i in 0..pattern.dim.0 {
+            for &j in pattern.row(i) {
+                values.push(A.get(i, j));
+            }
+        }
+        return Self { pattern, values };
+    }
+
+    pub fn get_dim(&self) -> (usize, usize) {
+        return self.pattern.dim;
+    }
+
+    pub fn nnz(&self) -> usize {
+        return self.pattern.nnz();
+    }
+
+    pub fn pattern(&self) -> &SparsityPattern {
+        return &self.pattern;
+    }
+
+    pub fn values(&self) -> &[f64] {
+        return &self.values;
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> f64 {
+        return match self.pattern.index(i, j) {
+            Some(idx) => self.values[idx],
+            None => 0.0,
+        };
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, val: f64) {
+        match self.pattern.index(i, j) {
+            Some(idx) => self.values[idx] = val,
+            None => panic!("Entry ({}, {}) is outside the sparsity pattern.", i, j),
+        }
+    }
+
+    // Stored entries of row i as (column, value) pairs
+    pub fn row(&self, i: usize) -> impl Iterator<Item = (usize, f64)> + '_ {
+        let start: usize = self.pattern.row_ptr[i];
+        return self
+            .pattern
+            .row(i)
+            .iter()
+            .enumerate()
+            .map(move |(k, &j)| (j, self.values[start + k]));
+    }
+
+    pub fn to_dense(&self) -> Matrix<f64> {
+        return Matrix::<f64>::from(self);
+    }
+}
+
+impl From<&CsrMatrix> for Matrix<f64> {
+    fn from(A: &CsrMatrix) -> Matrix<f64> {
+        let mut result: Matrix<f64> = Matrix::<f64>::new(A.get_dim());
+        for i in 0..A.get_dim().0 {
+            for (j, val) in A.row(i) {
+                result.set(i, j, val);
+            }
+        }
+        return result;
+    }
+}
+
+impl Mul<&Matrix<f64>> for &CsrMatrix {
+    type Output = Matrix<f64>;
+
+    fn mul(self, rhs: &Matrix<f64>) -> Matrix<f64> {
+        assert_eq!(
+            self.get_dim().1,
+            rhs.get_dim().0,
+            "Incompatible matrix dimensions"
+        );
+        let rows: usize = self.get_dim().0;
+        let cols: usize = rhs.get_dim().1;
+        let mut result: Matrix<f64> = Matrix::<f64>::new((rows, cols));
+        for i in 0..rows {
+            for c in 0..cols {
+                let mut sum: f64 = 0.0;
+                for (j, val) in self.row(i) {
+                    sum += val * rhs.get(j, c);
+                }
+                result.set(i, c, sum);
+            }
+        }
+        return result;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lin_alg::mat::Layout;
+
+    #[test]
+    fn test_pattern_construction() {
+        let pattern: SparsityPattern =
+            SparsityPattern::from_entries((3, 4), &[(2, 1), (0, 3), (0, 0), (2, 1), (1, 2)]);
+        assert_eq!(pattern.nnz(), 4);
+        assert_eq!(pattern.row(0), &[0, 3]);
+        assert!(pattern.row(1) == [2] && pattern.row(2) == [1]);
+        assert!(pattern.contains(0, 3) && !pattern.contains(1, 1));
+
+        let band: SparsityPattern = SparsityPattern::banded((4, 5), 1, 2);
+        assert_eq!(band.row(0), &[0, 1, 2]);
+        assert_eq!(band.row(3), &[2, 3, 4]);
+        assert_eq!(band.nnz(), 3 + 4 + 4 + 3);
+    }
+
+    #[test]
+    fn test_csr_matches_dense() {
+        let A: Matrix<f64> = Matrix::<f64>::from_vec(
+            (3, 3),
+            vec![4.0, 0.0, -1.0, 0.0, 2.0, 0.0, 3.0, 0.0, 5.0],
+            Layout::RowMajor,
+        );
+        let mut S: CsrMatrix = CsrMatrix::from_matrix(&A, SparsityPattern::from_matrix(&A));
+        assert_eq!(S.nnz(), 5);
+        assert_eq!(S.values(), &[4.0, -1.0, 2.0, 3.0, 5.0]);
+        crate::assert_matrix_approx_eq!(S.to_dense(), A);
+
+        let B: Matrix<f64> = Matrix::<f64>::from_vec(
+            (3, 2),
+            vec![1.0, 2.0, -1.0, 0.5, 3.0, 0.0],
+            Layout::RowMajor,
+        );
+        crate::assert_matrix_approx_eq!(&S * &B, &A * &B);
+
+        S.set(1, 1, -7.0);
+        assert_eq!(S.get(1, 1), -7.0);
+        assert_eq!(S.get(0, 1), 0.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_set_outside_pattern() {
+        let mut S: CsrMatrix = CsrMatrix::new(SparsityPattern::banded((3, 3), 0, 0));
+        S.set(0, 2, 1.0);
+    }
+}
@@ -0,0 +1,162 @@
+/*
+Forward and back substitution for triangular systems T * X = B, with any number of right-hand
+side columns in B.
+*/
+
+use super::mat::Matrix;
+use super::LinAlgError;
+use super::Scalar;
+
+// Which triangle of T holds the system. Entries in the other triangle are never read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Triangle {
+    Upper,
+    Lower,
+}
+
+// Unit assumes ones on the diagonal without reading it, as for the L factor of a packed LU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diagonal {
+    NonUnit,
+    Unit,
+}
+
+// Solves T * X = B, or T^T * X = B if `transpose` is set. Returns LinAlgError::Singular if a
+// diagonal entry of a non-unit T is exactly zero.
+pub fn solve_triangular<F: Scalar>(
+    T: &Matrix<F>,
+    B: &Matrix<F>,
+    triangle: Triangle,
+    transpose: bool,
+    diagonal: Diagonal,
+) -> Result<Matrix<F>, LinAlgError> {
+    let n: usize = T.get_dim().0;
+    if T.get_dim().1 != n {
+        return Err(LinAlgError::DimensionMismatch {
+            expected: (n, n),
+            found: T.get_dim(),
+        });
+    }
+    let k: usize = B.get_dim().1;
+    if B.get_dim().0 != n {
+        return Err(LinAlgError::DimensionMismatch {
+            expected: (n, k),
+            found: B.get_dim(),
+        });
+    }
+    if diagonal == Diagonal::NonUnit && (0..n).any(|i| T.get(i, i) == F::zero()) {
+        return Err(LinAlgError::Singular);
+    }
+
+    // Entry (i, j) of op(T)
+    let t = |i: usize, j: usize| -> F {
+        if transpose {
+            T.get(j, i)
+        } else {
+            T.get(i, j)
+        }
+    };
+    // The transpose of an upper triangular matrix is lower triangular and vice versa
+    let lower: bool = (triangle == Triangle::Lower) != transpose;
+
+    let mut X: Matrix<F> = B.clone();
+    for col in 0..k {
+        for step in 0..n {
+            let i: usize = if lower { step } else { n - 1 - step };
+            let mut sum: F = X.get(i, col);
+            let solved = if lower { 0..i } else { (i + 1)..n };
+            for j in solved {
+                sum -= t(i, j) * X.get(j, col);
+            }
+            if diagonal == Diagonal::NonUnit {
+                sum /= t(i, i);
+            }
+            X.set(i, col, sum);
+        }
+    }
+
+    return Ok(X);
+}
+
+// Solves U * X = B for upper triangular U
+pub fn back_substitution<F: Scalar>(
+    U: &Matrix<F>,
+    B: &Matrix<F>,
+) -> Result<Matrix<F>, LinAlgError> {
+    return solve_triangular(U, B, Triangle::Upper, false, Diagonal::NonUnit);
+}
+
+// Solves L * X = B for lower triangular L
+pub fn forward_substitution<F: Scalar>(
+    L: &Matrix<F>,
+    B: &Matrix<F>,
+) -> Result<Matrix<F>, LinAlgError> {
+    return solve_triangular(L, B, Triangle::Lower, false, Diagonal::NonUnit);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_upper() -> Matrix<f64> {
+        return Matrix::from_rows(&[[2.0, -1.0, 3.0], [0.0, 4.0, 1.0], [0.0, 0.0, -5.0]]);
+    }
+
+    #[test]
+    fn test_back_and_forward_substitution() {
+        let U: Matrix<f64> = example_upper();
+        let B: Matrix<f64> = Matrix::from_rows(&[[1.0, 0.0], [2.0, 1.0], [3.0, -1.0]]);
+
+        let X: Matrix<f64> = back_substitution(&U, &B).unwrap();
+        crate::assert_matrix_approx_eq!(&U * &X, B);
+
+        let L: Matrix<f64> = U.transpose();
+        let Y: Matrix<f64> = forward_substitution(&L, &B).unwrap();
+        crate::assert_matrix_approx_eq!(&L * &Y, B);
+    }
+
+    #[test]
+    fn test_transposed_solve() {
+        let U: Matrix<f64> = example_upper();
+        let B: Matrix<f64> = Matrix::from_rows(&[[1.0], [-2.0], [0.5]]);
+        let X: Matrix<f64> =
+            solve_triangular(&U, &B, Triangle::Upper, true, Diagonal::NonUnit).unwrap();
+        crate::assert_matrix_approx_eq!(&U.transpose() * &X, B);
+
+        let X_lower: Matrix<f64> =
+            solve_triangular(&U.transpose(), &B, Triangle::Lower, true, Diagonal::NonUnit).unwrap();
+        crate::assert_matrix_approx_eq!(&U * &X_lower, B);
+    }
+
+    #[test]
+    fn test_unit_diagonal_ignores_stored_diagonal() {
+        // Packed storage: the diagonal holds unrelated values and the upper part is ignored
+        let packed: Matrix<f64> =
+            Matrix::from_rows(&[[9.0, 9.0, 9.0], [2.0, 9.0, 9.0], [-1.0, 3.0, 9.0]]);
+        let B: Matrix<f64> = Matrix::from_rows(&[[1.0], [1.0], [1.0]]);
+        let X: Matrix<f64> =
+            solve_triangular(&packed, &B, Triangle::Lower, false, Diagonal::Unit).unwrap();
+
+        let mut L: Matrix<f64> = packed.lower_triangular();
+        L.set_diagonal(&[1.0, 1.0, 1.0]);
+        crate::assert_matrix_approx_eq!(&L * &X, B);
+    }
+
+    #[test]
+    fn test_singular_and_mismatched_inputs() {
+        let mut U: Matrix<f64> = example_upper();
+        U.set(1, 1, 0.0);
+        let B: Matrix<f64> = Matrix::<f64>::ones((3, 1));
+        assert_eq!(back_substitution(&U, &B), Err(LinAlgError::Singular));
+        // A unit diagonal solve never divides, so it succeeds
+        assert!(solve_triangular(&U, &B, Triangle::Upper, false, Diagonal::Unit).is_ok());
+
+        assert_eq!(
+            back_substitution(&example_upper(), &Matrix::<f64>::ones((2, 1))),
+            Err(LinAlgError::DimensionMismatch {
+                expected: (3, 1),
+                found: (2, 1)
+            })
+        );
+    }
+}
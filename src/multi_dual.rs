@@ -0,0 +1,424 @@
+/*
+Dual numbers with several tangents, for forward mode along many directions in one pass.
+
+A MultiDual<T, N> carries a value f and N tangents df, each propagated by the same chain rule as
+the single tangent of DiffNum. Seeding the inputs with N directions d_1..d_N yields the products
+J d_1..J d_N from one evaluation, so the value and the partial derivatives of every elementary
+function are computed once rather than once per direction. sparse_jacobian evaluates the colours
+of a column colouring this way.
+
+The subgradient conventions at kinks follow DiffNum: abs is flat at 0, min and max average their
+arguments at a tie, and hypot is flat at the origin.
+*/
+
+use crate::custom_rule::CustomRule;
+use crate::float::Float;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MultiDual<T, const N: usize> {
+    pub f: T,
+    pub df: [T; N],
+}
+
+impl<T: Float, const N: usize> MultiDual<T, N> {
+    pub fn constant(f: T) -> Self {
+        return Self {
+            f,
+            df: [T::zero(); N],
+        };
+    }
+
+    // Input seeded with the unit tangent of direction i
+    pub fn variable(f: T, i: usize) -> Self {
+        let mut result: Self = Self::constant(f);
+        result.df[i] = T::one();
+        return result;
+    }
+
+    // g(x) from g(f) and g'(f)
+    fn chain(self, f: T, slope: T) -> Self {
+        return Self {
+            f,
+            df: self.df.map(|d| slope * d),
+        };
+    }
+
+    pub fn powi(self, n: i32) -> Self {
+        if n == 0 {
+            return Self::constant(T::one());
+        }
+        return self.chain(self.f.powi(n), T::from_f64(n as f64) * self.f.powi(n - 1));
+    }
+
+    // x^y with a differentiable exponent. As for DiffNum::pow, a term is skipped where its
+    // tangent is zero, since its partial may be infinite at a zero base.
+    pub fn pow(self, other: Self) -> Self {
+        let f: T = self.f.powf(other.f);
+        let base_slope: T = other.f * self.f.powf(other.f - T::one());
+        let mut result: Self = Self::constant(f);
+        for i in 0..N {
+            if self.df[i] != T::zero() {
+                result.df[i] += base_slope * self.df[i];
+            }
+            if other.df[i] != T::zero() && f != T::zero() {
+                result.df[i] += f * self.f.ln() * other.df[i];
+            }
+        }
+        return result;
+    }
+
+    pub fn sqrt(self) -> Self {
+        let s: T = self.f.sqrt();
+        return self.chain(s, T::from_f64(0.5) / s);
+    }
+
+    pub fn cbrt(self) -> Self {
+        let c: T = self.f.cbrt();
+        return self.chain(c, T::one() / (T::from_f64(3.0) * c * c));
+    }
+
+    pub fn recip(self) -> Self {
+        let r: T = self.f.recip();
+        return self.chain(r, -r * r);
+    }
+
+    pub fn exp(self) -> Self {
+        let e: T = self.f.exp();
+        return self.chain(e, e);
+    }
+
+    pub fn exp2(self) -> Self {
+        let e: T = self.f.exp2();
+        return self.chain(e, e * T::from_f64(std::f64::consts::LN_2));
+    }
+
+    pub fn exp_m1(self) -> Self {
+        return self.chain(self.f.exp_m1(), self.f.exp());
+    }
+
+    pub fn ln(self) -> Self {
+        return self.chain(self.f.ln(), self.f.recip());
+    }
+
+    pub fn ln_1p(self) -> Self {
+        return self.chain(self.f.ln_1p(), (T::one() + self.f).recip());
+    }
+
+    pub fn log2(self) -> Self {
+        let slope: T = (self.f * T::from_f64(std::f64::consts::LN_2)).recip();
+        return self.chain(self.f.log2(), slope);
+    }
+
+    pub fn log10(self) -> Self {
+        let slope: T = (self.f * T::from_f64(std::f64::consts::LN_10)).recip();
+        return self.chain(self.f.log10(), slope);
+    }
+
+    // Both the argument and the base may carry derivatives
+    pub fn log(self, base: Self) -> Self {
+        let mut result: Self = self.ln() / base.ln();
+        result.f = self.f.log(base.f);
+        return result;
+    }
+
+    pub fn sin_cos(self) -> (Self, Self) {
+        let (sin, cos) = self.f.sin_cos();
+        return (self.chain(sin, cos), self.chain(cos, -sin));
+    }
+
+    pub fn sin(self) -> Self {
+        return self.chain(self.f.sin(), self.f.cos());
+    }
+
+    pub fn cos(self) -> Self {
+        return self.chain(self.f.cos(), -self.f.sin());
+    }
+
+    pub fn tan(self) -> Self {
+        let t: T = self.f.tan();
+        return self.chain(t, T::one() + t * t);
+    }
+
+    pub fn asin(self) -> Self {
+        return self.chain(self.f.asin(), (T::one() - self.f * self.f).sqrt().recip());
+    }
+
+    pub fn acos(self) -> Self {
+        return self.chain(self.f.acos(), -(T::one() - self.f * self.f).sqrt().recip());
+    }
+
+    pub fn atan(self) -> Self {
+        return self.chain(self.f.atan(), (T::one() + self.f * self.f).recip());
+    }
+
+    // Angle of the point (other, self), matching f64::atan2 with self as y
+    pub fn atan2(self, other: Self) -> Self {
+        let r2: T = self.f * self.f + other.f * other.f;
+        let mut result: Self = Self::constant(self.f.atan2(other.f));
+        for i in 0..N {
+            result.df[i] = (other.f * self.df[i] - self.f * other.df[i]) / r2;
+        }
+        return result;
+    }
+
+    pub fn sinh(self) -> Self {
+        return self.chain(self.f.sinh(), self.f.cosh());
+    }
+
+    pub fn cosh(self) -> Self {
+        return self.chain(self.f.cosh(), self.f.sinh());
+    }
+
+    pub fn tanh(self) -> Self {
+        let t: T = self.f.tanh();
+        return self.chain(t, T::one() - t * t);
+    }
+
+    pub fn asinh(self) -> Self {
+        return self.chain(self.f.asinh(), (self.f * self.f + T::one()).sqrt().recip());
+    }
+
+    pub fn acosh(self) -> Self {
+        return self.chain(self.f.acosh(), (self.f * self.f - T::one()).sqrt().recip());
+    }
+
+    pub fn atanh(self) -> Self {
+        return self.chain(self.f.atanh(), (T::one() - self.f * self.f).recip());
+    }
+
+    pub fn hypot(self, other: Self) -> Self {
+        let h: T = self.f.hypot(other.f);
+        let mut result: Self = Self::constant(h);
+        if h != T::zero() {
+            for i in 0..N {
+                result.df[i] = (self.f * self.df[i] + other.f * other.df[i]) / h;
+            }
+        }
+        return result;
+    }
+
+    pub fn mul_add(self, a: Self, b: Self) -> Self {
+        let mut result: Self = self * a + b;
+        result.f = self.f.mul_add(a.f, b.f);
+        return result;
+    }
+
+    pub fn abs(self) -> Self {
+        let slope: T = if self.f > T::zero() {
+            T::one()
+        } else if self.f < T::zero() {
+            -T::one()
+        } else {
+            T::zero()
+        };
+        return self.chain(self.f.abs(), slope);
+    }
+
+    pub fn signum(self) -> Self {
+        return Self::constant(self.f.signum());
+    }
+
+    pub fn min(self, other: Self) -> Self {
+        if self.f < other.f {
+            return self;
+        }
+        if other.f < self.f {
+            return other;
+        }
+        return (self + other) * Self::constant(T::from_f64(0.5));
+    }
+
+    pub fn max(self, other: Self) -> Self {
+        if self.f > other.f {
+            return self;
+        }
+        if other.f > self.f {
+            return other;
+        }
+        return (self + other) * Self::constant(T::from_f64(0.5));
+    }
+}
+
+// Arithmetic
+impl<T: Float, const N: usize> std::ops::Add for MultiDual<T, N> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let mut result: Self = self;
+        result += other;
+        return result;
+    }
+}
+
+impl<T: Float, const N: usize> std::ops::Sub for MultiDual<T, N> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        let mut result: Self = self;
+        result -= other;
+        return result;
+    }
+}
+
+impl<T: Float, const N: usize> std::ops::Mul for MultiDual<T, N> {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let mut result: Self = Self::constant(self.f * other.f);
+        for i in 0..N {
+            result.df[i] = self.df[i] * other.f + self.f * other.df[i];
+        }
+        return result;
+    }
+}
+
+impl<T: Float, const N: usize> std::ops::Div for MultiDual<T, N> {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        let q: T = self.f / other.f;
+        let mut result: Self = Self::constant(q);
+        for i in 0..N {
+            result.df[i] = (self.df[i] - q * other.df[i]) / other.f;
+        }
+        return result;
+    }
+}
+
+impl<T: Float, const N: usize> std::ops::Neg for MultiDual<T, N> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        return Self {
+            f: -self.f,
+            df: self.df.map(|d| -d),
+        };
+    }
+}
+
+impl<T: Float, const N: usize> std::ops::AddAssign for MultiDual<T, N> {
+    fn add_assign(&mut self, other: Self) {
+        self.f += other.f;
+        for (d, o) in self.df.iter_mut().zip(other.df) {
+            *d += o;
+        }
+    }
+}
+
+impl<T: Float, const N: usize> std::ops::SubAssign for MultiDual<T, N> {
+    fn sub_assign(&mut self, other: Self) {
+        self.f -= other.f;
+        for (d, o) in self.df.iter_mut().zip(other.df) {
+            *d -= o;
+        }
+    }
+}
+
+impl<T: Float, const N: usize> std::ops::MulAssign for MultiDual<T, N> {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+impl<T: Float, const N: usize> std::ops::DivAssign for MultiDual<T, N> {
+    fn div_assign(&mut self, other: Self) {
+        *self = *self / other;
+    }
+}
+
+// Ordering compares values only, as for DiffNum
+impl<T: Float, const N: usize> PartialOrd for MultiDual<T, N> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        return self.f.partial_cmp(&other.f);
+    }
+}
+
+impl<T: Float, const N: usize> Float for MultiDual<T, N> {
+    fn from_f64(value: f64) -> Self {
+        return Self::constant(T::from_f64(value));
+    }
+
+    fn to_f64(self) -> f64 {
+        return self.f.to_f64();
+    }
+
+    fn powf(self, n: Self) -> Self {
+        return self.pow(n);
+    }
+
+    // Primal of the values and one JVP per tangent direction
+    fn apply_rule<R: CustomRule>(rule: &R, x: &[Self]) -> Vec<Self> {
+        let values: Vec<T> = x.iter().map(|xi| xi.f).collect();
+        let f: Vec<T> = T::apply_rule(rule, &values);
+        let mut result: Vec<Self> = f.iter().map(|&fi| Self::constant(fi)).collect();
+        for i in 0..N {
+            let tangents: Vec<T> = x.iter().map(|xi| xi.df[i]).collect();
+            for (r, d) in result.iter_mut().zip(rule.jvp(&values, &f, &tangents)) {
+                r.df[i] = d;
+            }
+        }
+        return result;
+    }
+
+    crate::float::forward_methods!(
+        MultiDual<T, N>;
+        unary: sqrt, cbrt, recip, exp, exp2, exp_m1, ln, ln_1p, log2, log10, sin, cos, tan,
+            asin, acos, atan, sinh, cosh, tanh, asinh, acosh, atanh, abs, signum;
+        binary: log, atan2, hypot, min, max
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto_diff::DiffNum;
+
+    // Mixes every kind of operation, written once against the trait
+    fn mixed<F: Float>(x: &[F]) -> F {
+        let a: F = x[0].sin() * x[1].exp() / (x[2] * x[2] + F::one()).sqrt();
+        let b: F = x[0].atan2(x[2]).powf(x[1]) + x[1].hypot(x[2]).ln_1p();
+        let c: F = x[2].tanh().mul_add(x[0], x[1].cbrt()) - x[0].max(x[1]).log(x[2] + x[1]);
+        return a * b + c.abs() + x[1].powi(3).recip();
+    }
+
+    #[test]
+    fn test_matches_diffnum_in_every_direction() {
+        let x: [f64; 3] = [0.7, 1.3, 2.1];
+        let seeded: Vec<MultiDual<f64, 3>> = x
+            .iter()
+            .enumerate()
+            .map(|(j, &xj)| MultiDual::variable(xj, j))
+            .collect();
+        let y: MultiDual<f64, 3> = mixed(&seeded);
+
+        for j in 0..3 {
+            let dual: Vec<DiffNum<f64>> = x
+                .iter()
+                .enumerate()
+                .map(|(k, &xk)| DiffNum {
+                    f: xk,
+                    df: if k == j { 1.0 } else { 0.0 },
+                })
+                .collect();
+            let expected: DiffNum<f64> = mixed(&dual);
+            assert_eq!(y.f, expected.f);
+            assert!((y.df[j] - expected.df).abs() < 1e-14 * expected.df.abs().max(1.0));
+        }
+    }
+
+    #[test]
+    fn test_kinks_and_constants() {
+        let zero: MultiDual<f64, 2> = MultiDual {
+            f: 0.0,
+            df: [1.0, -2.0],
+        };
+        assert_eq!(zero.abs().df, [0.0, 0.0]);
+        assert_eq!(zero.hypot(zero).df, [0.0, 0.0]);
+        assert_eq!(zero.max(zero * zero).df, [0.5, -1.0]);
+        // A constant zero base with a fractional exponent stays finite
+        let base: MultiDual<f64, 2> = MultiDual::constant(0.0);
+        let exponent: MultiDual<f64, 2> = MultiDual::variable(0.5, 1);
+        assert_eq!(base.pow(exponent).df, [0.0, 0.0]);
+        assert_eq!(<MultiDual<f64, 2> as Float>::from_f64(2.5).df, [0.0, 0.0]);
+    }
+}
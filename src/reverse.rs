@@ -0,0 +1,690 @@
+/*
+Reverse mode automatic differentiation on a tape.
+
+Every operation on a Var appends a node to its Tape holding the indices of its operands and the
+partial derivatives with respect to them. Tape::backward then visits the nodes in reverse
+order once, accumulating adjoints, so the gradient of a scalar output costs a small multiple of
+the function evaluation regardless of the number of inputs. Forward mode with DiffNum is the
+better choice for few inputs and many outputs.
+
+Var implements Float, so generic code runs on it unchanged. Float::from_f64 creates constants
+that are not recorded; they only join the tape through operations with recorded variables.
+Variables of different tapes must not be mixed. The tape grows with every operation, see the
+checkpoint module for loops that would not fit in memory. Non-smooth functions follow the
+conventions of DiffNum.
+
+A custom rule is recorded as one node per output without edges plus a copy of the rule. The
+backward pass calls the rule's VJP once with the cotangents of all outputs when it reaches the
+last of them; at that point every later node has been visited, so those cotangents are final.
+*/
+
+use crate::custom_rule::CustomRule;
+use crate::float::Float;
+use crate::gradient_check::Objective;
+use std::cell::RefCell;
+
+// Dependence of a node on one operand
+#[derive(Clone, Copy, Debug)]
+struct Edge {
+    parent: usize,
+    partial: f64,
+}
+
+// Vector-Jacobian product w^T J(x) given x, y and w
+type Vjp = Box<dyn Fn(&[f64], &[f64], &[f64]) -> Vec<f64>>;
+
+// A custom rule whose outputs are the nodes first..first + y.len()
+struct RuleNode {
+    first: usize,
+    // Node of every input, None for constants
+    parents: Vec<Option<usize>>,
+    x: Vec<f64>,
+    y: Vec<f64>,
+    vjp: Vjp,
+}
+
+impl std::fmt::Debug for RuleNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f
+            .debug_struct("RuleNode")
+            .field("first", &self.first)
+            .field("parents", &self.parents)
+            .field("x", &self.x)
+            .field("y", &self.y)
+            .finish_non_exhaustive();
+    }
+}
+
+// The edges of node i are edges[ends[i - 1]..ends[i]]. Rules are ordered by their first node.
+#[derive(Debug, Default)]
+struct Recording {
+    ends: Vec<usize>,
+    edges: Vec<Edge>,
+    rules: Vec<RuleNode>,
+}
+
+#[derive(Debug, Default)]
+pub struct Tape {
+    recording: RefCell<Recording>,
+}
+
+// Adjoint of every node on a tape after a backward pass
+#[derive(Clone, Debug)]
+pub struct Adjoints {
+    values: Vec<f64>,
+}
+
+impl Adjoints {
+    // Derivative of the seeded outputs with respect to v, zero for constants
+    pub fn wrt(&self, v: Var) -> f64 {
+        return match v.tape {
+            Some(_) => self.values[v.index],
+            None => 0.0,
+        };
+    }
+}
+
+impl Tape {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    // New independent variable
+    pub fn var(&self, value: f64) -> Var<'_> {
+        let index: usize = self.push(&[]);
+        return Var {
+            value,
+            index,
+            tape: Some(self),
+        };
+    }
+
+    // Number of recorded nodes, including the independent variables
+    pub fn len(&self) -> usize {
+        return self.recording.borrow().ends.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    // Drops all nodes. Taking &mut self ensures no variable of the old recording is still alive.
+    pub fn clear(&mut self) {
+        let recording: &mut Recording = self.recording.get_mut();
+        recording.ends.clear();
+        recording.edges.clear();
+        recording.rules.clear();
+    }
+
+    fn push(&self, edges: &[Edge]) -> usize {
+        let mut recording = self.recording.borrow_mut();
+        recording.edges.extend_from_slice(edges);
+        let end: usize = recording.edges.len();
+        recording.ends.push(end);
+        return recording.ends.len() - 1;
+    }
+
+    // Vector-Jacobian product: adjoints of all nodes for the given output cotangents
+    pub fn backward(&self, outputs: &[Var], cotangents: &[f64]) -> Adjoints {
+        assert_eq!(
+            outputs.len(),
+            cotangents.len(),
+            "Expected one cotangent per output"
+        );
+        let recording = self.recording.borrow();
+        let mut values: Vec<f64> = vec![0.0; recording.ends.len()];
+        for (output, &cotangent) in outputs.iter().zip(cotangents) {
+            if let Some(tape) = output.tape {
+                assert!(std::ptr::eq(tape, self), "Output belongs to another tape");
+                values[output.index] += cotangent;
+            }
+        }
+        let mut rules = recording.rules.iter().rev().peekable();
+        for i in (0..recording.ends.len()).rev() {
+            if let Some(rule) = rules.next_if(|rule| rule.first + rule.y.len() == i + 1) {
+                let w: &[f64] = &values[rule.first..=i];
+                if w.iter().any(|&wi| wi != 0.0) {
+                    let x_bar: Vec<f64> = (rule.vjp)(&rule.x, &rule.y, w);
+                    for (parent, xb) in rule.parents.iter().zip(x_bar) {
+                        if let Some(parent) = *parent {
+                            values[parent] += xb;
+                        }
+                    }
+                }
+                // The outputs of a rule have no edges
+                continue;
+            }
+            let adjoint: f64 = values[i];
+            if adjoint == 0.0 {
+                continue;
+            }
+            let start: usize = if i == 0 { 0 } else { recording.ends[i - 1] };
+            for edge in &recording.edges[start..recording.ends[i]] {
+                values[edge.parent] += edge.partial * adjoint;
+            }
+        }
+        return Adjoints { values };
+    }
+
+    // Adjoints for a scalar output, i.e. its gradient
+    pub fn gradient(&self, output: Var) -> Adjoints {
+        return self.backward(&[output], &[1.0]);
+    }
+}
+
+// Gradient of a scalar objective by one recorded evaluation and one backward pass
+pub fn gradient<O: Objective>(objective: &O, x: &[f64]) -> Vec<f64> {
+    let tape: Tape = Tape::new();
+    let vars: Vec<Var> = x.iter().map(|&xi| tape.var(xi)).collect();
+    let adjoints: Adjoints = tape.gradient(objective.eval(&vars));
+    return vars.iter().map(|&v| adjoints.wrt(v)).collect();
+}
+
+#[derive(Clone, Copy)]
+pub struct Var<'t> {
+    value: f64,
+    index: usize,
+    tape: Option<&'t Tape>,
+}
+
+impl std::fmt::Debug for Var<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self.tape {
+            Some(_) => f
+                .debug_struct("Var")
+                .field("value", &self.value)
+                .field("index", &self.index)
+                .finish(),
+            None => f.debug_struct("Var").field("value", &self.value).finish(),
+        };
+    }
+}
+
+impl<'t> Var<'t> {
+    // Value that is not recorded on any tape
+    pub fn constant(value: f64) -> Self {
+        return Self {
+            value,
+            index: 0,
+            tape: None,
+        };
+    }
+
+    pub fn value(self) -> f64 {
+        return self.value;
+    }
+
+    pub fn is_constant(self) -> bool {
+        return self.tape.is_none();
+    }
+
+    fn unary(self, value: f64, partial: f64) -> Self {
+        return match self.tape {
+            Some(tape) => Self {
+                value,
+                index: tape.push(&[Edge {
+                    parent: self.index,
+                    partial,
+                }]),
+                tape: Some(tape),
+            },
+            None => Self::constant(value),
+        };
+    }
+
+    fn binary(self, other: Self, value: f64, d_self: f64, d_other: f64) -> Self {
+        return match (self.tape, other.tape) {
+            (Some(tape), Some(other_tape)) => {
+                assert!(
+                    std::ptr::eq(tape, other_tape),
+                    "Variables belong to different tapes"
+                );
+                Self {
+                    value,
+                    index: tape.push(&[
+                        Edge {
+                            parent: self.index,
+                            partial: d_self,
+                        },
+                        Edge {
+                            parent: other.index,
+                            partial: d_other,
+                        },
+                    ]),
+                    tape: Some(tape),
+                }
+            }
+            (Some(_), None) => self.unary(value, d_self),
+            (None, Some(_)) => other.unary(value, d_other),
+            (None, None) => Self::constant(value),
+        };
+    }
+
+    pub fn powi(self, n: i32) -> Self {
+        return self.unary(self.value.powi(n), n as f64 * self.value.powi(n - 1));
+    }
+
+    // x^y with both base and exponent differentiable. The ln(x) term is only formed for a
+    // recorded exponent, so that 0^y keeps a finite derivative for constant y.
+    pub fn powf(self, n: Self) -> Self {
+        let f: f64 = self.value.powf(n.value);
+        let d_base: f64 = n.value * self.value.powf(n.value - 1.0);
+        let d_exp: f64 = if n.is_constant() {
+            0.0
+        } else {
+            f * self.value.ln()
+        };
+        return self.binary(n, f, d_base, d_exp);
+    }
+
+    pub fn sqrt(self) -> Self {
+        let f: f64 = self.value.sqrt();
+        return self.unary(f, 0.5 / f);
+    }
+
+    pub fn cbrt(self) -> Self {
+        let f: f64 = self.value.cbrt();
+        return self.unary(f, 1.0 / (3.0 * f * f));
+    }
+
+    pub fn recip(self) -> Self {
+        return self.unary(self.value.recip(), -1.0 / (self.value * self.value));
+    }
+
+    pub fn exp(self) -> Self {
+        let f: f64 = self.value.exp();
+        return self.unary(f, f);
+    }
+
+    pub fn exp2(self) -> Self {
+        let f: f64 = self.value.exp2();
+        return self.unary(f, f * std::f64::consts::LN_2);
+    }
+
+    pub fn exp_m1(self) -> Self {
+        return self.unary(self.value.exp_m1(), self.value.exp());
+    }
+
+    pub fn ln(self) -> Self {
+        return self.unary(self.value.ln(), 1.0 / self.value);
+    }
+
+    pub fn ln_1p(self) -> Self {
+        return self.unary(self.value.ln_1p(), 1.0 / (1.0 + self.value));
+    }
+
+    pub fn log2(self) -> Self {
+        return self.unary(
+            self.value.log2(),
+            1.0 / (self.value * std::f64::consts::LN_2),
+        );
+    }
+
+    pub fn log10(self) -> Self {
+        return self.unary(
+            self.value.log10(),
+            1.0 / (self.value * std::f64::consts::LN_10),
+        );
+    }
+
+    // Both the argument and the base may be recorded
+    pub fn log(self, base: Self) -> Self {
+        return self.ln() / base.ln();
+    }
+
+    pub fn sin(self) -> Self {
+        return self.unary(self.value.sin(), self.value.cos());
+    }
+
+    pub fn cos(self) -> Self {
+        return self.unary(self.value.cos(), -self.value.sin());
+    }
+
+    pub fn tan(self) -> Self {
+        let t: f64 = self.value.tan();
+        return self.unary(t, 1.0 + t * t);
+    }
+
+    pub fn sin_cos(self) -> (Self, Self) {
+        let (s, c) = self.value.sin_cos();
+        return (self.unary(s, c), self.unary(c, -s));
+    }
+
+    pub fn asin(self) -> Self {
+        return self.unary(
+            self.value.asin(),
+            1.0 / (1.0 - self.value * self.value).sqrt(),
+        );
+    }
+
+    pub fn acos(self) -> Self {
+        return self.unary(
+            self.value.acos(),
+            -1.0 / (1.0 - self.value * self.value).sqrt(),
+        );
+    }
+
+    pub fn atan(self) -> Self {
+        return self.unary(self.value.atan(), 1.0 / (1.0 + self.value * self.value));
+    }
+
+    // Angle of the point (other, self), matching f64::atan2 with self as y
+    pub fn atan2(self, other: Self) -> Self {
+        let r2: f64 = self.value * self.value + other.value * other.value;
+        return self.binary(
+            other,
+            self.value.atan2(other.value),
+            other.value / r2,
+            -self.value / r2,
+        );
+    }
+
+    pub fn sinh(self) -> Self {
+        return self.unary(self.value.sinh(), self.value.cosh());
+    }
+
+    pub fn cosh(self) -> Self {
+        return self.unary(self.value.cosh(), self.value.sinh());
+    }
+
+    pub fn tanh(self) -> Self {
+        let t: f64 = self.value.tanh();
+        return self.unary(t, 1.0 - t * t);
+    }
+
+    pub fn asinh(self) -> Self {
+        return self.unary(
+            self.value.asinh(),
+            1.0 / (self.value * self.value + 1.0).sqrt(),
+        );
+    }
+
+    pub fn acosh(self) -> Self {
+        return self.unary(
+            self.value.acosh(),
+            1.0 / (self.value * self.value - 1.0).sqrt(),
+        );
+    }
+
+    pub fn atanh(self) -> Self {
+        return self.unary(self.value.atanh(), 1.0 / (1.0 - self.value * self.value));
+    }
+
+    pub fn abs(self) -> Self {
+        let slope: f64 = if self.value > 0.0 {
+            1.0
+        } else if self.value < 0.0 {
+            -1.0
+        } else {
+            0.0
+        };
+        return self.unary(self.value.abs(), slope);
+    }
+
+    pub fn signum(self) -> Self {
+        return Self::constant(self.value.signum());
+    }
+
+    // The derivative at the origin is taken as zero
+    pub fn hypot(self, other: Self) -> Self {
+        let h: f64 = self.value.hypot(other.value);
+        if h == 0.0 {
+            return self.binary(other, h, 0.0, 0.0);
+        }
+        return self.binary(other, h, self.value / h, other.value / h);
+    }
+
+    pub fn mul_add(self, a: Self, b: Self) -> Self {
+        return self * a + b;
+    }
+
+    pub fn min(self, other: Self) -> Self {
+        if self.value < other.value {
+            return self;
+        }
+        if other.value < self.value {
+            return other;
+        }
+        return self.binary(other, self.value, 0.5, 0.5);
+    }
+
+    pub fn max(self, other: Self) -> Self {
+        if self.value > other.value {
+            return self;
+        }
+        if other.value > self.value {
+            return other;
+        }
+        return self.binary(other, self.value, 0.5, 0.5);
+    }
+}
+
+// Arithmetic
+impl std::ops::Add for Var<'_> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        return self.binary(other, self.value + other.value, 1.0, 1.0);
+    }
+}
+
+impl std::ops::Sub for Var<'_> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        return self.binary(other, self.value - other.value, 1.0, -1.0);
+    }
+}
+
+impl std::ops::Mul for Var<'_> {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        return self.binary(other, self.value * other.value, other.value, self.value);
+    }
+}
+
+impl std::ops::Div for Var<'_> {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        let f: f64 = self.value / other.value;
+        return self.binary(other, f, 1.0 / other.value, -f / other.value);
+    }
+}
+
+impl std::ops::Neg for Var<'_> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        return self.unary(-self.value, -1.0);
+    }
+}
+
+// Assignment forms and operations with f64 scalars on either side, all through the operators
+// above
+macro_rules! impl_derived_ops {
+    ($($trait:ident, $method:ident, $assign_trait:ident, $assign_method:ident, $op:tt);*) => {
+        $(
+            impl std::ops::$assign_trait for Var<'_> {
+                fn $assign_method(&mut self, other: Self) {
+                    *self = *self $op other;
+                }
+            }
+
+            impl std::ops::$trait<f64> for Var<'_> {
+                type Output = Self;
+
+                fn $method(self, other: f64) -> Self {
+                    return self $op Var::constant(other);
+                }
+            }
+
+            impl<'t> std::ops::$trait<Var<'t>> for f64 {
+                type Output = Var<'t>;
+
+                fn $method(self, other: Var<'t>) -> Var<'t> {
+                    return Var::constant(self) $op other;
+                }
+            }
+        )*
+    };
+}
+
+impl_derived_ops!(
+    Add, add, AddAssign, add_assign, +;
+    Sub, sub, SubAssign, sub_assign, -;
+    Mul, mul, MulAssign, mul_assign, *;
+    Div, div, DivAssign, div_assign, /
+);
+
+// Comparisons look at values only
+impl PartialEq for Var<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        return self.value == other.value;
+    }
+}
+
+impl PartialOrd for Var<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        return self.value.partial_cmp(&other.value);
+    }
+}
+
+impl<'t> Float for Var<'t> {
+    fn from_f64(value: f64) -> Self {
+        return Self::constant(value);
+    }
+
+    fn to_f64(self) -> f64 {
+        return self.value;
+    }
+
+    // One node per output, with the VJP deferred to the backward pass
+    fn apply_rule<R: CustomRule>(rule: &R, x: &[Self]) -> Vec<Self> {
+        let values: Vec<f64> = x.iter().map(|xi| xi.value).collect();
+        let y: Vec<f64> = rule.primal(&values);
+        let tape: &'t Tape = match x.iter().find_map(|xi| xi.tape) {
+            Some(tape) if !y.is_empty() => tape,
+            _ => return y.into_iter().map(Var::constant).collect(),
+        };
+        let mut parents: Vec<Option<usize>> = Vec::with_capacity(x.len());
+        for xi in x {
+            if let Some(other) = xi.tape {
+                assert!(
+                    std::ptr::eq(tape, other),
+                    "Variables belong to different tapes"
+                );
+            }
+            parents.push(xi.tape.map(|_| xi.index));
+        }
+        let first: usize = tape.len();
+        let result: Vec<Self> = y
+            .iter()
+            .map(|&value| Var {
+                value,
+                index: tape.push(&[]),
+                tape: Some(tape),
+            })
+            .collect();
+        let rule: R = rule.clone();
+        tape.recording.borrow_mut().rules.push(RuleNode {
+            first,
+            parents,
+            x: values,
+            y,
+            vjp: Box::new(move |x: &[f64], y: &[f64], w: &[f64]| rule.vjp(x, y, w)),
+        });
+        return result;
+    }
+
+    crate::float::forward_methods!(
+        Var<'t>;
+        unary: sqrt, cbrt, recip, exp, exp2, exp_m1, ln, ln_1p, log2, log10, sin, cos, tan,
+            asin, acos, atan, sinh, cosh, tanh, asinh, acosh, atanh, abs, signum;
+        binary: powf, log, atan2, hypot, min, max
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradient_check::autodiff_gradient;
+
+    #[test]
+    fn test_gradient_of_expression() {
+        // f(x, y) = x y + sin(x) / y, df/dx = y + cos(x) / y, df/dy = x - sin(x) / y^2
+        let tape: Tape = Tape::new();
+        let x: Var = tape.var(0.5);
+        let y: Var = tape.var(2.0);
+        let f: Var = x * y + x.sin() / y;
+        assert_eq!(f.value(), 1.0 + 0.5_f64.sin() / 2.0);
+
+        let adjoints: Adjoints = tape.gradient(f);
+        assert!((adjoints.wrt(x) - (2.0 + 0.5_f64.cos() / 2.0)).abs() < 1e-15);
+        assert!((adjoints.wrt(y) - (0.5 - 0.5_f64.sin() / 4.0)).abs() < 1e-15);
+        assert_eq!(adjoints.wrt(Var::constant(3.0)), 0.0);
+
+        // Constants do not grow the tape
+        let len: usize = tape.len();
+        let c: Var = Var::constant(2.0) * 3.0 + 1.0;
+        assert!(c.is_constant() && c.value() == 7.0);
+        assert_eq!(tape.len(), len);
+    }
+
+    #[test]
+    fn test_backward_vector_output() {
+        // Outputs (x y, x + y) with cotangents (2, -1): x_bar = 2 y - 1, y_bar = 2 x - 1
+        let tape: Tape = Tape::new();
+        let x: Var = tape.var(3.0);
+        let y: Var = tape.var(-1.5);
+        let adjoints: Adjoints = tape.backward(&[x * y, x + y], &[2.0, -1.0]);
+        assert_eq!(adjoints.wrt(x), -4.0);
+        assert_eq!(adjoints.wrt(y), 5.0);
+    }
+
+    // Touches every elementary function of Float
+    struct Everything;
+
+    impl Objective for Everything {
+        fn eval<F: Float>(&self, x: &[F]) -> F {
+            let (a, b, c) = (x[0], x[1], x[2]);
+            let (s, co) = a.sin_cos();
+            return a.powi(3) + a.powf(b) + b.sqrt() + c.cbrt() + b.recip() - a.exp() * b.exp2()
+                + c.exp_m1()
+                + b.ln()
+                + a.ln_1p()
+                + b.log2()
+                + b.log10()
+                + b.log(a + F::one())
+                + s * co
+                + a.tan()
+                + a.asin()
+                + a.acos() * b
+                + a.atan()
+                + c.atan2(b)
+                + a.sinh()
+                + b.cosh() * a
+                + c.tanh()
+                + c.asinh()
+                + b.acosh()
+                + a.atanh()
+                + c.abs() * a
+                + a.hypot(c)
+                + a.min(b) * c
+                + c.max(a) * b
+                + a.mul_add(b, c)
+                + c.signum();
+        }
+    }
+
+    #[test]
+    fn test_matches_forward_mode() {
+        let x: [f64; 3] = [0.4, 1.7, -0.6];
+        let reverse: Vec<f64> = gradient(&Everything, &x);
+        let forward: Vec<f64> = autodiff_gradient(&Everything, &x);
+        for (r, f) in reverse.iter().zip(forward) {
+            assert!((r - f).abs() < 1e-12 * f.abs().max(1.0));
+        }
+    }
+}
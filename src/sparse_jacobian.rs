@@ -0,0 +1,275 @@
+/*
+Sparse Jacobians by column colouring (Curtis, Powell and Reid 1974).
+
+Two columns of J are structurally orthogonal if no row has a non-zero in both. Columns of one
+colour are pairwise orthogonal, so the direction d given by the sum of their unit vectors gives
+J * d whose row i holds J[i][j] for the unique column j of that colour touching row i. The
+directions of all colours are seeded together as the tangents of a MultiDual, so up to LANES
+colours cost a single evaluation of f. A banded Jacobian with kl sub- and ku super-diagonals has
+kl + ku + 1 colours whatever its size, instead of one per column.
+
+The colouring is greedy in natural column order, which is optimal for banded patterns. The
+pattern must contain every entry that can be non-zero: entries missing from it are not
+evaluated, and their contributions would be attributed to other entries of the same row.
+*/
+
+use crate::float::Float;
+use crate::lin_alg::sparse::{CsrMatrix, SparsityPattern};
+use crate::multi_dual::MultiDual;
+
+// Colours evaluated per pass of f. Patterns with more colours take one pass per LANES colours.
+pub const LANES: usize = 8;
+
+// Vector function f: R^n -> R^m that can be evaluated in any Float type
+pub trait VectorFunction {
+    fn eval<F: Float>(&self, x: &[F]) -> Vec<F>;
+}
+
+// Colour of every column such that columns sharing a row have different colours. Colours are
+// numbered from 0 and the number of colours is one more than the largest.
+pub fn color_columns(pattern: &SparsityPattern) -> Vec<usize> {
+    let (rows, cols) = pattern.get_dim();
+    let mut col_rows: Vec<Vec<usize>> = vec![Vec::new(); cols];
+    for i in 0..rows {
+        for &j in pattern.row(i) {
+            col_rows[j].push(i);
+        }
+    }
+
+    const UNCOLORED: usize = usize::MAX;
+    let mut colors: Vec<usize> = vec![UNCOLORED; cols];
+    // forbidden[c] == j marks colour c as taken by a neighbour of column j
+    let mut forbidden: Vec<usize> = Vec::new();
+    for j in 0..cols {
+        for &i in &col_rows[j] {
+            for &k in pattern.row(i) {
+                if colors[k] != UNCOLORED {
+                    forbidden[colors[k]] = j;
+                }
+            }
+        }
+        let color: usize = (0..forbidden.len())
+            .find(|&c| forbidden[c] != j)
+            .unwrap_or(forbidden.len());
+        if color == forbidden.len() {
+            forbidden.push(UNCOLORED);
+        }
+        colors[j] = color;
+    }
+    return colors;
+}
+
+// Jacobian of f at x restricted to the pattern, one MultiDual pass per LANES colours
+pub fn sparse_jacobian<V: VectorFunction>(
+    f: &V,
+    x: &[f64],
+    pattern: &SparsityPattern,
+) -> CsrMatrix {
+    let (rows, cols) = pattern.get_dim();
+    assert_eq!(
+        x.len(),
+        cols,
+        "Pattern has {} columns for {} variables",
+        cols,
+        x.len()
+    );
+    let colors: Vec<usize> = color_columns(pattern);
+    let n_colors: usize = colors.iter().map(|&c| c + 1).max().unwrap_or(0);
+
+    let mut J: CsrMatrix = CsrMatrix::new(pattern.clone());
+    let mut point: Vec<MultiDual<f64, LANES>> =
+        x.iter().map(|&xi| MultiDual::constant(xi)).collect();
+    for first in (0..n_colors).step_by(LANES) {
+        // Seed directions: tangent k is the sum of the unit vectors of all columns with colour
+        // first + k
+        for j in 0..cols {
+            point[j].df = [0.0; LANES];
+            if (first..first + LANES).contains(&colors[j]) {
+                point[j].df[colors[j] - first] = 1.0;
+            }
+        }
+        let compressed: Vec<MultiDual<f64, LANES>> = f.eval(&point);
+        assert_eq!(
+            compressed.len(),
+            rows,
+            "Function returned {} outputs for a pattern with {} rows",
+            compressed.len(),
+            rows
+        );
+        for i in 0..rows {
+            for &j in pattern.row(i) {
+                if (first..first + LANES).contains(&colors[j]) {
+                    J.set(i, j, compressed[i].df[colors[j] - first]);
+                }
+            }
+        }
+    }
+    return J;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto_diff::DiffNum;
+    use crate::lin_alg::mat::Matrix;
+    use std::cell::Cell;
+
+    // Discretised Bratu problem: r_i = x_{i-1} - 2 x_i + x_{i+1} + lambda exp(x_i)
+    struct Bratu {
+        lambda: f64,
+    }
+
+    impl VectorFunction for Bratu {
+        fn eval<F: Float>(&self, x: &[F]) -> Vec<F> {
+            let n: usize = x.len();
+            return (0..n)
+                .map(|i| {
+                    let left: F = if i > 0 { x[i - 1] } else { F::zero() };
+                    let right: F = if i + 1 < n { x[i + 1] } else { F::zero() };
+                    left - F::from_f64(2.0) * x[i] + right + F::from_f64(self.lambda) * x[i].exp()
+                })
+                .collect();
+        }
+    }
+
+    // Dense Jacobian, one DiffNum pass per column
+    fn dense_jacobian<V: VectorFunction>(f: &V, x: &[f64]) -> Matrix<f64> {
+        let mut point: Vec<DiffNum<f64>> = x.iter().map(|&xi| DiffNum { f: xi, df: 0.0 }).collect();
+        let m: usize = f.eval(x).len();
+        let mut J: Matrix<f64> = Matrix::<f64>::new((m, x.len()));
+        for j in 0..x.len() {
+            point[j].df = 1.0;
+            for (i, r) in f.eval(&point).iter().enumerate() {
+                J.set(i, j, r.df);
+            }
+            point[j].df = 0.0;
+        }
+        return J;
+    }
+
+    fn is_valid_coloring(pattern: &SparsityPattern, colors: &[usize]) -> bool {
+        for i in 0..pattern.get_dim().0 {
+            let row: &[usize] = pattern.row(i);
+            for a in 0..row.len() {
+                for b in a + 1..row.len() {
+                    if colors[row[a]] == colors[row[b]] {
+                        return false;
+                    }
+                }
+            }
+        }
+        return true;
+    }
+
+    #[test]
+    fn test_color_columns() {
+        let band: SparsityPattern = SparsityPattern::banded((50, 50), 2, 1);
+        let colors: Vec<usize> = color_columns(&band);
+        assert!(is_valid_coloring(&band, &colors));
+        assert_eq!(colors.iter().max(), Some(&3));
+
+        // Arrowhead: a dense first row forces every column to its own colour
+        let mut entries: Vec<(usize, usize)> = (0..6).map(|j| (0, j)).collect();
+        entries.extend((1..6).map(|i| (i, i)));
+        let arrow: SparsityPattern = SparsityPattern::from_entries((6, 6), &entries);
+        let colors: Vec<usize> = color_columns(&arrow);
+        assert!(is_valid_coloring(&arrow, &colors));
+        assert_eq!(colors, vec![0, 1, 2, 3, 4, 5]);
+
+        // Diagonal: one colour suffices
+        assert_eq!(
+            color_columns(&SparsityPattern::banded((4, 4), 0, 0)),
+            vec![0; 4]
+        );
+    }
+
+    #[test]
+    fn test_sparse_jacobian_banded() {
+        let n: usize = 200;
+        let x: Vec<f64> = (0..n).map(|i| (i as f64 * 0.1).sin()).collect();
+        let f: Bratu = Bratu { lambda: 0.8 };
+        let pattern: SparsityPattern = SparsityPattern::banded((n, n), 1, 1);
+
+        let J: CsrMatrix = sparse_jacobian(&f, &x, &pattern);
+        assert_eq!(J.nnz(), 3 * n - 2);
+        crate::assert_matrix_approx_eq!(J.to_dense(), dense_jacobian(&f, &x));
+        assert!((J.get(5, 5) - (-2.0 + 0.8 * x[5].exp())).abs() < 1e-15);
+        assert_eq!(J.get(5, 6), 1.0);
+    }
+
+    // Rectangular function with an irregular pattern: r = (x0 x3, sin x1 + x2^2, x4 / x0, x2)
+    struct Irregular;
+
+    impl VectorFunction for Irregular {
+        fn eval<F: Float>(&self, x: &[F]) -> Vec<F> {
+            return vec![x[0] * x[3], x[1].sin() + x[2] * x[2], x[4] / x[0], x[2]];
+        }
+    }
+
+    #[test]
+    fn test_sparse_jacobian_irregular() {
+        let x: [f64; 5] = [1.5, 0.3, -2.0, 0.7, 4.0];
+        let pattern: SparsityPattern = SparsityPattern::from_entries(
+            (4, 5),
+            &[(0, 0), (0, 3), (1, 1), (1, 2), (2, 0), (2, 4), (3, 2)],
+        );
+        let colors: Vec<usize> = color_columns(&pattern);
+        assert!(is_valid_coloring(&pattern, &colors));
+        assert_eq!(colors.iter().max(), Some(&1));
+
+        let J: CsrMatrix = sparse_jacobian(&Irregular, &x, &pattern);
+        crate::assert_matrix_approx_eq!(J.to_dense(), dense_jacobian(&Irregular, &x));
+    }
+
+    // Counts the evaluations of the wrapped function
+    struct Counted<V> {
+        f: V,
+        calls: Cell<usize>,
+    }
+
+    impl<V: VectorFunction> VectorFunction for Counted<V> {
+        fn eval<F: Float>(&self, x: &[F]) -> Vec<F> {
+            self.calls.set(self.calls.get() + 1);
+            return self.f.eval(x);
+        }
+    }
+
+    // r_0 = sum of squares, r_i = x_0 x_i: the dense first row needs one colour per column
+    struct Arrowhead;
+
+    impl VectorFunction for Arrowhead {
+        fn eval<F: Float>(&self, x: &[F]) -> Vec<F> {
+            let mut r: Vec<F> = vec![x.iter().fold(F::zero(), |acc, &xi| acc + xi * xi)];
+            r.extend(x[1..].iter().map(|&xi| x[0] * xi));
+            return r;
+        }
+    }
+
+    #[test]
+    fn test_colours_share_passes() {
+        // The three colours of a tridiagonal Jacobian take a single evaluation
+        let n: usize = 50;
+        let x: Vec<f64> = (0..n).map(|i| (i as f64 * 0.3).cos()).collect();
+        let bratu: Counted<Bratu> = Counted {
+            f: Bratu { lambda: 1.5 },
+            calls: Cell::new(0),
+        };
+        let J: CsrMatrix = sparse_jacobian(&bratu, &x, &SparsityPattern::banded((n, n), 1, 1));
+        assert_eq!(bratu.calls.get(), 1);
+        crate::assert_matrix_approx_eq!(J.to_dense(), dense_jacobian(&bratu.f, &x));
+
+        // Twenty colours take ceil(20 / LANES) evaluations
+        let n: usize = 20;
+        let x: Vec<f64> = (0..n).map(|i| i as f64 - 4.5).collect();
+        let mut entries: Vec<(usize, usize)> = (0..n).map(|j| (0, j)).collect();
+        entries.extend((1..n).flat_map(|i| [(i, 0), (i, i)]));
+        let arrow: Counted<Arrowhead> = Counted {
+            f: Arrowhead,
+            calls: Cell::new(0),
+        };
+        let J: CsrMatrix =
+            sparse_jacobian(&arrow, &x, &SparsityPattern::from_entries((n, n), &entries));
+        assert_eq!(arrow.calls.get(), n.div_ceil(LANES));
+        crate::assert_matrix_approx_eq!(J.to_dense(), dense_jacobian(&Arrowhead, &x));
+    }
+}
@@ -0,0 +1,828 @@
+/*
+Truncated Taylor polynomials for arbitrary order forward mode differentiation.
+
+A Taylor<T, K> holds the coefficients c_0..c_K of x(t) = sum_k c_k t^k, where c_k is the k-th
+derivative divided by k!. Seeding a variable as x0 + t and evaluating a function on it yields
+all derivatives up to order K in one pass. Products and quotients use Cauchy convolutions and
+the elementary functions use the standard recurrences (Griewank and Walther, "Evaluating
+Derivatives", chapter 13), most of them by integrating f'(x(t)) x'(t) term by term.
+
+Taylor implements Float, so generic code and nested types such as Taylor<DiffNum<f64>, K> work.
+*/
+
+use crate::custom_rule::CustomRule;
+use crate::float::Float;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Taylor<T, const K: usize> {
+    // c_0 is stored on its own, since stable Rust has no [T; K + 1]
+    value: T,
+    coeffs: [T; K],
+}
+
+impl<T: Float, const K: usize> Taylor<T, K> {
+    pub fn constant(value: T) -> Self {
+        return Self {
+            value,
+            coeffs: [T::zero(); K],
+        };
+    }
+
+    // The independent variable x0 + t
+    pub fn variable(x0: T) -> Self {
+        let mut result: Self = Self::constant(x0);
+        if K > 0 {
+            result.coeffs[0] = T::one();
+        }
+        return result;
+    }
+
+    // `coeffs[k - 1]` is the coefficient of t^k
+    pub fn from_coeffs(value: T, coeffs: [T; K]) -> Self {
+        return Self { value, coeffs };
+    }
+
+    pub fn value(&self) -> T {
+        return self.value;
+    }
+
+    // Coefficient c_k for k in 0..=K
+    pub fn coeff(&self, k: usize) -> T {
+        return if k == 0 {
+            self.value
+        } else {
+            self.coeffs[k - 1]
+        };
+    }
+
+    fn set_coeff(&mut self, k: usize, val: T) {
+        if k == 0 {
+            self.value = val;
+        } else {
+            self.coeffs[k - 1] = val;
+        }
+    }
+
+    // k-th derivative of the represented function, k! c_k
+    pub fn derivative(&self, k: usize) -> T {
+        let mut factorial: f64 = 1.0;
+        for i in 2..=k {
+            factorial *= i as f64;
+        }
+        return self.coeff(k) * T::from_f64(factorial);
+    }
+
+    // Series of x'(t). Its top coefficient would need c_(K + 1) and is left at zero, which
+    // integrate never reads.
+    fn shift_derivative(&self) -> Self {
+        let mut result: Self = Self::constant(T::zero());
+        for k in 0..K {
+            result.set_coeff(k, self.coeff(k + 1) * T::from_f64((k + 1) as f64));
+        }
+        return result;
+    }
+
+    // Antiderivative of d with constant term c0
+    fn integrate(c0: T, d: &Self) -> Self {
+        let mut result: Self = Self::constant(c0);
+        for k in 1..=K {
+            result.set_coeff(k, d.coeff(k - 1) / T::from_f64(k as f64));
+        }
+        return result;
+    }
+
+    // f(x(t)) from f(x_0) and the series of f'(x(t))
+    fn chain(self, f0: T, df: Self) -> Self {
+        return Self::integrate(f0, &(df * self.shift_derivative()));
+    }
+
+    // Coefficient sum_{j=1}^k j a_j b_(k - j) / k, the building block of the ODE recurrences
+    fn weighted_sum(a: &Self, b: &Self, k: usize) -> T {
+        let mut sum: T = T::zero();
+        for j in 1..=k {
+            sum += T::from_f64(j as f64) * a.coeff(j) * b.coeff(k - j);
+        }
+        return sum / T::from_f64(k as f64);
+    }
+
+    pub fn powi(self, n: i32) -> Self {
+        let mut base: Self = self;
+        let mut exp: u32 = n.unsigned_abs();
+        let mut result: Self = Self::constant(T::one());
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result *= base;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base *= base;
+            }
+        }
+        return if n < 0 { result.recip() } else { result };
+    }
+
+    // x^r for a constant r, from x p' = r x' p. Needs x_0 != 0 unless r is a whole number.
+    pub fn powf(self, r: T) -> Self {
+        if self.value == T::zero() && r >= T::zero() && r.to_f64().fract() == 0.0 {
+            return self.powi(r.to_f64() as i32);
+        }
+        return self.power_series(r, self.value.powf(r));
+    }
+
+    fn power_series(self, r: T, p0: T) -> Self {
+        let mut p: Self = Self::constant(p0);
+        for k in 1..=K {
+            let mut sum: T = T::zero();
+            for j in 1..=k {
+                let weight: T = r * T::from_f64(j as f64) - T::from_f64((k - j) as f64);
+                sum += weight * self.coeff(j) * p.coeff(k - j);
+            }
+            p.set_coeff(k, sum / (T::from_f64(k as f64) * self.value));
+        }
+        return p;
+    }
+
+    pub fn sqrt(self) -> Self {
+        let mut s: Self = Self::constant(self.value.sqrt());
+        for k in 1..=K {
+            let mut sum: T = self.coeff(k);
+            for j in 1..k {
+                sum -= s.coeff(j) * s.coeff(k - j);
+            }
+            s.set_coeff(k, sum / (T::from_f64(2.0) * s.value));
+        }
+        return s;
+    }
+
+    pub fn cbrt(self) -> Self {
+        return self.power_series(T::from_f64(1.0 / 3.0), self.value.cbrt());
+    }
+
+    pub fn recip(self) -> Self {
+        return Self::constant(T::one()) / self;
+    }
+
+    // x^y with a differentiable exponent
+    pub fn pow(self, other: Self) -> Self {
+        if other.coeffs.iter().all(|c| *c == T::zero()) {
+            return self.powf(other.value);
+        }
+        let mut result: Self = (other * self.ln()).exp();
+        result.value = self.value.powf(other.value);
+        return result;
+    }
+
+    pub fn exp(self) -> Self {
+        let mut e: Self = Self::constant(self.value.exp());
+        for k in 1..=K {
+            let c: T = Self::weighted_sum(&self, &e, k);
+            e.set_coeff(k, c);
+        }
+        return e;
+    }
+
+    pub fn exp2(self) -> Self {
+        let mut result: Self = (self * T::from_f64(std::f64::consts::LN_2)).exp();
+        result.value = self.value.exp2();
+        return result;
+    }
+
+    pub fn exp_m1(self) -> Self {
+        let mut result: Self = self.exp();
+        result.value = self.value.exp_m1();
+        return result;
+    }
+
+    pub fn ln(self) -> Self {
+        return self.chain(self.value.ln(), self.recip());
+    }
+
+    pub fn ln_1p(self) -> Self {
+        return self.chain(self.value.ln_1p(), (self + T::one()).recip());
+    }
+
+    pub fn log2(self) -> Self {
+        let mut result: Self = self.ln() / T::from_f64(std::f64::consts::LN_2);
+        result.value = self.value.log2();
+        return result;
+    }
+
+    pub fn log10(self) -> Self {
+        let mut result: Self = self.ln() / T::from_f64(std::f64::consts::LN_10);
+        result.value = self.value.log10();
+        return result;
+    }
+
+    pub fn log(self, base: T) -> Self {
+        let mut result: Self = self.ln() / base.ln();
+        result.value = self.value.log(base);
+        return result;
+    }
+
+    pub fn sin_cos(self) -> (Self, Self) {
+        let (sin0, cos0) = self.value.sin_cos();
+        let mut s: Self = Self::constant(sin0);
+        let mut c: Self = Self::constant(cos0);
+        for k in 1..=K {
+            let s_k: T = Self::weighted_sum(&self, &c, k);
+            let c_k: T = -Self::weighted_sum(&self, &s, k);
+            s.set_coeff(k, s_k);
+            c.set_coeff(k, c_k);
+        }
+        return (s, c);
+    }
+
+    pub fn sin(self) -> Self {
+        return self.sin_cos().0;
+    }
+
+    pub fn cos(self) -> Self {
+        return self.sin_cos().1;
+    }
+
+    // tan' = 1 + tan^2, with u = 1 + tan^2 built alongside
+    pub fn tan(self) -> Self {
+        return self.tan_like(self.value.tan(), T::one());
+    }
+
+    fn tan_like(self, t0: T, sign: T) -> Self {
+        let mut t: Self = Self::constant(t0);
+        let mut u: Self = Self::constant(T::one() + sign * t0 * t0);
+        for k in 1..=K {
+            let t_k: T = Self::weighted_sum(&self, &u, k);
+            t.set_coeff(k, t_k);
+            let mut square: T = T::zero();
+            for j in 0..=k {
+                square += t.coeff(j) * t.coeff(k - j);
+            }
+            u.set_coeff(k, sign * square);
+        }
+        return t;
+    }
+
+    pub fn asin(self) -> Self {
+        let df: Self = (-(self * self) + T::one()).sqrt().recip();
+        return self.chain(self.value.asin(), df);
+    }
+
+    pub fn acos(self) -> Self {
+        let df: Self = -(-(self * self) + T::one()).sqrt().recip();
+        return self.chain(self.value.acos(), df);
+    }
+
+    pub fn atan(self) -> Self {
+        return self.chain(self.value.atan(), (self * self + T::one()).recip());
+    }
+
+    // Angle of the point (other, self), as f64::atan2 with self as y
+    pub fn atan2(self, other: Self) -> Self {
+        let num: Self = other * self.shift_derivative() - self * other.shift_derivative();
+        let den: Self = self * self + other * other;
+        return Self::integrate(self.value.atan2(other.value), &(num / den));
+    }
+
+    pub fn sinh_cosh(self) -> (Self, Self) {
+        let mut s: Self = Self::constant(self.value.sinh());
+        let mut c: Self = Self::constant(self.value.cosh());
+        for k in 1..=K {
+            let s_k: T = Self::weighted_sum(&self, &c, k);
+            let c_k: T = Self::weighted_sum(&self, &s, k);
+            s.set_coeff(k, s_k);
+            c.set_coeff(k, c_k);
+        }
+        return (s, c);
+    }
+
+    pub fn sinh(self) -> Self {
+        return self.sinh_cosh().0;
+    }
+
+    pub fn cosh(self) -> Self {
+        return self.sinh_cosh().1;
+    }
+
+    // tanh' = 1 - tanh^2
+    pub fn tanh(self) -> Self {
+        return self.tan_like(self.value.tanh(), -T::one());
+    }
+
+    pub fn asinh(self) -> Self {
+        let df: Self = (self * self + T::one()).sqrt().recip();
+        return self.chain(self.value.asinh(), df);
+    }
+
+    pub fn acosh(self) -> Self {
+        let df: Self = (self * self - T::one()).sqrt().recip();
+        return self.chain(self.value.acosh(), df);
+    }
+
+    pub fn atanh(self) -> Self {
+        return self.chain(self.value.atanh(), (-(self * self) + T::one()).recip());
+    }
+
+    // At hypot's kink in the origin the higher coefficients are taken as zero
+    pub fn hypot(self, other: Self) -> Self {
+        if self.value == T::zero() && other.value == T::zero() {
+            return Self::constant(T::zero());
+        }
+        let mut result: Self = (self * self + other * other).sqrt();
+        result.value = self.value.hypot(other.value);
+        return result;
+    }
+
+    pub fn mul_add(self, a: Self, b: Self) -> Self {
+        let mut result: Self = self * a + b;
+        result.value = self.value.mul_add(a.value, b.value);
+        return result;
+    }
+
+    // Same subgradient conventions as DiffNum: abs is flat at 0 and min and max average their
+    // arguments at a tie
+    pub fn abs(self) -> Self {
+        if self.value > T::zero() {
+            return self;
+        }
+        if self.value < T::zero() {
+            return -self;
+        }
+        return Self::constant(self.value.abs());
+    }
+
+    pub fn signum(self) -> Self {
+        return Self::constant(self.value.signum());
+    }
+
+    pub fn min(self, other: Self) -> Self {
+        if self.value < other.value {
+            return self;
+        }
+        if other.value < self.value {
+            return other;
+        }
+        return (self + other) * T::from_f64(0.5);
+    }
+
+    pub fn max(self, other: Self) -> Self {
+        if self.value > other.value {
+            return self;
+        }
+        if other.value > self.value {
+            return other;
+        }
+        return (self + other) * T::from_f64(0.5);
+    }
+}
+
+// Arithmetic
+impl<T: Float, const K: usize> std::ops::Add for Taylor<T, K> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let mut result: Self = self;
+        result += other;
+        return result;
+    }
+}
+
+impl<T: Float, const K: usize> std::ops::Sub for Taylor<T, K> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        let mut result: Self = self;
+        result -= other;
+        return result;
+    }
+}
+
+impl<T: Float, const K: usize> std::ops::Mul for Taylor<T, K> {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let mut result: Self = Self::constant(T::zero());
+        for k in 0..=K {
+            let mut sum: T = T::zero();
+            for j in 0..=k {
+                sum += self.coeff(j) * other.coeff(k - j);
+            }
+            result.set_coeff(k, sum);
+        }
+        return result;
+    }
+}
+
+impl<T: Float, const K: usize> std::ops::Div for Taylor<T, K> {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        let mut result: Self = Self::constant(T::zero());
+        for k in 0..=K {
+            let mut sum: T = self.coeff(k);
+            for j in 1..=k {
+                sum -= other.coeff(j) * result.coeff(k - j);
+            }
+            result.set_coeff(k, sum / other.value);
+        }
+        return result;
+    }
+}
+
+impl<T: Float, const K: usize> std::ops::Neg for Taylor<T, K> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        return Self {
+            value: -self.value,
+            coeffs: self.coeffs.map(|c| -c),
+        };
+    }
+}
+
+impl<T: Float, const K: usize> std::ops::AddAssign for Taylor<T, K> {
+    fn add_assign(&mut self, other: Self) {
+        self.value += other.value;
+        for (c, o) in self.coeffs.iter_mut().zip(other.coeffs) {
+            *c += o;
+        }
+    }
+}
+
+impl<T: Float, const K: usize> std::ops::SubAssign for Taylor<T, K> {
+    fn sub_assign(&mut self, other: Self) {
+        self.value -= other.value;
+        for (c, o) in self.coeffs.iter_mut().zip(other.coeffs) {
+            *c -= o;
+        }
+    }
+}
+
+impl<T: Float, const K: usize> std::ops::MulAssign for Taylor<T, K> {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+impl<T: Float, const K: usize> std::ops::DivAssign for Taylor<T, K> {
+    fn div_assign(&mut self, other: Self) {
+        *self = *self / other;
+    }
+}
+
+// Operations with scalars on the right
+impl<T: Float, const K: usize> std::ops::Add<T> for Taylor<T, K> {
+    type Output = Self;
+
+    fn add(self, other: T) -> Self {
+        let mut result: Self = self;
+        result.value += other;
+        return result;
+    }
+}
+
+impl<T: Float, const K: usize> std::ops::Sub<T> for Taylor<T, K> {
+    type Output = Self;
+
+    fn sub(self, other: T) -> Self {
+        let mut result: Self = self;
+        result.value -= other;
+        return result;
+    }
+}
+
+impl<T: Float, const K: usize> std::ops::Mul<T> for Taylor<T, K> {
+    type Output = Self;
+
+    fn mul(self, other: T) -> Self {
+        return Self {
+            value: self.value * other,
+            coeffs: self.coeffs.map(|c| c * other),
+        };
+    }
+}
+
+impl<T: Float, const K: usize> std::ops::Div<T> for Taylor<T, K> {
+    type Output = Self;
+
+    fn div(self, other: T) -> Self {
+        return Self {
+            value: self.value / other,
+            coeffs: self.coeffs.map(|c| c / other),
+        };
+    }
+}
+
+// Operations with f64 scalars on the left
+impl<T: Float, const K: usize> std::ops::Add<Taylor<T, K>> for f64 {
+    type Output = Taylor<T, K>;
+
+    fn add(self, other: Taylor<T, K>) -> Taylor<T, K> {
+        return other + T::from_f64(self);
+    }
+}
+
+impl<T: Float, const K: usize> std::ops::Sub<Taylor<T, K>> for f64 {
+    type Output = Taylor<T, K>;
+
+    fn sub(self, other: Taylor<T, K>) -> Taylor<T, K> {
+        return -other + T::from_f64(self);
+    }
+}
+
+impl<T: Float, const K: usize> std::ops::Mul<Taylor<T, K>> for f64 {
+    type Output = Taylor<T, K>;
+
+    fn mul(self, other: Taylor<T, K>) -> Taylor<T, K> {
+        return other * T::from_f64(self);
+    }
+}
+
+impl<T: Float, const K: usize> std::ops::Div<Taylor<T, K>> for f64 {
+    type Output = Taylor<T, K>;
+
+    fn div(self, other: Taylor<T, K>) -> Taylor<T, K> {
+        return Taylor::constant(T::from_f64(self)) / other;
+    }
+}
+
+// Ordering compares values only, as for DiffNum
+impl<T: Float, const K: usize> PartialOrd for Taylor<T, K> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        return self.value.partial_cmp(&other.value);
+    }
+}
+
+impl<T: Float, const K: usize> Float for Taylor<T, K> {
+    fn from_f64(value: f64) -> Self {
+        return Self::constant(T::from_f64(value));
+    }
+
+    fn to_f64(self) -> f64 {
+        return self.value.to_f64();
+    }
+
+    fn powi(self, n: i32) -> Self {
+        return Taylor::powi(self, n);
+    }
+
+    fn powf(self, n: Self) -> Self {
+        return self.pow(n);
+    }
+
+    fn log(self, base: Self) -> Self {
+        return self.ln() / base.ln();
+    }
+
+    // Picard iteration y <- y_0 + int J(x(t)) x'(t) dt, where J may depend on y. Each pass fixes
+    // one more coefficient.
+    fn apply_rule<R: CustomRule>(rule: &R, x: &[Self]) -> Vec<Self> {
+        let values: Vec<T> = x.iter().map(|xi| xi.value).collect();
+        let rates: Vec<Self> = x.iter().map(|xi| xi.shift_derivative()).collect();
+        let y0: Vec<T> = T::apply_rule(rule, &values);
+        let mut y: Vec<Self> = y0.iter().map(|&c0| Self::constant(c0)).collect();
+        for _ in 0..K {
+            let dy: Vec<Self> = rule.jvp(x, &y, &rates);
+            y = y0
+                .iter()
+                .zip(&dy)
+                .map(|(&c0, d)| Self::integrate(c0, d))
+                .collect();
+        }
+        return y;
+    }
+
+    fn sin_cos(self) -> (Self, Self) {
+        return Taylor::sin_cos(self);
+    }
+
+    fn mul_add(self, a: Self, b: Self) -> Self {
+        return Taylor::mul_add(self, a, b);
+    }
+
+    fn sqrt(self) -> Self {
+        return Taylor::sqrt(self);
+    }
+
+    fn cbrt(self) -> Self {
+        return Taylor::cbrt(self);
+    }
+
+    fn recip(self) -> Self {
+        return Taylor::recip(self);
+    }
+
+    fn exp(self) -> Self {
+        return Taylor::exp(self);
+    }
+
+    fn exp2(self) -> Self {
+        return Taylor::exp2(self);
+    }
+
+    fn exp_m1(self) -> Self {
+        return Taylor::exp_m1(self);
+    }
+
+    fn ln(self) -> Self {
+        return Taylor::ln(self);
+    }
+
+    fn ln_1p(self) -> Self {
+        return Taylor::ln_1p(self);
+    }
+
+    fn log2(self) -> Self {
+        return Taylor::log2(self);
+    }
+
+    fn log10(self) -> Self {
+        return Taylor::log10(self);
+    }
+
+    fn sin(self) -> Self {
+        return Taylor::sin(self);
+    }
+
+    fn cos(self) -> Self {
+        return Taylor::cos(self);
+    }
+
+    fn tan(self) -> Self {
+        return Taylor::tan(self);
+    }
+
+    fn asin(self) -> Self {
+        return Taylor::asin(self);
+    }
+
+    fn acos(self) -> Self {
+        return Taylor::acos(self);
+    }
+
+    fn atan(self) -> Self {
+        return Taylor::atan(self);
+    }
+
+    fn atan2(self, other: Self) -> Self {
+        return Taylor::atan2(self, other);
+    }
+
+    fn sinh(self) -> Self {
+        return Taylor::sinh(self);
+    }
+
+    fn cosh(self) -> Self {
+        return Taylor::cosh(self);
+    }
+
+    fn tanh(self) -> Self {
+        return Taylor::tanh(self);
+    }
+
+    fn asinh(self) -> Self {
+        return Taylor::asinh(self);
+    }
+
+    fn acosh(self) -> Self {
+        return Taylor::acosh(self);
+    }
+
+    fn atanh(self) -> Self {
+        return Taylor::atanh(self);
+    }
+
+    fn abs(self) -> Self {
+        return Taylor::abs(self);
+    }
+
+    fn signum(self) -> Self {
+        return Taylor::signum(self);
+    }
+
+    fn hypot(self, other: Self) -> Self {
+        return Taylor::hypot(self, other);
+    }
+
+    fn min(self, other: Self) -> Self {
+        return Taylor::min(self, other);
+    }
+
+    fn max(self, other: Self) -> Self {
+        return Taylor::max(self, other);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto_diff::DiffNum;
+
+    type T6 = Taylor<f64, 6>;
+
+    fn assert_coeffs(x: T6, expected: [f64; 7]) {
+        for (k, e) in expected.iter().enumerate() {
+            assert!(
+                (x.coeff(k) - e).abs() < 1e-12,
+                "coefficient {}: {} != {}",
+                k,
+                x.coeff(k),
+                e
+            );
+        }
+    }
+
+    #[test]
+    fn test_maclaurin_series() {
+        let t: T6 = Taylor::variable(0.0);
+        let f: [f64; 7] = [1.0, 1.0, 2.0, 6.0, 24.0, 120.0, 720.0];
+        assert_coeffs(t.exp(), f.map(|k| 1.0 / k));
+        assert_coeffs(t.sin(), [0.0, 1.0, 0.0, -1.0 / 6.0, 0.0, 1.0 / 120.0, 0.0]);
+        assert_coeffs(
+            t.cos(),
+            [1.0, 0.0, -0.5, 0.0, 1.0 / 24.0, 0.0, -1.0 / 720.0],
+        );
+        assert_coeffs(t.tan(), [0.0, 1.0, 0.0, 1.0 / 3.0, 0.0, 2.0 / 15.0, 0.0]);
+        assert_coeffs(t.atan(), [0.0, 1.0, 0.0, -1.0 / 3.0, 0.0, 1.0 / 5.0, 0.0]);
+        assert_coeffs(
+            t.ln_1p(),
+            [0.0, 1.0, -0.5, 1.0 / 3.0, -0.25, 0.2, -1.0 / 6.0],
+        );
+        assert_coeffs((1.0 - t).recip(), [1.0; 7]);
+        // Binomial series of sqrt(1 + t)
+        assert_coeffs(
+            (t + 1.0).sqrt(),
+            [
+                1.0,
+                0.5,
+                -0.125,
+                0.0625,
+                -0.0390625,
+                0.02734375,
+                -0.0205078125,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_derivatives_of_polynomial() {
+        let x: T6 = Taylor::variable(2.0);
+        let y: T6 = x.powi(5);
+        let expected: [f64; 7] = [32.0, 80.0, 160.0, 240.0, 240.0, 120.0, 0.0];
+        for (k, e) in expected.iter().enumerate() {
+            assert!((y.derivative(k) - e).abs() < 1e-10);
+        }
+        assert_eq!(Taylor::<f64, 3>::variable(0.0).powf(2.0).coeff(2), 1.0);
+    }
+
+    #[test]
+    fn test_identities_away_from_zero() {
+        let x: T6 = Taylor::variable(0.4);
+        let (s, c) = x.sin_cos();
+        assert_coeffs(s * s + c * c, [1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+
+        let identity: [f64; 7] = [0.4, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+        assert_coeffs(x.exp().ln(), identity);
+        assert_coeffs(x.sin().asin(), identity);
+        assert_coeffs(x.cos().acos(), identity);
+        assert_coeffs(x.tanh().atanh(), identity);
+        assert_coeffs(x.sinh().asinh(), identity);
+        assert_coeffs(x.cosh().acosh(), identity);
+        assert_coeffs(x.cbrt().powi(3), identity);
+        assert_coeffs(x.exp2().log2(), identity);
+        assert_coeffs(x.exp_m1().ln_1p(), identity);
+        assert_coeffs(x.log10() - x.log(10.0), [0.0; 7]);
+        assert_coeffs(x.log10() * 10.0_f64.ln() - x.ln(), [0.0; 7]);
+        assert_coeffs(x.tan() * x.cos() - x.sin(), [0.0; 7]);
+        assert_coeffs(x.atan2(Taylor::constant(1.0)) - x.atan(), [0.0; 7]);
+        assert_coeffs(x.hypot(Taylor::constant(0.0)), identity);
+        assert_coeffs(x.pow(x) - (x * x.ln()).exp(), [0.0; 7]);
+        assert_coeffs(
+            x.powf(2.5) / (x * x * x.sqrt()),
+            [1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        );
+    }
+
+    // Written once against Float, so both Taylor and nested DiffNum can evaluate it
+    fn f<F: Float>(x: F) -> F {
+        return (x.sin() * x.exp()).atan() / (F::one() + x * x).sqrt() + x.tanh().powi(3);
+    }
+
+    #[test]
+    fn test_matches_nested_diffnum() {
+        let x0: f64 = 0.8;
+        let y: Taylor<f64, 2> = f(Taylor::variable(x0));
+        let z: DiffNum<DiffNum<f64>> = f(DiffNum {
+            f: DiffNum { f: x0, df: 1.0 },
+            df: DiffNum { f: 1.0, df: 0.0 },
+        });
+        assert!((y.derivative(0) - z.f.f).abs() < 1e-14);
+        assert!((y.derivative(1) - z.f.df).abs() < 1e-13);
+        assert!((y.derivative(2) - z.df.df).abs() < 1e-12);
+
+        // Taylor over dual numbers carries the sensitivity of every coefficient to x0
+        let w: Taylor<DiffNum<f64>, 2> = f(Taylor::variable(DiffNum { f: x0, df: 1.0 }));
+        let h: f64 = 1e-6;
+        let up: Taylor<f64, 2> = f(Taylor::variable(x0 + h));
+        let down: Taylor<f64, 2> = f(Taylor::variable(x0 - h));
+        let fd: f64 = (up.derivative(2) - down.derivative(2)) / (2.0 * h);
+        assert!((w.derivative(2).df - fd).abs() < 1e-6);
+    }
+}